@@ -0,0 +1,117 @@
+//! Allocations and throughput of decoding with and without a
+//! `BufferPool`
+//!
+//! ```sh
+//! cargo bench --bench pool
+//! ```
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{BufferPool, Decoder, DecoderOptions};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// System allocator counting allocations and allocated bytes
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const ITERATIONS: u32 = 2000;
+
+/// Decode `data` as a texture streaming system would: every frame is
+/// "uploaded" (summed), then handed back to the pool if there is one
+async fn decode(data: &[u8], pool: Option<&BufferPool>) -> u64 {
+    let options = DecoderOptions {
+        buffer_pool: pool.cloned(),
+        ..Default::default()
+    };
+    let (_, mut stream) = Decoder::with_options(data, options)
+        .read_async()
+        .await
+        .unwrap();
+    let mut sum = 0_u64;
+    while let Some((_, buf)) = stream.try_next().await.unwrap() {
+        sum += buf.iter().map(|&x| u64::from(x)).sum::<u64>();
+        if let Some(pool) = pool {
+            pool.put(buf);
+        }
+    }
+    sum
+}
+
+/// Run `ITERATIONS` decodings, returning the time, the allocations
+/// and the bytes allocated per file
+async fn run(data: &[u8], pool: Option<&BufferPool>) -> (Duration, u64, u64) {
+    // Warm up (and fill the pool)
+    decode(data, pool).await;
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut sum = 0;
+    for _ in 0..ITERATIONS {
+        sum += decode(data, pool).await;
+    }
+    let elapsed = start.elapsed();
+    assert!(sum > 0);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes;
+    let n = u64::from(ITERATIONS);
+    (elapsed, allocations / n, bytes / n)
+}
+
+fn report(name: &str, size: usize, (elapsed, allocations, bytes): (Duration, u64, u64)) {
+    let per_file = elapsed / ITERATIONS;
+    let throughput = (size as f64 * f64::from(ITERATIONS)) / elapsed.as_secs_f64() / 1e6;
+    println!(
+        "{:<12} {:>10.2?}/file {:>9.1} MB/s {:>6} allocations/file {:>9} bytes/file",
+        name, per_file, throughput, allocations, bytes
+    );
+}
+
+fn main() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+    let data = std::fs::read(&path).unwrap();
+    println!("{} ({} bytes)", path.display(), data.len());
+
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        report("no pool", data.len(), run(&data, None).await);
+        let pool = BufferPool::default();
+        report("BufferPool", data.len(), run(&data, Some(&pool)).await);
+    });
+}
@@ -80,68 +80,66 @@ async fn main() {
 
         match event {
             Event::WindowEvent { ref event, .. } => match event {
-                WindowEvent::Resized(logical_size) => {
-                    let dpi_factor = glctx.window().hidpi_factor();
-                    glctx.resize(logical_size.to_physical(dpi_factor));
+                WindowEvent::Resized(physical_size) => {
+                    glctx.resize(*physical_size);
+                }
+                WindowEvent::CloseRequested => {
+                    *control_flow = ControlFlow::Exit;
+                }
+                _ => (),
+            },
+            Event::RedrawRequested(_) => {
+                // Clear Render Target
+                unsafe {
+                    gl::Clear(gl::COLOR_BUFFER_BIT);
                 }
-                WindowEvent::RedrawRequested => {
-                    // Clear Render Target
-                    unsafe {
-                        gl::Clear(gl::COLOR_BUFFER_BIT);
-                    }
 
-                    // Bind Program
-                    unsafe {
-                        gl::UseProgram(program);
-                    }
+                // Bind Program
+                unsafe {
+                    gl::UseProgram(program);
+                }
 
-                    // Update Uniform
-                    unsafe {
-                        if tex_uniform >= 0 {
-                            gl::Uniform1i(tex_uniform, 0);
-                        }
+                // Update Uniform
+                unsafe {
+                    if tex_uniform >= 0 {
+                        gl::Uniform1i(tex_uniform, 0);
                     }
+                }
 
-                    // Bind Texture
-                    unsafe {
-                        gl::ActiveTexture(gl::TEXTURE0);
-                        gl::BindTexture(gl::TEXTURE_2D, texture);
-                    }
+                // Bind Texture
+                unsafe {
+                    gl::ActiveTexture(gl::TEXTURE0);
+                    gl::BindTexture(gl::TEXTURE_2D, texture);
+                }
 
-                    // Bind Vertex Attrib
-                    unsafe {
-                        gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
-                        if position_attrib >= 0 {
-                            let loc = position_attrib as GLuint;
-                            let off = std::mem::transmute(0_usize);
-                            gl::EnableVertexAttribArray(loc);
-                            gl::VertexAttribPointer(loc, 2, gl::FLOAT, gl::FALSE, 16, off);
-                        }
-                        if texcoord_attrib >= 0 {
-                            let loc = texcoord_attrib as GLuint;
-                            let off = std::mem::transmute(8_usize);
-                            gl::EnableVertexAttribArray(loc);
-                            gl::VertexAttribPointer(loc, 2, gl::FLOAT, gl::FALSE, 16, off);
-                        }
+                // Bind Vertex Attrib
+                unsafe {
+                    gl::BindBuffer(gl::ARRAY_BUFFER, vertex_buffer);
+                    if position_attrib >= 0 {
+                        let loc = position_attrib as GLuint;
+                        let off = std::mem::transmute(0_usize);
+                        gl::EnableVertexAttribArray(loc);
+                        gl::VertexAttribPointer(loc, 2, gl::FLOAT, gl::FALSE, 16, off);
                     }
-
-                    // Draw
-                    unsafe {
-                        gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+                    if texcoord_attrib >= 0 {
+                        let loc = texcoord_attrib as GLuint;
+                        let off = std::ptr::with_exposed_provenance::<std::ffi::c_void>(8_usize);
+                        gl::EnableVertexAttribArray(loc);
+                        gl::VertexAttribPointer(loc, 2, gl::FLOAT, gl::FALSE, 16, off);
                     }
+                }
 
-                    // Finalize and Present
-                    unsafe {
-                        gl::Flush();
-                    }
-                    glctx.swap_buffers().unwrap();
+                // Draw
+                unsafe {
+                    gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
                 }
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                    return;
+
+                // Finalize and Present
+                unsafe {
+                    gl::Flush();
                 }
-                _ => return,
-            },
+                glctx.swap_buffers().unwrap();
+            }
             Event::LoopDestroyed => {
                 // Release OpenGL resources
                 unsafe {
@@ -149,7 +147,6 @@ async fn main() {
                     gl::DeleteTextures(1, &texture);
                     gl::DeleteProgram(program);
                 }
-                return;
             }
             _ => (),
         }
@@ -285,7 +282,7 @@ lazy_static! {
     static ref PROJECT_DIR: std::path::PathBuf = {
         use std::env::var_os;
         var_os("CARGO_MANIFEST_DIR")
-            .map(|s| std::path::PathBuf::from(s))
+            .map(std::path::PathBuf::from)
             .unwrap()
     };
 }
@@ -0,0 +1,58 @@
+#![no_main]
+
+use futures_executor::block_on;
+use futures_util::stream::TryStreamExt as _;
+use ktx_async::{parse, Decoder, DecoderOptions, FrameOrder, Limits};
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+/// Limits well below the memory limit of libFuzzer, so that large
+/// sizes in the input fail with `LimitExceeded` instead of running out
+/// of memory
+fn limits() -> Limits {
+    Limits {
+        max_key_value_bytes: 1 << 20,
+        max_total_bytes: 16 << 20,
+        ..Default::default()
+    }
+}
+
+fn decoder(data: &[u8]) -> Decoder<&[u8]> {
+    let options = DecoderOptions {
+        limits: limits(),
+        ..Default::default()
+    };
+    Decoder::with_options(data, options)
+}
+
+// Full decode over an in-memory reader, in each of the reading modes,
+// and of the slice itself
+fuzz_target!(|data: &[u8]| {
+    if let Ok((_, frames)) = parse::read(data, &limits()) {
+        frames.for_each(drop);
+    }
+    if let Ok((_, frames)) = decoder(data).read() {
+        frames.for_each(drop);
+    }
+    block_on(async {
+        if let Ok((_, stream)) = decoder(data).read_async().await {
+            let _ = stream.try_for_each(|_| async { Ok(()) }).await;
+        }
+        if let Ok((_, stream)) = decoder(data).read_chunks_async(4096).await {
+            let _ = stream.try_for_each(|_| async { Ok(()) }).await;
+        }
+        if let Ok((_, stream)) = decoder(data).read_levels_async().await {
+            let _ = stream.try_for_each(|_| async { Ok(()) }).await;
+        }
+        let _ = decoder(data).read_all_async().await;
+        let options = DecoderOptions {
+            frame_order: FrameOrder::SmallestFirst,
+            limits: limits(),
+            ..Default::default()
+        };
+        let decoder = Decoder::with_options(Cursor::new(data), options);
+        if let Ok((_, stream)) = decoder.read_seekable_async().await {
+            let _ = stream.try_for_each(|_| async { Ok(()) }).await;
+        }
+    });
+});
@@ -0,0 +1,23 @@
+#![no_main]
+
+use ktx_async::{parse, Limits};
+use libfuzzer_sys::fuzz_target;
+use std::convert::TryFrom as _;
+
+// The 64 bytes of the header before the key/value data
+fuzz_target!(|data: &[u8]| {
+    let buf = match data.get(..64).map(<&[u8; 64]>::try_from) {
+        Some(Ok(x)) => x,
+        _ => return,
+    };
+    let limits = Limits::default();
+    for generate_mipmaps in [false, true].iter() {
+        if let Ok((header, _)) = parse::parse_header(buf, &limits, *generate_mipmaps) {
+            let _ = header.kind();
+            let _ = header.data_offset();
+            for level in 0..header.number_of_mipmap_levels {
+                let _ = header.mipmap_size(level);
+            }
+        }
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use ktx_async::{Entries, KeyValueData};
+use libfuzzer_sys::fuzz_target;
+
+// Key/value data on its own, as it follows the header
+fuzz_target!(|data: &[u8]| {
+    let mut copy = KeyValueData::default();
+    for (key, value) in Entries::new(data) {
+        assert!(Entries::new(data).any(|(k, _)| k == key));
+        copy.push(key, value);
+    }
+    for (key, value) in copy.iter() {
+        let _ = copy.get(key);
+        let _ = value.len();
+    }
+    let _ = format!("{:?}", copy);
+});
@@ -0,0 +1,16 @@
+#!/bin/sh
+# Fill the corpora of the fuzz targets from the test data.
+#
+# The header target gets the 64 bytes of the header of every file, the
+# key/value target its key/value data; the decode target gets whole
+# files, cut to 64 KiB.
+set -e
+cd "$(dirname "$0")"
+mkdir -p corpus/header corpus/key_value corpus/decode
+for file in ../data/khr/*.ktx ../data/pvr/*.ktx; do
+    name=$(basename "$(dirname "$file")")-$(basename "$file" .ktx)
+    kvbytes=$(od -An -tu4 -j60 -N4 "$file" | tr -d ' ')
+    head -c 64 "$file" > "corpus/header/$name"
+    tail -c +65 "$file" | head -c "$kvbytes" > "corpus/key_value/$name"
+    head -c 65536 "$file" > "corpus/decode/$name"
+done
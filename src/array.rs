@@ -0,0 +1,198 @@
+//! Array Texture Assembly
+//!
+//! `ArrayBuilder` stacks textures of the same format and size into the
+//! layers of an array texture, and `extract_layers` takes a layer or a
+//! range of layers back out. Both move the frames of the `Decoder`
+//! stream as they are, so compressed data is never re-encoded.
+
+use crate::error::bail;
+use crate::io::AsyncWrite;
+use crate::{Encoder, Frame, HeaderInfo, KeyValueData, Result};
+use std::cmp::max;
+use std::ops::Range;
+
+/// Builder of an array texture
+#[derive(Debug, Clone, Default)]
+pub struct ArrayBuilder {
+    textures: Vec<(HeaderInfo, Vec<Frame>)>,
+    key_value_data: Option<KeyValueData>,
+}
+
+impl ArrayBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add the layers of a texture: the header and all frames of a KTX
+    /// file, in the order of the `Decoder` stream.
+    ///
+    /// A texture without array elements adds one layer. The frames must
+    /// cover every level, layer and face of the header.
+    pub fn texture(mut self, info: HeaderInfo, frames: Vec<Frame>) -> Self {
+        self.textures.push((info, frames));
+        self
+    }
+
+    /// Set the key/value data of the file. By default it is taken from
+    /// the first texture.
+    pub fn key_value_data(mut self, key_value_data: KeyValueData) -> Self {
+        self.key_value_data = Some(key_value_data);
+        self
+    }
+
+    /// Check the textures and return the header and the frames of the
+    /// array texture
+    pub fn build(self) -> Result<(HeaderInfo, Vec<Frame>)> {
+        let first = match self.textures.first() {
+            Some((info, _)) => info.clone(),
+            None => bail!("an array texture needs at least one layer"),
+        };
+        if first.pixel_depth > 0 {
+            bail!("3D textures cannot be array layers");
+        }
+
+        let nlevels = max(1, first.number_of_mipmap_levels);
+        let nfaces = max(1, first.number_of_faces);
+        let mut nlayers = 0;
+        for (i, (info, frames)) in self.textures.iter().enumerate() {
+            let format = |x: &HeaderInfo| {
+                (
+                    x.gl_type,
+                    x.gl_format,
+                    x.gl_internal_format,
+                    x.gl_base_internal_format,
+                )
+            };
+            if format(info) != format(&first) {
+                bail!(
+                    "texture {} has internal format {:#X}, texture 0 has {:#X}",
+                    i,
+                    info.gl_internal_format,
+                    first.gl_internal_format
+                );
+            }
+            let shape = |x: &HeaderInfo| {
+                (
+                    x.pixel_width,
+                    x.pixel_height,
+                    x.pixel_depth,
+                    max(1, x.number_of_faces),
+                    max(1, x.number_of_mipmap_levels),
+                )
+            };
+            if shape(info) != shape(&first) {
+                bail!(
+                    "texture {} is {}x{} with {} faces and {} levels, \
+                     texture 0 is {}x{} with {} faces and {} levels",
+                    i,
+                    info.pixel_width,
+                    info.pixel_height,
+                    max(1, info.number_of_faces),
+                    max(1, info.number_of_mipmap_levels),
+                    first.pixel_width,
+                    first.pixel_height,
+                    nfaces,
+                    nlevels
+                );
+            }
+            let layers = max(1, info.number_of_array_elements);
+            check_frames(i, info, frames)?;
+            nlayers += layers;
+        }
+
+        // Interleave the layers of the textures level by level
+        let mut levels: Vec<Vec<Frame>> = vec![vec![]; nlevels as usize];
+        let mut layer_offset = 0;
+        for (info, frames) in self.textures {
+            let per_level = (max(1, info.number_of_array_elements) * nfaces) as usize;
+            for (i, (mut frame, buf)) in frames.into_iter().enumerate() {
+                frame.layer += layer_offset;
+                levels[i / per_level].push((frame, buf));
+            }
+            layer_offset += max(1, info.number_of_array_elements);
+        }
+
+        let info = HeaderInfo {
+            number_of_array_elements: nlayers,
+            key_value_data: self.key_value_data.unwrap_or(first.key_value_data),
+            ..first
+        };
+        Ok((info, levels.into_iter().flatten().collect()))
+    }
+
+    /// Build the array texture and write it as a KTX 1.1 file
+    ///
+    /// Returns the writer.
+    pub async fn write_async<W: AsyncWrite + Unpin>(self, write: W) -> Result<W> {
+        let (info, frames) = self.build()?;
+        Encoder::new(write).write_async(&info, &frames).await
+    }
+}
+
+/// Take a range of layers out of a texture, given its header and all
+/// its frames in the order of the `Decoder` stream.
+///
+/// A single layer becomes a texture without array elements; a longer
+/// range stays an array texture. The key/value data is kept.
+pub fn extract_layers(
+    info: &HeaderInfo,
+    frames: Vec<Frame>,
+    layers: Range<u32>,
+) -> Result<(HeaderInfo, Vec<Frame>)> {
+    let nlayers = max(1, info.number_of_array_elements);
+    if layers.start >= layers.end || layers.end > nlayers {
+        bail!(
+            "invalid layers {}..{} of a texture with {} layers",
+            layers.start,
+            layers.end,
+            nlayers
+        );
+    }
+    check_frames(0, info, &frames)?;
+
+    let count = layers.end - layers.start;
+    let frames = frames
+        .into_iter()
+        .filter(|(frame, _)| layers.contains(&frame.layer))
+        .map(|(mut frame, buf)| {
+            frame.layer -= layers.start;
+            (frame, buf)
+        })
+        .collect();
+    let info = HeaderInfo {
+        number_of_array_elements: if count == 1 { 0 } else { count },
+        ..info.clone()
+    };
+    Ok((info, frames))
+}
+
+/// Check that the frames of a texture are complete and in order
+fn check_frames(texture: usize, info: &HeaderInfo, frames: &[Frame]) -> Result<()> {
+    let nlevels = max(1, info.number_of_mipmap_levels);
+    let nlayers = max(1, info.number_of_array_elements);
+    let nfaces = max(1, info.number_of_faces);
+    let expected = nlevels as usize * nlayers as usize * nfaces as usize;
+    if frames.len() != expected {
+        bail!(
+            "texture {} has {} frames, expected {}",
+            texture,
+            frames.len(),
+            expected
+        );
+    }
+    let order = (0..nlevels).flat_map(|level| {
+        (0..nlayers).flat_map(move |layer| (0..nfaces).map(move |face| (level, layer, face)))
+    });
+    for ((frame, _), expect) in frames.iter().zip(order) {
+        if (frame.level, frame.layer, frame.face) != expect {
+            bail!(
+                "frame (level {}, layer {}, face {}) of texture {} is out of order",
+                frame.level,
+                frame.layer,
+                frame.face,
+                texture
+            );
+        }
+    }
+    Ok(())
+}
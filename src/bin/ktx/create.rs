@@ -0,0 +1,337 @@
+//! `ktx create`
+
+use image::DynamicImage;
+use ktx::cubemap::CubemapBuilder;
+use ktx::{convert, format, gl, Encoder, FrameInfo, HeaderInfo, KeyValueData};
+
+const USAGE: &str = "\
+Usage: ktx create [options] -o <output> <input>...
+
+Build a KTX file from PNG images (or raw pixel data with --raw).
+
+Options:
+    -o, --output <file>        Output file; a .ktx2 extension selects KTX 2.0
+    --ktx2                     Write KTX 2.0 regardless of the extension
+    --mipmaps                  Inputs are mip levels, largest first
+    --array                    Inputs are array layers
+    --cubemap                  Inputs are the cube faces +X, -X, +Y, -Y, +Z, -Z
+    --equirect <size>          Input is an equirectangular panorama, projected
+                               onto a cubemap with faces of the given size
+    -f, --format <format>      GL internal format, e.g. RGBA8 or GL_SRGB8_ALPHA8
+                               (default: SRGB8_ALPHA8 or SRGB8 for 8-bit images,
+                               RGBA16 or RGB16 for 16-bit images,
+                               RGBA32F or RGB32F for floating-point images)
+    --raw <width>x<height>     Inputs hold tightly packed pixels of the given
+                               size in the layout of --format
+    --orientation <value>      KTXorientation (default: S=r,T=d)
+    --kv <key>=<value>         Add a key/value pair (may be repeated)";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Layout {
+    Single,
+    Mipmaps,
+    Array,
+    Cubemap,
+    Equirect(u32),
+}
+
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let mut output = None;
+    let mut ktx2 = false;
+    let mut layout = Layout::Single;
+    let mut internal_format = None;
+    let mut raw_size = None;
+    let mut orientation = "S=r,T=d".to_string();
+    let mut key_values = vec![];
+    let mut inputs = vec![];
+
+    let mut args = args.iter();
+    let value = |args: &mut std::slice::Iter<String>| match args.next() {
+        Some(x) => x.clone(),
+        None => crate::usage_error(USAGE),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(value(&mut args)),
+            "--ktx2" => ktx2 = true,
+            "--mipmaps" | "--array" | "--cubemap" | "--equirect" if layout != Layout::Single => {
+                return Err(
+                    "only one of --mipmaps, --array, --cubemap and --equirect is allowed".into(),
+                )
+            }
+            "--mipmaps" => layout = Layout::Mipmaps,
+            "--array" => layout = Layout::Array,
+            "--cubemap" => layout = Layout::Cubemap,
+            "--equirect" => {
+                let size = value(&mut args);
+                match size.parse() {
+                    Ok(x) if x > 0 => layout = Layout::Equirect(x),
+                    _ => return Err(format!("invalid size {}", size)),
+                }
+            }
+            "-f" | "--format" => {
+                let name = value(&mut args);
+                match gl::from_name(&name) {
+                    Some(x) => internal_format = Some(x),
+                    None => return Err(format!("unknown format {}", name)),
+                }
+            }
+            "--raw" => {
+                let size = value(&mut args);
+                match parse_size(&size) {
+                    Some(x) => raw_size = Some(x),
+                    None => return Err(format!("invalid size {}", size)),
+                }
+            }
+            "--orientation" => orientation = value(&mut args),
+            "--kv" => {
+                let kv = value(&mut args);
+                match kv.find('=') {
+                    Some(i) => key_values.push((kv[..i].to_string(), kv[i + 1..].to_string())),
+                    None => return Err(format!("invalid key/value pair {}", kv)),
+                }
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if !arg.starts_with('-') => inputs.push(arg.clone()),
+            _ => crate::usage_error(USAGE),
+        }
+    }
+    let output = match output {
+        Some(x) => x,
+        None => crate::usage_error(USAGE),
+    };
+    if inputs.is_empty() {
+        crate::usage_error(USAGE);
+    }
+    ktx2 |= output.ends_with(".ktx2");
+
+    match layout {
+        Layout::Single | Layout::Equirect(_) if inputs.len() != 1 => {
+            return Err("multiple inputs need --mipmaps, --array or --cubemap".into())
+        }
+        Layout::Cubemap if inputs.len() != 6 => return Err("cubemaps need 6 inputs".into()),
+        _ => {}
+    }
+    if !is_valid_orientation(&orientation) {
+        return Err(format!("invalid orientation {}", orientation));
+    }
+
+    // Load the inputs
+    let images = match raw_size {
+        Some(_) => None,
+        None => {
+            let images = inputs
+                .iter()
+                .map(|path| image::open(path).map_err(|e| format!("{}: {}", path, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(images)
+        }
+    };
+    let internal_format = match (internal_format, &images) {
+        (Some(x), _) => x,
+        (None, Some(images)) => default_format(&images[0]),
+        (None, None) => return Err("--raw needs --format".into()),
+    };
+    let (gl_format, gl_type) = match format::pixel_format(internal_format) {
+        Some(x) => x,
+        None => {
+            return Err(format!(
+                "{} is not an uncompressed format",
+                gl::name(internal_format).unwrap_or("the format")
+            ))
+        }
+    };
+    let (width, height) = match (raw_size, &images) {
+        (Some(size), _) => size,
+        (None, Some(images)) => (images[0].width(), images[0].height()),
+        (None, None) => unreachable!(),
+    };
+
+    let mut key_value_data = KeyValueData::default();
+    key_value_data.push("KTXorientation", &nul_terminated(&orientation));
+    for (key, value) in &key_values {
+        key_value_data.push(key, &nul_terminated(value));
+    }
+
+    if let Layout::Equirect(face_size) = layout {
+        let image = match &images {
+            Some(images) => &images[0],
+            None => return Err("--equirect needs an image".into()),
+        };
+        let pixels = image.to_rgba32f();
+        let (info, frames) = CubemapBuilder::from_equirectangular(
+            internal_format,
+            &pixels,
+            image.width(),
+            image.height(),
+            face_size,
+        )
+        .and_then(|builder| builder.key_value_data(key_value_data).build())
+        .map_err(|e| e.to_string())?;
+        return write(&output, ktx2, &info, &frames).await;
+    }
+
+    let n = inputs.len() as u32;
+    let info = HeaderInfo {
+        gl_type,
+        gl_type_size: format::type_size(gl_format, gl_type),
+        gl_format,
+        gl_internal_format: internal_format,
+        gl_base_internal_format: gl_format,
+        pixel_width: width,
+        pixel_height: height,
+        pixel_depth: 0,
+        number_of_array_elements: if layout == Layout::Array { n } else { 0 },
+        number_of_faces: if layout == Layout::Cubemap { 6 } else { 1 },
+        number_of_mipmap_levels: if layout == Layout::Mipmaps { n } else { 1 },
+        key_value_data,
+    };
+    if layout == Layout::Cubemap && width != height {
+        return Err(format!("cube faces are not square ({}x{})", width, height));
+    }
+
+    // Build the frames
+    let mut frames = vec![];
+    for (i, path) in inputs.iter().enumerate() {
+        let i = i as u32;
+        let level = if layout == Layout::Mipmaps { i } else { 0 };
+        let (w, h, _) = info.mipmap_size(level);
+        let frame = FrameInfo {
+            level,
+            layer: if layout == Layout::Array { i } else { 0 },
+            face: if layout == Layout::Cubemap { i } else { 0 },
+            pixel_width: w,
+            pixel_height: h,
+            pixel_depth: 1,
+        };
+        let buf = match &images {
+            Some(images) => {
+                let image = &images[i as usize];
+                if (image.width(), image.height()) != (w, h) {
+                    return Err(format!(
+                        "{}: expected {}x{} pixels, got {}x{}",
+                        path,
+                        w,
+                        h,
+                        image.width(),
+                        image.height()
+                    ));
+                }
+                image_to_frame(image, gl_format, gl_type)?
+            }
+            None => {
+                let data = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+                raw_to_frame(&data, w, h, gl_format, gl_type)
+                    .map_err(|e| format!("{}: {}", path, e))?
+            }
+        };
+        frames.push((frame, buf));
+    }
+    write(&output, ktx2, &info, &frames).await
+}
+
+async fn write(
+    output: &str,
+    ktx2: bool,
+    info: &HeaderInfo,
+    frames: &[(FrameInfo, Vec<u8>)],
+) -> Result<(), String> {
+    let encoder = Encoder::new(Vec::new());
+    let result = if ktx2 {
+        encoder.write_ktx2_async(info, frames).await
+    } else {
+        encoder.write_async(info, frames).await
+    };
+    let data = result.map_err(|e| e.to_string())?;
+    std::fs::write(output, data).map_err(|e| format!("{}: {}", output, e))
+}
+
+fn parse_size(size: &str) -> Option<(u32, u32)> {
+    let i = size.find('x')?;
+    let width = size[..i].parse().ok()?;
+    let height = size[i + 1..].parse().ok()?;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some((width, height))
+}
+
+/// Check a KTX 1.1 orientation such as `S=r,T=d`
+fn is_valid_orientation(value: &str) -> bool {
+    let parts: Vec<&str> = value.split(',').collect();
+    let allowed = [("S=", "rl"), ("T=", "du"), ("R=", "io")];
+    parts.len() <= 3
+        && parts
+            .iter()
+            .zip(allowed.iter())
+            .all(|(part, (prefix, values))| {
+                part.len() == 3 && part.starts_with(prefix) && values.contains(&part[2..])
+            })
+}
+
+fn nul_terminated(value: &str) -> Vec<u8> {
+    let mut v = value.as_bytes().to_vec();
+    v.push(0);
+    v
+}
+
+fn default_format(image: &DynamicImage) -> u32 {
+    use image::ColorType::*;
+    let color = image.color();
+    let has_alpha = color.has_alpha();
+    match color {
+        L16 | La16 | Rgb16 | Rgba16 if has_alpha => gl::RGBA16,
+        L16 | La16 | Rgb16 | Rgba16 => gl::RGB16,
+        Rgb32F | Rgba32F if has_alpha => gl::RGBA32F,
+        Rgb32F | Rgba32F => gl::RGB32F,
+        _ if has_alpha => gl::SRGB8_ALPHA8,
+        _ => gl::SRGB8,
+    }
+}
+
+/// Convert an image into the frame layout of the format
+/// (rows padded to 4 bytes)
+fn image_to_frame(image: &DynamicImage, gl_format: u32, gl_type: u32) -> Result<Vec<u8>, String> {
+    let pixels = match gl_format {
+        // Luminance ends up in the red channel
+        gl::LUMINANCE | gl::LUMINANCE_ALPHA => {
+            DynamicImage::ImageLumaA16(image.to_luma_alpha16()).to_rgba32f()
+        }
+        _ => image.to_rgba32f(),
+    };
+    convert::from_rgba32f(
+        gl_format,
+        gl_type,
+        image.width(),
+        image.height(),
+        1,
+        &pixels,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Pad the rows of tightly packed pixels to 4 bytes
+fn raw_to_frame(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    gl_format: u32,
+    gl_type: u32,
+) -> Result<Vec<u8>, String> {
+    let pixel_size =
+        convert::pixel_size(gl_format, gl_type).ok_or("unsupported pixel format for raw data")?;
+    let row_size = width as usize * pixel_size;
+    let expected = row_size * height as usize;
+    if data.len() != expected {
+        return Err(format!("expected {} bytes, got {}", expected, data.len()));
+    }
+    let mut buf = vec![];
+    for row in data.chunks_exact(row_size) {
+        buf.extend_from_slice(row);
+        buf.resize((buf.len() + 3) & !3, 0);
+    }
+    Ok(buf)
+}
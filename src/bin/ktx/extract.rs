@@ -0,0 +1,150 @@
+//! `ktx extract`
+
+use futures_core::stream::Stream;
+use ktx::{format, image::frame_to_image, Decoder, DecoderOptions, FrameInfo, HeaderInfo, Limits};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+
+const USAGE: &str = "\
+Usage: ktx extract [options] <file>
+
+Write every (level, layer, face) frame to a separate PNG file.
+Compressed frames are decoded with the software codecs.
+
+Options:
+    -o, --output <dir>       Output directory (default: .)
+    -t, --template <name>    File name template, with the placeholders
+                             {name}, {level}, {layer}, {face} and {slice}
+                             (default: {name}_L{level}_A{layer}_F{face}.png,
+                             with _Z{slice} added for 3D textures)
+    --raw                    Write the untouched frame data instead
+                             (default template: {name}_L{level}_A{layer}_F{face}.raw)
+    --lenient                Write the frames before the end of a truncated
+                             file and report the truncation as a warning";
+
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let mut raw = false;
+    let mut lenient = false;
+    let mut output = PathBuf::from(".");
+    let mut template = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--raw" => raw = true,
+            "--lenient" => lenient = true,
+            "-o" | "--output" => match args.next() {
+                Some(x) => output = PathBuf::from(x),
+                None => crate::usage_error(USAGE),
+            },
+            "-t" | "--template" => match args.next() {
+                Some(x) => template = Some(x.clone()),
+                None => crate::usage_error(USAGE),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg.clone()),
+            _ => crate::usage_error(USAGE),
+        }
+    }
+    let path = match path {
+        Some(x) => x,
+        None => crate::usage_error(USAGE),
+    };
+
+    let data = std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let name = Path::new(&path)
+        .file_stem()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let options = DecoderOptions {
+        lenient,
+        limits: Limits::relaxed(),
+        ..Default::default()
+    };
+    let (info, mut stream) = Decoder::with_options(&data[..], options)
+        .read_async()
+        .await
+        .map_err(|e| format!("{}: {}", path, e))?;
+
+    let is_3d = info.pixel_depth > 0;
+    let template = template.unwrap_or_else(|| match (raw, is_3d) {
+        (true, _) => "{name}_L{level}_A{layer}_F{face}.raw".into(),
+        (false, false) => "{name}_L{level}_A{layer}_F{face}.png".into(),
+        (false, true) => "{name}_L{level}_A{layer}_F{face}_Z{slice}.png".into(),
+    });
+    if is_3d && !raw && !template.contains("{slice}") {
+        return Err("the template needs {slice} for 3D textures".into());
+    }
+
+    while let Some(frame) = std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        let (frame, buf) = match frame {
+            Err(e) if lenient && e.is_truncation() => {
+                eprintln!("warning: {}: {}", path, e);
+                break;
+            }
+            frame => frame.map_err(|e| format!("{}: {}", path, e))?,
+        };
+        if raw {
+            let file = output.join(file_name(&template, &name, &frame, 0));
+            write(&file, &buf)?;
+            continue;
+        }
+        for (slice, (slice_frame, slice_buf)) in
+            slices(&info, &frame, &buf)?.into_iter().enumerate()
+        {
+            let file = output.join(file_name(&template, &name, &frame, slice as u32));
+            let image = frame_to_image(&info, &slice_frame, slice_buf).map_err(|e| {
+                format!(
+                    "level {} layer {} face {}: {} (use --raw to dump the data)",
+                    frame.level, frame.layer, frame.face, e
+                )
+            })?;
+            image
+                .save(&file)
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            println!("{}", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Split a frame of a 3D texture into its z slices
+fn slices<'a>(
+    info: &HeaderInfo,
+    frame: &FrameInfo,
+    buf: &'a [u8],
+) -> Result<Vec<(FrameInfo, &'a [u8])>, String> {
+    let slice_frame = FrameInfo {
+        pixel_depth: 1,
+        ..frame.clone()
+    };
+    if frame.pixel_depth <= 1 {
+        return Ok(vec![(slice_frame, buf)]);
+    }
+    let slice_size = format::image_size(info, frame.pixel_width, frame.pixel_height, 1)
+        .ok_or("unknown format of a 3D texture")? as usize;
+    Ok(buf
+        .chunks_exact(slice_size)
+        .take(frame.pixel_depth as usize)
+        .map(|x| (slice_frame.clone(), x))
+        .collect())
+}
+
+fn file_name(template: &str, name: &str, frame: &FrameInfo, slice: u32) -> String {
+    template
+        .replace("{name}", name)
+        .replace("{level}", &frame.level.to_string())
+        .replace("{layer}", &frame.layer.to_string())
+        .replace("{face}", &frame.face.to_string())
+        .replace("{slice}", &slice.to_string())
+}
+
+fn write(file: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(file, data).map_err(|e| format!("{}: {}", file.display(), e))?;
+    println!("{}", file.display());
+    Ok(())
+}
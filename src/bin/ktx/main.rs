@@ -0,0 +1,56 @@
+//! KTX texture tool
+//!
+//! Usage: ktx <command> [options]
+//!
+//! Commands:
+//!     create     Build a KTX file from images
+//!     extract    Write every frame of a KTX file to a separate file
+//!     trim       Remove mipmap levels
+
+extern crate ktx_async as ktx;
+
+mod create;
+mod extract;
+mod trim;
+
+use std::process::exit;
+
+const USAGE: &str = "\
+Usage: ktx <command> [options]
+
+Commands:
+    create     Build a KTX file from images
+    extract    Write every frame of a KTX file to a separate file
+    trim       Remove mipmap levels
+
+Run `ktx <command> --help` for the options of a command.";
+
+#[tokio::main(basic_scheduler)]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let command = args.next();
+    let args: Vec<String> = args.collect();
+    let result = match command.as_deref() {
+        Some("create") => create::run(&args).await,
+        Some("extract") => extract::run(&args).await,
+        Some("trim") => trim::run(&args).await,
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            return;
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("ktx: {}", e);
+        exit(1);
+    }
+}
+
+/// Print the usage of a command and exit if the arguments are invalid
+fn usage_error(usage: &str) -> ! {
+    eprintln!("{}", usage);
+    exit(2);
+}
@@ -0,0 +1,66 @@
+//! `ktx trim`
+
+use ktx::mipmap::Filter;
+use ktx::trim::{trim_async, TrimOptions};
+use ktx::{DecoderOptions, Limits};
+
+const USAGE: &str = "\
+Usage: ktx trim [options] -o <output> <file>
+
+Remove mipmap levels without re-encoding the others. The levels of
+files with numberOfMipmapLevels 0 are generated first.
+
+Options:
+    -o, --output <file>      Output file
+    --drop <n>               Drop the n largest levels
+    --max-size <size>        Drop the largest levels until no dimension
+                             exceeds size
+    --min-size <size>        Drop the smallest levels whose largest
+                             dimension is below size";
+
+pub async fn run(args: &[String]) -> Result<(), String> {
+    let mut options = TrimOptions::default();
+    let mut output = None;
+    let mut path = None;
+
+    let mut args = args.iter();
+    let number = |args: &mut std::slice::Iter<String>| match args.next() {
+        Some(x) => x
+            .parse::<u32>()
+            .map_err(|_| format!("invalid number {}", x)),
+        None => crate::usage_error(USAGE),
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(x) => output = Some(x.clone()),
+                None => crate::usage_error(USAGE),
+            },
+            "--drop" => options.drop_levels = number(&mut args)?,
+            "--max-size" => options.max_size = Some(number(&mut args)?),
+            "--min-size" => options.min_size = Some(number(&mut args)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg.clone()),
+            _ => crate::usage_error(USAGE),
+        }
+    }
+    let (path, output) = match (path, output) {
+        (Some(path), Some(output)) => (path, output),
+        _ => crate::usage_error(USAGE),
+    };
+
+    let data = std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?;
+    // Levels of files without mipmaps are generated to be trimmed
+    let decoder_options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        limits: Limits::relaxed(),
+        ..Default::default()
+    };
+    let trimmed = trim_async(&data[..], Vec::new(), &options, decoder_options)
+        .await
+        .map_err(|e| format!("{}: {}", path, e))?;
+    std::fs::write(&output, trimmed).map_err(|e| format!("{}: {}", output, e))
+}
@@ -0,0 +1,66 @@
+//! Check KTX files for conformance with the specification
+//!
+//! Usage: ktxcheck [--strict] <file>...
+//!
+//! Exits with status 1 if any file has errors (or warnings with
+//! `--strict`).
+
+extern crate ktx_async as ktx;
+
+use ktx::validate::Severity;
+use std::process::exit;
+
+const USAGE: &str = "Usage: ktxcheck [--strict] <file>...";
+
+fn main() {
+    let mut strict = false;
+    let mut paths = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--strict" => strict = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if !arg.starts_with('-') => paths.push(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                exit(2);
+            }
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", USAGE);
+        exit(2);
+    }
+
+    let threshold = if strict {
+        Severity::Warning
+    } else {
+        Severity::Error
+    };
+    let mut failed = false;
+    for path in &paths {
+        let data = match std::fs::read(path) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+        let issues = ktx::validate(&data);
+        for issue in &issues {
+            println!("{}: {}", path, issue);
+        }
+        if issues.iter().any(|issue| issue.severity >= threshold) {
+            failed = true;
+        } else if issues.is_empty() {
+            println!("{}: ok", path);
+        }
+    }
+
+    if failed {
+        exit(1);
+    }
+}
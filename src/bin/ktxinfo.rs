@@ -0,0 +1,269 @@
+//! Print the header, key/value data and level layout of a KTX file
+//!
+//! Usage: ktxinfo [--json] <file>
+
+extern crate ktx_async as ktx;
+
+use ktx::{gl, ktx2, parse, vk, ErrorKind, HeaderInfo, KeyValueData, Limits};
+use serde_json::{json, Value};
+use std::convert::TryFrom as _;
+use std::process::exit;
+
+const USAGE: &str = "Usage: ktxinfo [--json] <file>";
+
+#[tokio::main(basic_scheduler)]
+async fn main() {
+    let mut json_output = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => {
+                eprintln!("{}", USAGE);
+                exit(2);
+            }
+        }
+    }
+    let path = match path {
+        Some(x) => x,
+        None => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+    };
+
+    let data = match std::fs::read(&path) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            exit(1);
+        }
+    };
+
+    let report = if data.starts_with(&ktx2::MAGIC) {
+        ktx2_report(&data).await
+    } else {
+        ktx1_report(&data).await
+    };
+    let report = match report {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            exit(1);
+        }
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_report(&path, &report);
+    }
+}
+
+async fn ktx1_report(data: &[u8]) -> ktx::Result<Value> {
+    use byteorder::{ByteOrder as _, NativeEndian as NE};
+
+    // Only the header and the key/value data, so files whose levels
+    // are left to the loader (0 levels) or too large to decode are shown
+    let fixed = match data.get(..64).map(<&[u8; 64]>::try_from) {
+        Some(Ok(x)) => x,
+        _ => return Err(ErrorKind::UnexpectedEnd.into()),
+    };
+    let (mut header, key_value_bytes) = parse::parse_header(fixed, &Limits::relaxed(), true)?;
+    header.key_value_data = match data.get(64..64 + key_value_bytes as usize) {
+        Some(x) => x,
+        None => return Err(ErrorKind::UnexpectedEnd.into()),
+    };
+    let info = HeaderInfo::from(header);
+    let bytes_of_key_value_data = NE::read_u32(&data[60..64]);
+
+    let header = json!({
+        "glType": gl_enum(info.gl_type),
+        "glTypeSize": info.gl_type_size,
+        "glFormat": gl_enum(info.gl_format),
+        "glInternalFormat": gl_enum(info.gl_internal_format),
+        "glBaseInternalFormat": gl_enum(info.gl_base_internal_format),
+        "pixelWidth": info.pixel_width,
+        "pixelHeight": info.pixel_height,
+        "pixelDepth": info.pixel_depth,
+        "numberOfArrayElements": info.number_of_array_elements,
+        "numberOfFaces": info.number_of_faces,
+        "numberOfMipmapLevels": info.number_of_mipmap_levels,
+        "bytesOfKeyValueData": bytes_of_key_value_data,
+    });
+
+    Ok(json!({
+        "version": "KTX 1.1",
+        "header": header,
+        "keyValueData": key_values(&info.key_value_data),
+        "levels": ktx1_levels(&info, data, bytes_of_key_value_data),
+    }))
+}
+
+/// Walk the `imageSize` fields of a KTX 1 file
+fn ktx1_levels(info: &HeaderInfo, data: &[u8], bytes_of_key_value_data: u32) -> Vec<Value> {
+    use byteorder::{ByteOrder as _, NativeEndian as NE};
+
+    let is_cubemap = info.header().is_cubemap();
+    let align = |x: u64| (x + 3) & !3;
+
+    let mut levels = vec![];
+    let mut offset = 64 + align(u64::from(bytes_of_key_value_data));
+    for level in 0..info.number_of_mipmap_levels.max(1) {
+        if offset + 4 > data.len() as u64 {
+            break;
+        }
+        let image_size = NE::read_u32(&data[offset as usize..offset as usize + 4]);
+        let (width, height, depth) = info.mipmap_size(level);
+        levels.push(json!({
+            "level": level,
+            "width": width,
+            "height": height,
+            "depth": depth,
+            "imageSize": image_size,
+            "offset": offset + 4,
+        }));
+        let nfaces = if is_cubemap { 6 } else { 1 };
+        offset += 4 + nfaces * align(u64::from(image_size));
+    }
+    levels
+}
+
+async fn ktx2_report(data: &[u8]) -> ktx::Result<Value> {
+    let info = ktx2::read_header_with_limits_async(data, &Limits::relaxed()).await?;
+
+    let header = json!({
+        "vkFormat": {
+            "value": info.vk_format,
+            "name": vk::name(info.vk_format),
+        },
+        "typeSize": info.type_size,
+        "pixelWidth": info.pixel_width,
+        "pixelHeight": info.pixel_height,
+        "pixelDepth": info.pixel_depth,
+        "layerCount": info.layer_count,
+        "faceCount": info.face_count,
+        "levelCount": info.level_count,
+        "supercompressionScheme": {
+            "value": info.supercompression_scheme,
+            "name": ktx2::supercompression_scheme_name(info.supercompression_scheme),
+        },
+        "dfdByteOffset": info.dfd_byte_offset,
+        "dfdByteLength": info.dfd_byte_length,
+        "kvdByteOffset": info.kvd_byte_offset,
+        "kvdByteLength": info.kvd_byte_length,
+        "sgdByteOffset": info.sgd_byte_offset,
+        "sgdByteLength": info.sgd_byte_length,
+    });
+
+    let levels: Vec<Value> = info
+        .levels
+        .iter()
+        .enumerate()
+        .map(|(level, index)| {
+            let size = |x: u32| std::cmp::max(1, x >> level);
+            json!({
+                "level": level,
+                "width": size(info.pixel_width),
+                "height": size(info.pixel_height),
+                "depth": size(info.pixel_depth),
+                "byteLength": index.byte_length,
+                "uncompressedByteLength": index.uncompressed_byte_length,
+                "offset": index.byte_offset,
+            })
+        })
+        .collect();
+
+    Ok(json!({
+        "version": "KTX 2.0",
+        "header": header,
+        "keyValueData": key_values(&info.key_value_data),
+        "levels": levels,
+    }))
+}
+
+fn gl_enum(value: u32) -> Value {
+    json!({ "value": value, "name": gl::name(value) })
+}
+
+/// Key/value entries, with values as strings when printable
+fn key_values(kvd: &KeyValueData) -> Vec<Value> {
+    kvd.iter()
+        .map(|(key, value)| {
+            // Strings should be NUL-terminated, but may not be
+            let text = value.strip_suffix(&[0]).unwrap_or(value);
+            match std::str::from_utf8(text) {
+                Ok(s) if !s.chars().any(|c| c.is_control() && c != '\n' && c != '\t') => {
+                    json!({ "key": key, "string": s })
+                }
+                _ => json!({ "key": key, "hex": hex(value) }),
+            }
+        })
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let digits: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    digits.join(" ")
+}
+
+fn print_report(path: &str, report: &Value) {
+    println!("File: {}", path);
+    println!("Version: {}", report["version"].as_str().unwrap());
+
+    println!();
+    println!("Header:");
+    for (field, value) in report["header"].as_object().unwrap() {
+        if value.is_object() {
+            let number = value["value"].as_u64().unwrap();
+            match value["name"].as_str() {
+                Some(name) => println!("  {}: 0x{:04X} ({})", field, number, name),
+                None => println!("  {}: 0x{:04X}", field, number),
+            }
+        } else {
+            println!("  {}: {}", field, value);
+        }
+    }
+
+    println!();
+    println!("Key/Value Data:");
+    let entries = report["keyValueData"].as_array().unwrap();
+    if entries.is_empty() {
+        println!("  (none)");
+    }
+    for entry in entries {
+        let key = entry["key"].as_str().unwrap();
+        match entry["string"].as_str() {
+            Some(s) => println!("  {}: {:?}", key, s),
+            None => println!("  {}: [{}]", key, entry["hex"].as_str().unwrap()),
+        }
+    }
+
+    println!();
+    println!("Levels:");
+    let size_field = if report["version"] == "KTX 2.0" {
+        "byteLength"
+    } else {
+        "imageSize"
+    };
+    println!(
+        "  {:>5} {:>7} {:>7} {:>7} {:>12} {:>12}",
+        "level", "width", "height", "depth", size_field, "offset"
+    );
+    for level in report["levels"].as_array().unwrap() {
+        println!(
+            "  {:>5} {:>7} {:>7} {:>7} {:>12} {:>12}",
+            level["level"].to_string(),
+            level["width"].to_string(),
+            level["height"].to_string(),
+            level["depth"].to_string(),
+            level[size_field].to_string(),
+            level["offset"].to_string()
+        );
+    }
+}
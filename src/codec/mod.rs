@@ -0,0 +1,40 @@
+//! Software Decoders for Compressed Texture Formats
+//!
+//! These are meant for previews and tools rather than for runtime
+//! use; upload the compressed data to the GPU whenever possible.
+
+pub mod pvrtc;
+
+use crate::error::bail;
+use crate::{gl, ErrorKind, FrameInfo, HeaderInfo, Result};
+use alloc::vec::Vec;
+
+/// Decode a compressed frame from the `Decoder` stream into RGBA8
+/// pixels (`pixel_width * pixel_height * 4` bytes).
+pub fn decode_rgba8(info: &HeaderInfo, frame: &FrameInfo, buf: &[u8]) -> Result<Vec<u8>> {
+    use pvrtc::Bpp;
+
+    let (bpp, has_alpha) = match info.gl_internal_format {
+        gl::COMPRESSED_RGB_PVRTC_2BPPV1_IMG | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT => {
+            (Bpp::Two, false)
+        }
+        gl::COMPRESSED_RGB_PVRTC_4BPPV1_IMG | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT => {
+            (Bpp::Four, false)
+        }
+        gl::COMPRESSED_RGBA_PVRTC_2BPPV1_IMG | gl::COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT => {
+            (Bpp::Two, true)
+        }
+        gl::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG | gl::COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT => {
+            (Bpp::Four, true)
+        }
+        x => bail!(ErrorKind::UnsupportedFormat(x)),
+    };
+
+    let mut pixels = pvrtc::decode(buf, frame.pixel_width, frame.pixel_height, bpp)?;
+    if !has_alpha {
+        for px in pixels.chunks_exact_mut(4) {
+            px[3] = 0xFF;
+        }
+    }
+    Ok(pixels)
+}
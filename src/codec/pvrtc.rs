@@ -0,0 +1,371 @@
+//! PVRTC1 Decoder
+//!
+//! PVRTC1 stores two low-resolution colour images (A and B) and a
+//! full-resolution modulation image. Each 64-bit word holds one
+//! texel of A and B plus the modulation bits of a 4x4 (4bpp) or
+//! 8x4 (2bpp) block of pixels. A and B are upscaled bilinearly,
+//! with each word's colour located at the centre of its block, so
+//! every pixel depends on the four nearest words. The texture wraps
+//! around at the edges.
+//!
+//! Words are stored in Morton (twiddled) order.
+//!
+//! The decoding follows the reference decompressor shipped with the
+//! PowerVR SDK.
+
+use crate::error::bail;
+use crate::{ErrorKind, Result};
+use alloc::{vec, vec::Vec};
+use core::cmp::max;
+
+/// Bits per pixel of a PVRTC1 texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bpp {
+    /// 2 bits per pixel, 8x4 pixels per word
+    Two,
+    /// 4 bits per pixel, 4x4 pixels per word
+    Four,
+}
+
+impl Bpp {
+    /// Width and height of the pixel block covered by one word
+    pub fn block_size(self) -> (u32, u32) {
+        match self {
+            Bpp::Two => (8, 4),
+            Bpp::Four => (4, 4),
+        }
+    }
+
+    /// Number of words in each dimension.
+    /// A PVRTC1 texture is at least 2x2 words large.
+    fn word_count(self, width: u32, height: u32) -> (u32, u32) {
+        let (bw, bh) = self.block_size();
+        let nx = max(2, width.div_ceil(bw));
+        let ny = max(2, height.div_ceil(bh));
+        (nx, ny)
+    }
+}
+
+/// Size in bytes of the compressed data of a `width` x `height` image
+pub fn image_size(width: u32, height: u32, bpp: Bpp) -> usize {
+    let (nx, ny) = bpp.word_count(width, height);
+    nx as usize * ny as usize * 8
+}
+
+/// Decode a PVRTC1 image into RGBA8 pixels (`width * height * 4` bytes)
+pub fn decode(data: &[u8], width: u32, height: u32, bpp: Bpp) -> Result<Vec<u8>> {
+    use byteorder::{ByteOrder as _, LittleEndian as LE};
+
+    let expected = image_size(width, height, bpp);
+    if data.len() < expected {
+        bail!(ErrorKind::InvalidBufferSize(expected, data.len()));
+    }
+
+    let (bw, bh) = bpp.block_size();
+    let (nx, ny) = bpp.word_count(width, height);
+    let full_width = nx * bw;
+    let full_height = ny * bh;
+
+    let read_word = |x: u32, y: u32| {
+        let offset = twiddle(nx, ny, x, y) as usize * 8;
+        Word {
+            modulation: LE::read_u32(&data[offset..offset + 4]),
+            color: LE::read_u32(&data[offset + 4..offset + 8]),
+        }
+    };
+
+    let mut pixels = vec![0_u8; (full_width * full_height * 4) as usize];
+    for wy in 0..ny {
+        for wx in 0..nx {
+            // The window spans from the centre of word P to the
+            // centre of word S, wrapping around the texture edges.
+            //   P Q
+            //   R S
+            let wx1 = (wx + 1) % nx;
+            let wy1 = (wy + 1) % ny;
+            let words = [
+                read_word(wx, wy),
+                read_word(wx1, wy),
+                read_word(wx, wy1),
+                read_word(wx1, wy1),
+            ];
+            let window = decode_window(&words, bpp);
+            for y in 0..bh {
+                let py = (wy * bh + bh / 2 + y) % full_height;
+                for x in 0..bw {
+                    let px = (wx * bw + bw / 2 + x) % full_width;
+                    let dst = ((py * full_width + px) * 4) as usize;
+                    let src = (y * bw + x) as usize;
+                    pixels[dst..dst + 4].copy_from_slice(&window[src]);
+                }
+            }
+        }
+    }
+
+    // Crop the padding of textures smaller than the minimum size
+    if full_width != width || full_height != height {
+        let row = (width * 4) as usize;
+        let full_row = (full_width * 4) as usize;
+        let mut cropped = Vec::with_capacity(row * height as usize);
+        for y in 0..height as usize {
+            cropped.extend_from_slice(&pixels[y * full_row..y * full_row + row]);
+        }
+        pixels = cropped;
+    }
+    Ok(pixels)
+}
+
+/// A 64-bit PVRTC1 word
+#[derive(Debug, Clone, Copy)]
+struct Word {
+    modulation: u32,
+    color: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Color {
+    r: i32,
+    g: i32,
+    b: i32,
+    a: i32,
+}
+
+impl Word {
+    /// Colour A, expanded to RGB555 + A4
+    fn color_a(self) -> Color {
+        let c = self.color;
+        if c & 0x8000 != 0 {
+            // Opaque: RGB554
+            Color {
+                r: ((c >> 10) & 0x1F) as i32,
+                g: ((c >> 5) & 0x1F) as i32,
+                b: ((c & 0x1E) | ((c & 0x1E) >> 4)) as i32,
+                a: 0xF,
+            }
+        } else {
+            // Translucent: ARGB3443
+            Color {
+                r: (((c & 0xF00) >> 7) | ((c & 0xF00) >> 11)) as i32,
+                g: (((c & 0xF0) >> 3) | ((c & 0xF0) >> 7)) as i32,
+                b: (((c & 0xE) << 1) | ((c & 0xE) >> 2)) as i32,
+                a: ((c & 0x7000) >> 11) as i32,
+            }
+        }
+    }
+
+    /// Colour B, expanded to RGB555 + A4
+    fn color_b(self) -> Color {
+        let c = self.color;
+        if c & 0x8000_0000 != 0 {
+            // Opaque: RGB555
+            Color {
+                r: ((c >> 26) & 0x1F) as i32,
+                g: ((c >> 21) & 0x1F) as i32,
+                b: ((c >> 16) & 0x1F) as i32,
+                a: 0xF,
+            }
+        } else {
+            // Translucent: ARGB3444
+            Color {
+                r: (((c & 0xF00_0000) >> 23) | ((c & 0xF00_0000) >> 27)) as i32,
+                g: (((c & 0xF0_0000) >> 19) | ((c & 0xF0_0000) >> 23)) as i32,
+                b: (((c & 0xF_0000) >> 15) | ((c & 0xF_0000) >> 19)) as i32,
+                a: ((c & 0x7000_0000) >> 27) as i32,
+            }
+        }
+    }
+}
+
+/// Modulation data of a 2x2 word window, indexed by `[x][y]`
+struct Modulation {
+    values: [[i32; 8]; 16],
+    modes: [[u32; 8]; 16],
+}
+
+impl Modulation {
+    fn unpack(words: &[Word; 4], bpp: Bpp) -> Self {
+        let (bw, bh) = bpp.block_size();
+        let mut m = Modulation {
+            values: [[0; 8]; 16],
+            modes: [[0; 8]; 16],
+        };
+        for (i, word) in words.iter().enumerate() {
+            let ox = (i as u32 % 2) * bw;
+            let oy = (i as u32 / 2) * bh;
+            match bpp {
+                Bpp::Two => m.unpack_2bpp(*word, ox as usize, oy as usize),
+                Bpp::Four => m.unpack_4bpp(*word, ox as usize, oy as usize),
+            }
+        }
+        m
+    }
+
+    fn unpack_4bpp(&mut self, word: Word, ox: usize, oy: usize) {
+        let punch_through = word.color & 1 != 0;
+        let mut bits = word.modulation;
+        for y in 0..4 {
+            for x in 0..4 {
+                let v = (bits & 3) as i32;
+                bits >>= 2;
+                // Weights in eighths; +10 flags punch-through alpha
+                self.values[ox + x][oy + y] = if punch_through {
+                    [0, 4, 14, 8][v as usize]
+                } else {
+                    [0, 3, 5, 8][v as usize]
+                };
+            }
+        }
+    }
+
+    fn unpack_2bpp(&mut self, word: Word, ox: usize, oy: usize) {
+        let mut mode = word.color & 1;
+        let mut bits = word.modulation;
+
+        if mode == 0 {
+            // Direct encoding: one bit per pixel
+            for y in 0..4 {
+                for x in 0..8 {
+                    self.modes[ox + x][oy + y] = mode;
+                    self.values[ox + x][oy + y] = if bits & 1 != 0 { 3 } else { 0 };
+                    bits >>= 1;
+                }
+            }
+            return;
+        }
+
+        // Interpolated encoding: two bits for every other pixel in a
+        // checkerboard pattern. The LSB of the first pixel selects
+        // between H+V interpolation and H-only/V-only interpolation,
+        // in which case the LSB of the centre pixel picks the latter.
+        if bits & 1 != 0 {
+            mode = if bits & (1 << 20) != 0 { 3 } else { 2 };
+            if bits & (1 << 21) != 0 {
+                bits |= 1 << 20;
+            } else {
+                bits &= !(1 << 20);
+            }
+        }
+        if bits & 2 != 0 {
+            bits |= 1;
+        } else {
+            bits &= !1;
+        }
+        for y in 0..4 {
+            for x in 0..8 {
+                self.modes[ox + x][oy + y] = mode;
+                if (x ^ y) & 1 == 0 {
+                    self.values[ox + x][oy + y] = (bits & 3) as i32;
+                    bits >>= 2;
+                }
+            }
+        }
+    }
+
+    /// Modulation weight in eighths of the pixel at `(x, y)`,
+    /// with 10 added if the alpha is punched through.
+    fn get(&self, x: usize, y: usize, bpp: Bpp) -> i32 {
+        const WEIGHTS: [i32; 4] = [0, 3, 5, 8];
+
+        if bpp == Bpp::Four {
+            return self.values[x][y];
+        }
+
+        let w = |x: usize, y: usize| WEIGHTS[self.values[x][y] as usize];
+        let mode = self.modes[x][y];
+        if mode == 0 || (x ^ y) & 1 == 0 {
+            // Stored value
+            return w(x, y);
+        }
+        // Interpolated from the neighbours
+        match mode {
+            1 => (w(x, y - 1) + w(x, y + 1) + w(x - 1, y) + w(x + 1, y) + 2) / 4,
+            2 => (w(x - 1, y) + w(x + 1, y) + 1) / 2,
+            _ => (w(x, y - 1) + w(x, y + 1) + 1) / 2,
+        }
+    }
+}
+
+/// Decode the pixels between the centres of the four words,
+/// in row-major order.
+fn decode_window(words: &[Word; 4], bpp: Bpp) -> [[u8; 4]; 32] {
+    let (bw, bh) = bpp.block_size();
+    let modulation = Modulation::unpack(words, bpp);
+    let color_a = upscale(words.map(Word::color_a), bpp);
+    let color_b = upscale(words.map(Word::color_b), bpp);
+
+    let mut out = [[0_u8; 4]; 32];
+    for y in 0..bh as usize {
+        for x in 0..bw as usize {
+            let i = y * bw as usize + x;
+            let mut m = modulation.get(x + bw as usize / 2, y + bh as usize / 2, bpp);
+            let punch_through = m > 10;
+            if punch_through {
+                m -= 10;
+            }
+            let (a, b) = (color_a[i], color_b[i]);
+            let blend = |ca: i32, cb: i32| ((ca * (8 - m) + cb * m) / 8) as u8;
+            out[i] = [
+                blend(a.r, b.r),
+                blend(a.g, b.g),
+                blend(a.b, b.b),
+                if punch_through { 0 } else { blend(a.a, b.a) },
+            ];
+        }
+    }
+    out
+}
+
+/// Bilinearly upscale the colours of words P, Q, R, S to 8 bits per
+/// channel over the window between their centres, in row-major order.
+fn upscale(colors: [Color; 4], bpp: Bpp) -> [Color; 32] {
+    let (bw, bh) = bpp.block_size();
+    let (bw, bh) = (bw as i32, bh as i32);
+    let [p, q, r, s] = colors;
+
+    // The sum of the weights is 4 * bw, i.e. 16 (4bpp) or 32 (2bpp).
+    // Expand 5-bit colour and 4-bit alpha to 8 bits accordingly.
+    let (shift_hi, shift_lo) = match bpp {
+        Bpp::Two => (7, 2),
+        Bpp::Four => (6, 1),
+    };
+    let expand_color = |v: i32| (v >> shift_hi) + (v >> shift_lo);
+    let expand_alpha = |v: i32| (v >> (shift_hi - 2)) + (v >> (shift_lo - 1));
+
+    let mut out = [Color::default(); 32];
+    for y in 0..bh {
+        for x in 0..bw {
+            let lerp = |p: i32, q: i32, r: i32, s: i32| {
+                (bh - y) * ((bw - x) * p + x * q) + y * ((bw - x) * r + x * s)
+            };
+            out[(y * bw + x) as usize] = Color {
+                r: expand_color(lerp(p.r, q.r, r.r, s.r)),
+                g: expand_color(lerp(p.g, q.g, r.g, s.g)),
+                b: expand_color(lerp(p.b, q.b, r.b, s.b)),
+                a: expand_alpha(lerp(p.a, q.a, r.a, s.a)),
+            };
+        }
+    }
+    out
+}
+
+/// Morton index of the word at `(x, y)` in a `nx` x `ny` word grid.
+/// For non-square grids the remaining bits of the larger dimension
+/// are prepended.
+fn twiddle(nx: u32, ny: u32, x: u32, y: u32) -> u32 {
+    let (min_dim, mut rest) = if ny < nx { (ny, x) } else { (nx, y) };
+    let mut twiddled = 0;
+    let mut bit = 1;
+    let mut shift = 0;
+    while bit < min_dim {
+        if y & bit != 0 {
+            twiddled |= 1 << (2 * shift);
+        }
+        if x & bit != 0 {
+            twiddled |= 2 << (2 * shift);
+        }
+        bit <<= 1;
+        shift += 1;
+    }
+    rest >>= shift;
+    twiddled | (rest << (2 * shift))
+}
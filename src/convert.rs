@@ -0,0 +1,497 @@
+//! Pixel Conversion for Uncompressed Textures
+//!
+//! Converts frames of uncompressed textures, described by `glFormat`
+//! and `glType`, into normalized RGBA8 or RGBA32F pixels, and RGBA32F
+//! pixels back into frames. Rows of frame data are padded to 4 bytes,
+//! which is what KTX requires (`GL_UNPACK_ALIGNMENT = 4`).
+//!
+//! Values are not converted between colour spaces, e.g. frames of
+//! sRGB textures yield sRGB-encoded values.
+//!
+//! Without the `std` feature only `pixel_size` is available.
+
+#[cfg(feature = "std")]
+use crate::error::bail;
+use crate::gl;
+#[cfg(feature = "std")]
+use crate::{ErrorKind, FrameInfo, HeaderInfo, Result};
+
+/// Convert an uncompressed frame into RGBA8 pixels
+/// (`pixel_width * pixel_height * pixel_depth * 4` bytes).
+///
+/// Values outside `[0, 1]` (e.g. from signed or floating-point
+/// types) are clamped.
+#[cfg(feature = "std")]
+pub fn to_rgba8(info: &HeaderInfo, frame: &FrameInfo, buf: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    convert(info, frame, buf, |rgba| {
+        out.extend(
+            rgba.iter()
+                .map(|&v| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8),
+        );
+    })?;
+    Ok(out)
+}
+
+/// Convert an uncompressed frame into RGBA32F pixels
+/// (`pixel_width * pixel_height * pixel_depth * 4` values).
+#[cfg(feature = "std")]
+pub fn to_rgba32f(info: &HeaderInfo, frame: &FrameInfo, buf: &[u8]) -> Result<Vec<f32>> {
+    let mut out = Vec::new();
+    convert(info, frame, buf, |rgba| out.extend_from_slice(&rgba))?;
+    Ok(out)
+}
+
+/// Convert RGBA32F pixels (`width * height * depth * 4` values) into
+/// a frame of the given format and type, with rows padded to 4 bytes.
+///
+/// Values are clamped to the range of the type.
+#[cfg(feature = "std")]
+pub fn from_rgba32f(
+    gl_format: u32,
+    gl_type: u32,
+    width: u32,
+    height: u32,
+    depth: u32,
+    pixels: &[f32],
+) -> Result<Vec<u8>> {
+    let layout = match PixelLayout::new(gl_format, gl_type) {
+        Some(x) => x,
+        None => bail!(ErrorKind::UnsupportedPixelFormat(gl_format, gl_type)),
+    };
+
+    let width = width as usize;
+    let nrows = height as usize * depth as usize;
+    let expected = width * nrows * 4;
+    if pixels.len() < expected {
+        bail!(ErrorKind::InvalidBufferSize(expected, pixels.len()));
+    }
+    let row_size = width * layout.size;
+    let row_stride = (row_size + 3) & !3;
+
+    let mut out = Vec::with_capacity(row_stride * nrows);
+    for row in pixels[..expected].chunks_exact(width * 4) {
+        for px in row.chunks_exact(4) {
+            layout.encode([px[0], px[1], px[2], px[3]], &mut out);
+        }
+        out.resize(out.len() + row_stride - row_size, 0);
+    }
+    Ok(out)
+}
+
+/// Size in bytes of one pixel of the given format and type,
+/// or `None` if the combination is not supported.
+pub fn pixel_size(gl_format: u32, gl_type: u32) -> Option<usize> {
+    PixelLayout::new(gl_format, gl_type).map(|layout| layout.size)
+}
+
+#[cfg(feature = "std")]
+fn convert(
+    info: &HeaderInfo,
+    frame: &FrameInfo,
+    buf: &[u8],
+    mut output: impl FnMut([f32; 4]),
+) -> Result<()> {
+    let layout = match PixelLayout::new(info.gl_format, info.gl_type) {
+        Some(x) => x,
+        None => bail!(ErrorKind::UnsupportedPixelFormat(
+            info.gl_format,
+            info.gl_type
+        )),
+    };
+
+    let width = frame.pixel_width as usize;
+    let height = frame.pixel_height as usize;
+    let depth = frame.pixel_depth as usize;
+    let row_size = width * layout.size;
+    let row_stride = (row_size + 3) & !3;
+    let expected = row_stride * height * depth;
+    if buf.len() < expected {
+        bail!(ErrorKind::InvalidBufferSize(expected, buf.len()));
+    }
+
+    for row in buf[..expected].chunks_exact(row_stride) {
+        for px in row[..row_size].chunks_exact(layout.size) {
+            output(layout.decode(px));
+        }
+    }
+    Ok(())
+}
+
+/// How the components of a pixel are encoded
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+enum Encoding {
+    /// Unsigned normalized components of the given size
+    Unorm(usize),
+    /// Signed normalized components of the given size
+    Snorm(usize),
+    Half,
+    Float,
+    /// Components packed into one integer of the given size,
+    /// with their bit widths listed from the first component.
+    /// Non-`REV` types put the first component in the most
+    /// significant bits, `REV` types in the least significant bits.
+    Packed {
+        size: usize,
+        widths: &'static [u32],
+        rev: bool,
+    },
+    /// `UNSIGNED_INT_10F_11F_11F_REV`
+    R11G11B10F,
+    /// `UNSIGNED_INT_5_9_9_9_REV`
+    Rgb9E5,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+struct PixelLayout {
+    format: u32,
+    ncomponents: usize,
+    encoding: Encoding,
+    size: usize,
+}
+
+impl PixelLayout {
+    fn new(gl_format: u32, gl_type: u32) -> Option<Self> {
+        use Encoding::*;
+
+        let ncomponents = match gl_format {
+            gl::RED | gl::GREEN | gl::BLUE | gl::ALPHA | gl::LUMINANCE => 1,
+            gl::RG | gl::LUMINANCE_ALPHA => 2,
+            gl::RGB | gl::BGR => 3,
+            gl::RGBA | gl::BGRA => 4,
+            _ => return None,
+        };
+
+        let packed = |size, widths: &'static [u32], rev| Packed { size, widths, rev };
+        let encoding = match gl_type {
+            gl::UNSIGNED_BYTE => Unorm(1),
+            gl::UNSIGNED_SHORT => Unorm(2),
+            gl::UNSIGNED_INT => Unorm(4),
+            gl::BYTE => Snorm(1),
+            gl::SHORT => Snorm(2),
+            gl::INT => Snorm(4),
+            gl::HALF_FLOAT | gl::HALF_FLOAT_OES => Half,
+            gl::FLOAT => Float,
+            gl::UNSIGNED_BYTE_3_3_2 => packed(1, &[3, 3, 2], false),
+            gl::UNSIGNED_BYTE_2_3_3_REV => packed(1, &[3, 3, 2], true),
+            gl::UNSIGNED_SHORT_5_6_5 => packed(2, &[5, 6, 5], false),
+            gl::UNSIGNED_SHORT_5_6_5_REV => packed(2, &[5, 6, 5], true),
+            gl::UNSIGNED_SHORT_4_4_4_4 => packed(2, &[4, 4, 4, 4], false),
+            gl::UNSIGNED_SHORT_4_4_4_4_REV => packed(2, &[4, 4, 4, 4], true),
+            gl::UNSIGNED_SHORT_5_5_5_1 => packed(2, &[5, 5, 5, 1], false),
+            gl::UNSIGNED_SHORT_1_5_5_5_REV => packed(2, &[5, 5, 5, 1], true),
+            gl::UNSIGNED_INT_8_8_8_8 => packed(4, &[8, 8, 8, 8], false),
+            gl::UNSIGNED_INT_8_8_8_8_REV => packed(4, &[8, 8, 8, 8], true),
+            gl::UNSIGNED_INT_10_10_10_2 => packed(4, &[10, 10, 10, 2], false),
+            gl::UNSIGNED_INT_2_10_10_10_REV => packed(4, &[10, 10, 10, 2], true),
+            gl::UNSIGNED_INT_10F_11F_11F_REV if gl_format == gl::RGB => R11G11B10F,
+            gl::UNSIGNED_INT_5_9_9_9_REV if gl_format == gl::RGB => Rgb9E5,
+            _ => return None,
+        };
+
+        let size = match encoding {
+            Unorm(n) | Snorm(n) => n * ncomponents,
+            Half => 2 * ncomponents,
+            Float => 4 * ncomponents,
+            Packed { size, widths, .. } => {
+                // Packed types carry exactly as many components as the format
+                if widths.len() != ncomponents {
+                    return None;
+                }
+                size
+            }
+            R11G11B10F | Rgb9E5 => 4,
+        };
+
+        Some(PixelLayout {
+            format: gl_format,
+            ncomponents,
+            encoding,
+            size,
+        })
+    }
+
+    /// Decode one pixel into RGBA
+    #[cfg(feature = "std")]
+    fn decode(&self, px: &[u8]) -> [f32; 4] {
+        use byteorder::{ByteOrder as _, NativeEndian as NE};
+        use Encoding::*;
+
+        let mut c = [0.0_f32; 4];
+        let n = self.ncomponents;
+        match self.encoding {
+            Unorm(1) => {
+                for (x, &b) in c.iter_mut().zip(&px[..n]) {
+                    *x = f32::from(b) / 255.0;
+                }
+            }
+            Unorm(2) => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(2)) {
+                    *x = f32::from(NE::read_u16(b)) / 65535.0;
+                }
+            }
+            Unorm(_) => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(4)) {
+                    *x = (f64::from(NE::read_u32(b)) / f64::from(u32::MAX)) as f32;
+                }
+            }
+            Snorm(1) => {
+                for (x, &b) in c.iter_mut().zip(&px[..n]) {
+                    *x = (f32::from(b as i8) / 127.0).max(-1.0);
+                }
+            }
+            Snorm(2) => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(2)) {
+                    *x = (f32::from(NE::read_i16(b)) / 32767.0).max(-1.0);
+                }
+            }
+            Snorm(_) => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(4)) {
+                    let v = f64::from(NE::read_i32(b)) / f64::from(i32::MAX);
+                    *x = v.max(-1.0) as f32;
+                }
+            }
+            Half => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(2)) {
+                    *x = half_to_f32(NE::read_u16(b));
+                }
+            }
+            Float => {
+                for (x, b) in c.iter_mut().zip(px.chunks_exact(4)) {
+                    *x = NE::read_f32(b);
+                }
+            }
+            Packed { size, widths, rev } => {
+                let v = match size {
+                    1 => u32::from(px[0]),
+                    2 => u32::from(NE::read_u16(px)),
+                    _ => NE::read_u32(px),
+                };
+                let mut shift = if rev { 0 } else { size as u32 * 8 };
+                for (x, &w) in c.iter_mut().zip(widths) {
+                    if !rev {
+                        shift -= w;
+                    }
+                    let mask = (1_u32 << w) - 1;
+                    *x = ((v >> shift) & mask) as f32 / mask as f32;
+                    if rev {
+                        shift += w;
+                    }
+                }
+            }
+            R11G11B10F => {
+                let v = NE::read_u32(px);
+                c[0] = ufloat_to_f32(v & 0x7FF, 6);
+                c[1] = ufloat_to_f32((v >> 11) & 0x7FF, 6);
+                c[2] = ufloat_to_f32(v >> 22, 5);
+            }
+            Rgb9E5 => {
+                let v = NE::read_u32(px);
+                let scale = 2_f32.powi((v >> 27) as i32 - 15 - 9);
+                c[0] = (v & 0x1FF) as f32 * scale;
+                c[1] = ((v >> 9) & 0x1FF) as f32 * scale;
+                c[2] = ((v >> 18) & 0x1FF) as f32 * scale;
+            }
+        }
+
+        match self.format {
+            gl::RED => [c[0], 0.0, 0.0, 1.0],
+            gl::GREEN => [0.0, c[0], 0.0, 1.0],
+            gl::BLUE => [0.0, 0.0, c[0], 1.0],
+            gl::ALPHA => [0.0, 0.0, 0.0, c[0]],
+            gl::LUMINANCE => [c[0], c[0], c[0], 1.0],
+            gl::LUMINANCE_ALPHA => [c[0], c[0], c[0], c[1]],
+            gl::RG => [c[0], c[1], 0.0, 1.0],
+            gl::RGB => [c[0], c[1], c[2], 1.0],
+            gl::BGR => [c[2], c[1], c[0], 1.0],
+            gl::BGRA => [c[2], c[1], c[0], c[3]],
+            _ => c,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl PixelLayout {
+    /// Encode one RGBA pixel
+    fn encode(&self, rgba: [f32; 4], out: &mut Vec<u8>) {
+        use byteorder::{NativeEndian as NE, WriteBytesExt as _};
+        use Encoding::*;
+
+        let [r, g, b, a] = rgba;
+        let mut c = [0.0_f32; 4];
+        let components: &[f32] = match self.format {
+            gl::RED | gl::LUMINANCE => &[r],
+            gl::GREEN => &[g],
+            gl::BLUE => &[b],
+            gl::ALPHA => &[a],
+            gl::LUMINANCE_ALPHA => &[r, a],
+            gl::RG => &[r, g],
+            gl::RGB => &[r, g, b],
+            gl::BGR => &[b, g, r],
+            gl::BGRA => &[b, g, r, a],
+            _ => &rgba,
+        };
+        c[..components.len()].copy_from_slice(components);
+        let c = &c[..self.ncomponents];
+
+        let unorm = |v: f32, max: f64| (f64::from(v.clamp(0.0, 1.0)) * max).round();
+        let snorm = |v: f32, max: f64| (f64::from(v.clamp(-1.0, 1.0)) * max).round();
+        match self.encoding {
+            Unorm(1) => out.extend(c.iter().map(|&v| unorm(v, 255.0) as u8)),
+            Unorm(2) => {
+                for &v in c {
+                    out.write_u16::<NE>(unorm(v, 65535.0) as u16).unwrap();
+                }
+            }
+            Unorm(_) => {
+                for &v in c {
+                    out.write_u32::<NE>(unorm(v, f64::from(u32::MAX)) as u32)
+                        .unwrap();
+                }
+            }
+            Snorm(1) => out.extend(c.iter().map(|&v| snorm(v, 127.0) as i8 as u8)),
+            Snorm(2) => {
+                for &v in c {
+                    out.write_i16::<NE>(snorm(v, 32767.0) as i16).unwrap();
+                }
+            }
+            Snorm(_) => {
+                for &v in c {
+                    out.write_i32::<NE>(snorm(v, f64::from(i32::MAX)) as i32)
+                        .unwrap();
+                }
+            }
+            Half => {
+                for &v in c {
+                    out.write_u16::<NE>(f32_to_half(v)).unwrap();
+                }
+            }
+            Float => {
+                for &v in c {
+                    out.write_f32::<NE>(v).unwrap();
+                }
+            }
+            Packed { size, widths, rev } => {
+                let mut v = 0_u32;
+                let mut shift = if rev { 0 } else { size as u32 * 8 };
+                for (&x, &w) in c.iter().zip(widths) {
+                    if !rev {
+                        shift -= w;
+                    }
+                    let mask = (1_u32 << w) - 1;
+                    v |= (unorm(x, f64::from(mask)) as u32) << shift;
+                    if rev {
+                        shift += w;
+                    }
+                }
+                match size {
+                    1 => out.push(v as u8),
+                    2 => out.write_u16::<NE>(v as u16).unwrap(),
+                    _ => out.write_u32::<NE>(v).unwrap(),
+                }
+            }
+            R11G11B10F => {
+                let v = f32_to_ufloat(c[0], 6)
+                    | (f32_to_ufloat(c[1], 6) << 11)
+                    | (f32_to_ufloat(c[2], 5) << 22);
+                out.write_u32::<NE>(v).unwrap();
+            }
+            Rgb9E5 => out
+                .write_u32::<NE>(f32_to_rgb9e5([c[0], c[1], c[2]]))
+                .unwrap(),
+        }
+    }
+}
+
+/// Convert an IEEE 754 half-precision float
+#[cfg(feature = "std")]
+fn half_to_f32(v: u16) -> f32 {
+    let magnitude = ufloat_to_f32(u32::from(v & 0x7FFF), 10);
+    if v & 0x8000 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Convert an unsigned float with a 5-bit exponent and
+/// `mantissa_bits` bits of mantissa (as in half, 11- and 10-bit floats)
+#[cfg(feature = "std")]
+fn ufloat_to_f32(v: u32, mantissa_bits: u32) -> f32 {
+    let exponent = (v >> mantissa_bits) as i32;
+    let mantissa = (v & ((1 << mantissa_bits) - 1)) as f32 / (1 << mantissa_bits) as f32;
+    match exponent {
+        0 => 2_f32.powi(-14) * mantissa,
+        31 if mantissa == 0.0 => f32::INFINITY,
+        31 => f32::NAN,
+        e => 2_f32.powi(e - 15) * (1.0 + mantissa),
+    }
+}
+
+/// Convert to an IEEE 754 half-precision float, rounding to nearest even
+#[cfg(feature = "std")]
+fn f32_to_half(v: f32) -> u16 {
+    let sign = if v.is_sign_negative() { 0x8000 } else { 0 };
+    sign | f32_to_ufloat(v.abs(), 10) as u16
+}
+
+/// Convert to an unsigned float with a 5-bit exponent and
+/// `mantissa_bits` bits of mantissa, rounding to nearest even.
+/// Negative values become 0.
+#[cfg(feature = "std")]
+fn f32_to_ufloat(v: f32, mantissa_bits: u32) -> u32 {
+    let infinity = 0x1F << mantissa_bits;
+    if v.is_nan() {
+        return infinity | (1 << (mantissa_bits - 1));
+    }
+    if v <= 0.0 {
+        return 0;
+    }
+
+    let bits = v.to_bits();
+    let exponent = (bits >> 23) as i32 - 127 + 15;
+    let mantissa = bits & 0x7F_FFFF;
+    if exponent >= 31 {
+        return infinity;
+    }
+    let round = |value: u32, shift: u32| {
+        if shift >= 32 {
+            return 0;
+        }
+        let truncated = value >> shift;
+        let rest = value & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        if rest > halfway || (rest == halfway && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+    let shift = 23 - mantissa_bits;
+    if exponent <= 0 {
+        // Subnormal
+        return round(mantissa | 0x80_0000, shift + 1 + (-exponent) as u32);
+    }
+    // A carry out of the mantissa correctly bumps the exponent
+    round(((exponent as u32) << 23) | mantissa, shift)
+}
+
+/// Convert to `UNSIGNED_INT_5_9_9_9_REV`
+#[cfg(feature = "std")]
+fn f32_to_rgb9e5(rgb: [f32; 3]) -> u32 {
+    // Largest representable value: 511/512 * 2^16
+    const MAX: f32 = 65408.0;
+    let [r, g, b] = rgb.map(|x| if x > 0.0 { x.min(MAX) } else { 0.0 });
+    let max = r.max(g).max(b);
+
+    let mut exponent = (max.log2().floor() as i32).max(-16) + 1 + 15;
+    let mut denom = 2_f32.powi(exponent - 15 - 9);
+    if (max / denom + 0.5).floor() as u32 == 512 {
+        exponent += 1;
+        denom *= 2.0;
+    }
+    let m = |x: f32| (x / denom + 0.5).floor() as u32;
+    m(r) | (m(g) << 9) | (m(b) << 18) | ((exponent as u32) << 27)
+}
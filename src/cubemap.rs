@@ -0,0 +1,262 @@
+//! Cubemap Assembly
+//!
+//! `CubemapBuilder` turns six face images into the header and frames of
+//! a cubemap, ready for the `Encoder`. Faces come in the order of the
+//! KTX file: +X, -X, +Y, -Y, +Z, -Z.
+//!
+//! `equirectangular_to_faces` projects a latitude/longitude panorama
+//! onto the faces, following the cube map face selection of OpenGL: the
+//! centre of the panorama looks towards -Z, its top towards +Y.
+
+use crate::error::bail;
+use crate::io::AsyncWrite;
+use crate::{
+    convert, format, mipmap, Encoder, ErrorKind, Frame, FrameInfo, HeaderInfo, KeyValueData, Result,
+};
+use std::f32::consts::PI;
+
+/// Builder of a cubemap from six faces
+#[derive(Debug, Clone)]
+pub struct CubemapBuilder {
+    gl_internal_format: u32,
+    faces: Vec<(u32, u32, Vec<u8>)>,
+    key_value_data: KeyValueData,
+}
+
+impl CubemapBuilder {
+    /// Start a cubemap of the given internal format
+    pub fn new(gl_internal_format: u32) -> Self {
+        CubemapBuilder {
+            gl_internal_format,
+            faces: vec![],
+            key_value_data: KeyValueData::default(),
+        }
+    }
+
+    /// Project an equirectangular panorama (RGBA32F pixels) onto faces
+    /// of `face_size` pixels and add them.
+    ///
+    /// The pixel values are encoded as is, so values of sRGB formats
+    /// are expected to be sRGB-encoded; they are filtered in linear
+    /// space. Only uncompressed formats are supported.
+    pub fn from_equirectangular(
+        gl_internal_format: u32,
+        pixels: &[f32],
+        width: u32,
+        height: u32,
+        face_size: u32,
+    ) -> Result<Self> {
+        let (gl_format, gl_type) = match format::pixel_format(gl_internal_format) {
+            Some(x) => x,
+            None => bail!("{:#X} is not an uncompressed format", gl_internal_format),
+        };
+        let expect = width as usize * height as usize * 4;
+        if width == 0 || height == 0 || face_size == 0 || pixels.len() != expect {
+            bail!("invalid panorama of {}x{} pixels", width, height);
+        }
+
+        let is_srgb = format::is_srgb(gl_internal_format);
+        let linear;
+        let pixels = if is_srgb {
+            linear = map_rgb(pixels, mipmap::srgb_to_linear);
+            &linear[..]
+        } else {
+            pixels
+        };
+
+        let mut builder = CubemapBuilder::new(gl_internal_format);
+        for face in equirectangular_to_faces(pixels, width, height, face_size) {
+            let face = if is_srgb {
+                map_rgb(&face, mipmap::linear_to_srgb)
+            } else {
+                face
+            };
+            let buf = convert::from_rgba32f(gl_format, gl_type, face_size, face_size, 1, &face)?;
+            builder = builder.face(face_size, face_size, buf);
+        }
+        Ok(builder)
+    }
+
+    /// Add the next face, laid out like a frame of the `Decoder`
+    /// stream (rows of uncompressed data padded to 4 bytes)
+    pub fn face(mut self, width: u32, height: u32, buf: Vec<u8>) -> Self {
+        self.faces.push((width, height, buf));
+        self
+    }
+
+    /// Set the key/value data of the file
+    pub fn key_value_data(mut self, key_value_data: KeyValueData) -> Self {
+        self.key_value_data = key_value_data;
+        self
+    }
+
+    /// Check the faces and return the header and the frames of the
+    /// cubemap
+    pub fn build(self) -> Result<(HeaderInfo, Vec<Frame>)> {
+        let (gl_format, gl_type) = format::pixel_format(self.gl_internal_format).unwrap_or((0, 0));
+        let gl_base_internal_format = match format::base_internal_format(self.gl_internal_format) {
+            Some(x) => x,
+            None => bail!(ErrorKind::UnsupportedFormat(self.gl_internal_format)),
+        };
+        if self.faces.len() != 6 {
+            bail!("a cubemap needs 6 faces, got {}", self.faces.len());
+        }
+        let (size, _, _) = self.faces[0];
+        for (face, (width, height, _)) in self.faces.iter().enumerate() {
+            if width != height {
+                bail!("face {} is not square ({}x{})", face, width, height);
+            }
+            if *width != size {
+                bail!(
+                    "face {} is {}x{}, face 0 is {}x{}",
+                    face,
+                    width,
+                    height,
+                    size,
+                    size
+                );
+            }
+        }
+
+        let info = HeaderInfo {
+            gl_type,
+            gl_type_size: format::type_size(gl_format, gl_type),
+            gl_format,
+            gl_internal_format: self.gl_internal_format,
+            gl_base_internal_format,
+            pixel_width: size,
+            pixel_height: size,
+            pixel_depth: 0,
+            number_of_array_elements: 0,
+            number_of_faces: 6,
+            number_of_mipmap_levels: 1,
+            key_value_data: self.key_value_data,
+        };
+        let expect = format::image_size(&info, size, size, 1).map(|x| x as usize);
+        let mut frames = vec![];
+        for (face, (_, _, buf)) in self.faces.into_iter().enumerate() {
+            match expect {
+                Some(expect) if expect != buf.len() => {
+                    bail!(ErrorKind::InvalidBufferSize(expect, buf.len()))
+                }
+                _ => {}
+            }
+            let frame = FrameInfo {
+                level: 0,
+                layer: 0,
+                face: face as u32,
+                pixel_width: size,
+                pixel_height: size,
+                pixel_depth: 1,
+            };
+            frames.push((frame, buf));
+        }
+        Ok((info, frames))
+    }
+
+    /// Build the cubemap and write it as a KTX 1.1 file
+    ///
+    /// Returns the writer.
+    pub async fn write_async<W: AsyncWrite + Unpin>(self, write: W) -> Result<W> {
+        let (info, frames) = self.build()?;
+        Encoder::new(write).write_async(&info, &frames).await
+    }
+}
+
+/// Project an equirectangular panorama of RGBA32F pixels onto the six
+/// faces of a cube with bilinear filtering, and return the faces in
+/// KTX order (+X, -X, +Y, -Y, +Z, -Z).
+///
+/// Each face pixel averages a grid of samples when the panorama is
+/// larger than the faces.
+pub fn equirectangular_to_faces(
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    face_size: u32,
+) -> Vec<Vec<f32>> {
+    // Samples per face pixel along each axis: a face covers a quarter
+    // of the panorama width
+    let n = (width as f32 / (4.0 * face_size as f32))
+        .ceil()
+        .clamp(1.0, 4.0) as u32;
+    let size = face_size as f32;
+
+    (0..6)
+        .map(|face| {
+            let mut out = Vec::with_capacity(face_size as usize * face_size as usize * 4);
+            for y in 0..face_size {
+                for x in 0..face_size {
+                    let mut sum = [0.0_f32; 4];
+                    for sy in 0..n {
+                        for sx in 0..n {
+                            let s = 2.0 * (x as f32 + (sx as f32 + 0.5) / n as f32) / size - 1.0;
+                            let t = 2.0 * (y as f32 + (sy as f32 + 0.5) / n as f32) / size - 1.0;
+                            let dir = direction(face, s, t);
+                            let sample = sample_equirectangular(pixels, width, height, dir);
+                            for c in 0..4 {
+                                sum[c] += sample[c];
+                            }
+                        }
+                    }
+                    out.extend(sum.iter().map(|v| v / (n * n) as f32));
+                }
+            }
+            out
+        })
+        .collect()
+}
+
+/// Direction of the texel at `(s, t)` (both in -1..1) of a cube face
+fn direction(face: u32, s: f32, t: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -t, -s],
+        1 => [-1.0, -t, s],
+        2 => [s, 1.0, t],
+        3 => [s, -1.0, -t],
+        4 => [s, -t, 1.0],
+        _ => [-s, -t, -1.0],
+    }
+}
+
+fn sample_equirectangular(
+    pixels: &[f32],
+    width: u32,
+    height: u32,
+    [x, y, z]: [f32; 3],
+) -> [f32; 4] {
+    let len = (x * x + y * y + z * z).sqrt();
+    let u = 0.5 + x.atan2(-z) / (2.0 * PI);
+    let v = (y / len).clamp(-1.0, 1.0).acos() / PI;
+
+    // Bilinear filtering, wrapping around horizontally
+    let (w, h) = (width as i64, height as i64);
+    let fx = u * width as f32 - 0.5;
+    let fy = v * height as f32 - 0.5;
+    let (x0, y0) = (fx.floor(), fy.floor());
+    let (ax, ay) = (fx - x0, fy - y0);
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(w);
+        let y = y.clamp(0, h - 1);
+        let i = (y * w + x) as usize * 4;
+        &pixels[i..i + 4]
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let mut out = [0.0; 4];
+    for (c, v) in out.iter_mut().enumerate() {
+        let top = texel(x0, y0)[c] * (1.0 - ax) + texel(x0 + 1, y0)[c] * ax;
+        let bottom = texel(x0, y0 + 1)[c] * (1.0 - ax) + texel(x0 + 1, y0 + 1)[c] * ax;
+        *v = top * (1.0 - ay) + bottom * ay;
+    }
+    out
+}
+
+fn map_rgb(pixels: &[f32], f: fn(f32) -> f32) -> Vec<f32> {
+    let mut out = pixels.to_vec();
+    for px in out.chunks_exact_mut(4) {
+        for c in &mut px[..3] {
+            *c = f(*c);
+        }
+    }
+    out
+}
@@ -0,0 +1,951 @@
+//! Decoders
+//!
+//! The `Decoder` reads a file through an asynchronous reader (see the
+//! `io` module) or a `std::io::Read`, and yields its frames as they
+//! arrive. The header, the layout of the levels and the checks come
+//! from the `parse` module.
+
+use crate::error::{bail, ResultExt as _};
+use crate::io::{self, AsyncRead, AsyncSeek, SeekFrom};
+use crate::parse::{self, add_level_size, check_limit, check_total_size, Header};
+use crate::pool::{self, BufferPool};
+use crate::{
+    convert, format, mipmap, Error, ErrorKind, Frame, FrameInfo, HeaderInfo, KeyValueData,
+    LevelInfo,
+};
+use crate::{Limits, Result};
+use futures_core::stream::Stream;
+
+/// KTX decoder
+pub struct Decoder<R> {
+    read: R,
+    options: DecoderOptions,
+}
+
+/// Options of a `Decoder`
+#[derive(Debug, Clone, Default)]
+pub struct DecoderOptions {
+    /// Accept files with `numberOfMipmapLevels == 0` and generate the
+    /// full mipmap pyramid from level 0 with the given filter.
+    /// Only uncompressed formats are supported.
+    ///
+    /// `HeaderInfo::number_of_mipmap_levels` then reports the number
+    /// of levels in the stream.
+    pub generate_mipmaps: Option<mipmap::Filter>,
+    /// Order of the frames in the stream
+    pub frame_order: FrameOrder,
+    /// Limits on the sizes in the header
+    pub limits: Limits,
+    /// Salvage truncated files: every frame read in full before the
+    /// end of the file is yielded, followed by the truncation error
+    /// (see `Error::is_truncation`) as the last item of the stream.
+    ///
+    /// Without it the stream still yields the frames before the
+    /// truncation, but frames that are buffered (generated mipmaps,
+    /// `FrameOrder::SmallestFirst`) are lost, and
+    /// `Decoder::read_seekable_async` fails if a level is missing.
+    pub lenient: bool,
+    /// Take the buffers of the frames (and chunks) from a pool, see
+    /// `BufferPool`
+    pub buffer_pool: Option<BufferPool>,
+}
+
+/// Order of the frames in the `Decoder` stream
+///
+/// Frames are yielded level by level. Within a level they come in file
+/// order: by array layer, then by cubemap face. Each frame holds all
+/// the z slices of a 3D texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameOrder {
+    /// Level 0 (the largest) first, in file order. Works with any
+    /// reader.
+    #[default]
+    LargestFirst,
+    /// The smallest level first, so that a blurry texture can be shown
+    /// early and refined as the larger levels arrive. Needs a seekable
+    /// reader, see `Decoder::read_seekable_async`.
+    SmallestFirst,
+}
+
+impl<R> Decoder<R> {
+    pub fn new(read: R) -> Self {
+        Self::with_options(read, DecoderOptions::default())
+    }
+
+    pub fn with_options(read: R, options: DecoderOptions) -> Self {
+        Decoder { read, options }
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Read the header and the following frames asynchronously
+    ///
+    /// Frames come in `FrameOrder::LargestFirst` order;
+    /// `FrameOrder::SmallestFirst` needs `read_seekable_async`.
+    pub async fn read_async(
+        self,
+    ) -> Result<(
+        HeaderInfo,
+        impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin,
+    )> {
+        if self.options.frame_order != FrameOrder::LargestFirst {
+            bail!(ErrorKind::NeedsSeek);
+        }
+        let mut read = self.read;
+
+        // Read the header
+        let mut info = read_header_async(&mut read, &self.options).await?;
+        let generate_mipmaps = prepare_mipmap_generation(&mut info, &self.options)?;
+
+        // Create the stream of the frames
+        let stream = new_async_stream(read, &info, generate_mipmaps, &self.options);
+
+        Ok((info, stream))
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Read the header and the following frames asynchronously in
+    /// chunks of at most `max_chunk_size` bytes, to bound the memory
+    /// used by large levels
+    ///
+    /// Each item holds a range of pixel rows of a frame and their data.
+    /// Chunks hold whole rows (rows of blocks for compressed formats,
+    /// so ranges are aligned to the block height and only the last
+    /// chunk of an image may end in a partial block), with rows of
+    /// uncompressed data padded to 4 bytes; a chunk holds at least
+    /// one row. Rows of the z slices of 3D textures are numbered
+    /// consecutively (`z * pixel_height + y`), and chunks do not cross
+    /// slices.
+    ///
+    /// Frames of PVRTC formats, whose blocks are not stored row by row,
+    /// and generated mipmaps come as a single chunk.
+    pub async fn read_chunks_async(
+        self,
+        max_chunk_size: usize,
+    ) -> Result<(HeaderInfo, impl Stream<Item = Result<Chunk>> + Unpin)> {
+        if self.options.frame_order != FrameOrder::LargestFirst {
+            bail!(ErrorKind::NeedsSeek);
+        }
+        let mut read = self.read;
+        let mut info = read_header_async(&mut read, &self.options).await?;
+
+        // Chunks are bounded by `max_chunk_size` rather than by the size
+        // of the texture, unless mipmaps are generated from level 0
+        let mut options = self.options;
+        let max_frame_size = options.limits.max_total_bytes;
+        if options.generate_mipmaps.is_none() {
+            options.limits.max_total_bytes = u64::MAX;
+        }
+        let generate_mipmaps = prepare_mipmap_generation(&mut info, &options)?;
+        let stream = new_chunk_stream(
+            read,
+            &info,
+            generate_mipmaps,
+            &options,
+            max_chunk_size,
+            max_frame_size,
+        );
+        Ok((info, stream))
+    }
+}
+
+/// A range of pixel rows of a frame and their data, see
+/// `Decoder::read_chunks_async`
+pub type Chunk = (FrameInfo, std::ops::Range<u32>, Vec<u8>);
+
+impl<R> Decoder<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Read the header and the following levels asynchronously, each
+    /// in one buffer holding all its layers and faces (see
+    /// `LevelInfo`), as `glCompressedTexImage3D` or a single buffer
+    /// copy would take them
+    ///
+    /// A level cut short by the end of the file is not yielded: the
+    /// stream ends with the truncation error instead.
+    pub async fn read_levels_async(
+        self,
+    ) -> Result<(
+        HeaderInfo,
+        impl Stream<Item = Result<(LevelInfo, Vec<u8>)>> + Unpin,
+    )> {
+        if self.options.frame_order != FrameOrder::LargestFirst {
+            bail!(ErrorKind::NeedsSeek);
+        }
+        let mut read = self.read;
+        let mut info = read_header_async(&mut read, &self.options).await?;
+        let generate_mipmaps = prepare_mipmap_generation(&mut info, &self.options)?;
+        let stream = new_level_stream(read, &info, generate_mipmaps, &self.options);
+        Ok((info, stream))
+    }
+
+    /// Read the header and all the frames asynchronously into a single
+    /// buffer, along with the range of each level and frame in it
+    ///
+    /// Fails if the file is truncated, even if it is lenient.
+    pub async fn read_all_async(self) -> Result<(HeaderInfo, TextureData)> {
+        use futures_core::stream::Stream as _;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        if self.options.frame_order != FrameOrder::LargestFirst {
+            bail!(ErrorKind::NeedsSeek);
+        }
+        let mut read = self.read;
+        let mut info = read_header_async(&mut read, &self.options).await?;
+        let generate_mipmaps = prepare_mipmap_generation(&mut info, &self.options)?;
+
+        let mut texture = TextureData::default();
+        if generate_mipmaps.is_some() {
+            // Generated levels come from the level stream
+            let mut levels = new_level_stream(read, &info, generate_mipmaps, &self.options);
+            while let Some(level) = poll_fn(|cx| Pin::new(&mut levels).poll_next(cx)).await {
+                let (level, buf) = level?;
+                let start = texture.data.len();
+                texture.data.extend_from_slice(&buf);
+                texture.push_level(level, start);
+            }
+            return Ok((info, texture));
+        }
+
+        let mut offset = info.header().data_offset();
+        let mut total = 0;
+        for level in 0..info.number_of_mipmap_levels {
+            let level =
+                read_level_info(&mut read, &info, level, offset, &mut total, &self.options).await?;
+            offset += 4;
+            let start = texture.data.len();
+            texture.data.resize(start + level.size(), 0);
+            read_level_data(&mut read, &level, &mut texture.data[start..], offset).await?;
+            offset += level.size() as u64;
+            texture.push_level(level, start);
+        }
+        Ok((info, texture))
+    }
+}
+
+/// All the frames of a file in a single buffer, see
+/// `Decoder::read_all_async`
+#[derive(Debug, Clone, Default)]
+pub struct TextureData {
+    /// Data of the levels one after the other
+    pub data: Vec<u8>,
+    /// Levels and their ranges in `data`
+    pub levels: Vec<(LevelInfo, std::ops::Range<usize>)>,
+    /// Frames and their ranges in `data`, in file order
+    pub frames: Vec<(FrameInfo, std::ops::Range<usize>)>,
+}
+
+impl TextureData {
+    /// Data of a level
+    pub fn level(&self, level: u32) -> Option<&[u8]> {
+        let (_, range) = self.levels.get(level as usize)?;
+        Some(&self.data[range.clone()])
+    }
+
+    /// Data of a frame
+    pub fn frame(&self, level: u32, layer: u32, face: u32) -> Option<&[u8]> {
+        let (_, range) = self
+            .frames
+            .iter()
+            .find(|(x, _)| (x.level, x.layer, x.face) == (level, layer, face))?;
+        Some(&self.data[range.clone()])
+    }
+
+    /// Add a level whose data starts at `start`
+    fn push_level(&mut self, level: LevelInfo, start: usize) {
+        let frames = level.frames();
+        let frames = frames.map(|(frame, range)| (frame, start + range.start..start + range.end));
+        self.frames.extend(frames);
+        let range = start..start + level.size();
+        self.levels.push((level, range));
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    /// Read the header and the following frames asynchronously from a
+    /// seekable reader, in the order of `DecoderOptions::frame_order`
+    ///
+    /// For `FrameOrder::SmallestFirst` the `imageSize` fields of all
+    /// levels are located first, then the levels are read from the
+    /// last one backwards. Generated mipmaps are yielded once level 0
+    /// has been read.
+    pub async fn read_seekable_async(
+        self,
+    ) -> Result<(
+        HeaderInfo,
+        impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin,
+    )> {
+        use futures_core::stream::Stream as _;
+        use std::future::poll_fn;
+        use std::pin::Pin;
+
+        let mut read = self.read;
+        let mut info = read_header_async(&mut read, &self.options).await?;
+        let generate_mipmaps = prepare_mipmap_generation(&mut info, &self.options)?;
+        let order = self.options.frame_order;
+
+        // Offsets of the levels to read backwards
+        let levels = match order {
+            FrameOrder::SmallestFirst if generate_mipmaps.is_none() => {
+                Some(locate_levels_async(&mut read, &info, &self.options).await?)
+            }
+            _ => None,
+        };
+
+        let stream_info = info.clone();
+        let options = self.options;
+        let stream = Box::pin(async_stream::try_stream! {
+            match levels {
+                None => {
+                    // File order, or generated levels
+                    let mut frames = new_async_stream(read, &stream_info, generate_mipmaps, &options);
+                    let mut buffered = vec![];
+                    let mut truncation = None;
+                    while let Some(frame) = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await {
+                        let frame = match frame {
+                            Err(e) if options.lenient && e.is_truncation() => {
+                                truncation = Some(e);
+                                break;
+                            }
+                            frame => frame?,
+                        };
+                        match order {
+                            FrameOrder::LargestFirst => yield frame,
+                            FrameOrder::SmallestFirst => buffered.push(frame),
+                        }
+                    }
+                    buffered.sort_by_key(|(frame, _)| std::cmp::Reverse(frame.level));
+                    for frame in buffered {
+                        yield frame;
+                    }
+                    if let Some(e) = truncation {
+                        Err(e)?;
+                    }
+                }
+                Some(levels) => {
+                    let mut frames = new_reverse_async_stream(read, &stream_info, levels, &options);
+                    while let Some(frame) = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await {
+                        let frame = frame?;
+                        yield frame;
+                    }
+                }
+            }
+        });
+
+        Ok((info, stream))
+    }
+}
+
+impl<R> Decoder<R>
+where
+    R: std::io::Read,
+{
+    /// Read the header and the following frames synchronously
+    ///
+    /// Frames come in `FrameOrder::LargestFirst` order. Generated
+    /// mipmaps and `FrameOrder::SmallestFirst` need the asynchronous
+    /// methods. A file that ends early yields its complete frames,
+    /// then the truncation error, whether or not it is lenient.
+    pub fn read(self) -> Result<(HeaderInfo, impl Iterator<Item = Result<Frame>>)> {
+        if self.options.frame_order != FrameOrder::LargestFirst {
+            bail!(ErrorKind::NeedsSeek);
+        }
+        let mut read = self.read;
+        let limits = self.options.limits;
+
+        let mut buf = [0_u8; 64];
+        read.read_exact(&mut buf).at_offset(0)?;
+        let generate_mipmaps = self.options.generate_mipmaps.is_some();
+        let (header, key_value_bytes) = parse::parse_header(&buf, &limits, generate_mipmaps)?;
+        if header.number_of_mipmap_levels == 0 {
+            bail!(ErrorKind::NeedsAsyncReader);
+        }
+        let mut kvbuf = vec![0; key_value_bytes as usize];
+        read.read_exact(&mut kvbuf).at_offset(64)?;
+        let info = header_info(header, kvbuf)?;
+        check_total_size(&info.header(), &limits)?;
+
+        let nlevels = info.number_of_mipmap_levels;
+        let mut cursor = parse::FrameCursor::new(&info.header(), nlevels, &limits);
+        let pool = self.options.buffer_pool;
+        let frames = std::iter::from_fn(move || {
+            if cursor.is_done() {
+                return None;
+            }
+            let result = read_frame(&mut read, &mut cursor, pool.as_ref());
+            if result.is_err() {
+                cursor.finish();
+            }
+            result.transpose()
+        });
+        Ok((info, frames))
+    }
+}
+
+/// Read the next frame of `Decoder::read`, after the `imageSize` field
+/// of its level if it starts one
+fn read_frame(
+    read: &mut impl std::io::Read,
+    cursor: &mut parse::FrameCursor,
+    pool: Option<&BufferPool>,
+) -> Result<Option<Frame>> {
+    if let Some((offset, level)) = cursor.image_size_field() {
+        let mut buf = [0_u8; 4];
+        read.read_exact(&mut buf)
+            .at_offset(offset)
+            .in_level(level)?;
+        cursor.set_image_size(u32::from_ne_bytes(buf))?;
+    }
+    let (frame, offset, size) = match cursor.next_frame() {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+    let mut buf = pool::take(pool, size);
+    read.read_exact(&mut buf)
+        .at_offset(offset)
+        .in_frame(frame.level, frame.layer, frame.face)?;
+    Ok(Some((frame, buf)))
+}
+
+/// Check `DecoderOptions::generate_mipmaps` against the header, and
+/// return the filter if the levels after level 0 have to be generated
+fn prepare_mipmap_generation(
+    info: &mut HeaderInfo,
+    options: &DecoderOptions,
+) -> Result<Option<mipmap::Filter>> {
+    match options.generate_mipmaps {
+        Some(filter) if info.number_of_mipmap_levels == 0 => {
+            if convert::pixel_size(info.gl_format, info.gl_type).is_none() {
+                bail!(ErrorKind::UnsupportedPixelFormat(
+                    info.gl_format,
+                    info.gl_type
+                ));
+            }
+            info.number_of_mipmap_levels =
+                mipmap::level_count(info.pixel_width, info.pixel_height, info.pixel_depth);
+            check_limit(
+                "numberOfMipmapLevels",
+                info.number_of_mipmap_levels.into(),
+                options.limits.max_levels.into(),
+            )?;
+            check_total_size(&info.header(), &options.limits)?;
+            Ok(Some(filter))
+        }
+        _ => {
+            check_total_size(&info.header(), &options.limits)?;
+            Ok(None)
+        }
+    }
+}
+
+/// Levels found by `locate_levels_async`
+struct LevelLocations {
+    /// Offset of the data, size of a frame and number of frames to read
+    /// of each level
+    levels: Vec<(u64, u32, u32)>,
+    /// End of the file before the end of the last level, in lenient
+    /// mode
+    truncation: Option<Error>,
+}
+
+/// Find the offset of the data and the `imageSize` of each level,
+/// starting at the current position (the end of the key/value data)
+///
+/// In lenient mode the search stops at the end of the file, and the
+/// last level keeps the frames before it.
+async fn locate_levels_async(
+    read: &mut (impl AsyncRead + AsyncSeek + Unpin),
+    info: &HeaderInfo,
+    options: &DecoderOptions,
+) -> Result<LevelLocations> {
+    use std::cmp::{max, min};
+
+    let nlayers = max(1, info.number_of_array_elements);
+    let nfaces = max(1, info.number_of_faces);
+    let nframes = nlayers * nfaces;
+    let is_cubemap = info.header().is_cubemap();
+    let mut offset = io::seek(read, SeekFrom::Current(0)).await?;
+    let end = if options.lenient {
+        io::seek(read, SeekFrom::End(0)).await?
+    } else {
+        u64::MAX
+    };
+    let mut levels = vec![];
+    let mut total = 0;
+    for level in 0..info.number_of_mipmap_levels {
+        let result: Result<u32> = async {
+            io::seek(read, SeekFrom::Start(offset)).await?;
+            let mut buf = [0_u8; 4];
+            io::read_exact(read, &mut buf).await?;
+            let image_size = u32::from_ne_bytes(buf);
+            add_level_size(
+                &mut total,
+                &info.header(),
+                level,
+                image_size,
+                is_cubemap,
+                &options.limits,
+            )
+        }
+        .await;
+        let buf_size = match result.at_offset(offset).in_level(level) {
+            Err(e) if options.lenient && e.is_truncation() => {
+                return Ok(LevelLocations {
+                    levels,
+                    truncation: Some(e),
+                });
+            }
+            result => result?,
+        };
+        offset += 4;
+
+        // Frames before the end of the file
+        let size = u64::from(buf_size) * u64::from(nframes);
+        if offset + size > end {
+            let available = min(u64::from(nframes), (end - offset) / u64::from(buf_size)) as u32;
+            levels.push((offset, buf_size, available));
+            let truncated_at = offset + u64::from(available) * u64::from(buf_size);
+            let e = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+            let truncation = Err::<(), _>(e)
+                .at_offset(truncated_at)
+                .in_frame(level, available / nfaces, available % nfaces)
+                .unwrap_err();
+            return Ok(LevelLocations {
+                levels,
+                truncation: Some(truncation),
+            });
+        }
+        levels.push((offset, buf_size, nframes));
+        offset += size;
+    }
+    Ok(LevelLocations {
+        levels,
+        truncation: None,
+    })
+}
+
+/// Stream of the frames of the levels located by
+/// `locate_levels_async`, from the last level to the first
+fn new_reverse_async_stream(
+    read: impl AsyncRead + AsyncSeek + Unpin,
+    info: &HeaderInfo,
+    levels: LevelLocations,
+    options: &DecoderOptions,
+) -> impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin {
+    use async_stream::try_stream;
+    use std::cmp::max;
+
+    let info = info.clone();
+    let nfaces = max(1, info.number_of_faces);
+    let pool = options.buffer_pool.clone();
+
+    Box::pin(try_stream! {
+        let mut read = read;
+        let LevelLocations { levels, truncation } = levels;
+        for (level, (offset, buf_size, nframes)) in levels.into_iter().enumerate().rev() {
+            let level = level as u32;
+            let (pixel_width, pixel_height, pixel_depth) = info.mipmap_size(level);
+
+            io::seek(&mut read, SeekFrom::Start(offset)).await.at_offset(offset).in_level(level)?;
+            let mut offset = offset;
+            for i in 0..nframes {
+                let (layer, face) = (i / nfaces, i % nfaces);
+                let mut buf = pool::take(pool.as_ref(), buf_size as usize);
+                io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_frame(level, layer, face)?;
+                offset += u64::from(buf_size);
+                let frame_info = FrameInfo {
+                    level,
+                    layer,
+                    face,
+                    pixel_width,
+                    pixel_height,
+                    pixel_depth,
+                };
+                yield (frame_info, buf);
+            }
+        }
+        if let Some(e) = truncation {
+            Err(e)?;
+        }
+    })
+}
+
+fn new_chunk_stream(
+    read: impl AsyncRead + Unpin,
+    info: &HeaderInfo,
+    generate_mipmaps: Option<mipmap::Filter>,
+    options: &DecoderOptions,
+    max_chunk_size: usize,
+    max_frame_size: u64,
+) -> impl Stream<Item = Result<Chunk>> + Unpin {
+    use async_stream::try_stream;
+    use futures_core::stream::Stream as _;
+    use std::cmp::{max, min};
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let info = info.clone();
+    let options = options.clone();
+    let nlayers = max(1, info.number_of_array_elements);
+    let nfaces = max(1, info.number_of_faces);
+    let is_cubemap = info.header().is_cubemap();
+
+    Box::pin(try_stream! {
+        let mut read = read;
+
+        // Generated levels need all of level 0
+        if generate_mipmaps.is_some() {
+            let mut frames = new_async_stream(read, &info, generate_mipmaps, &options);
+            while let Some(frame) = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await {
+                let (frame, buf) = frame?;
+                let rows = frame.pixel_height.saturating_mul(frame.pixel_depth);
+                yield (frame, 0..rows, buf);
+            }
+            return;
+        }
+
+        let mut offset = info.header().data_offset();
+        let mut total = 0;
+        for level in 0..info.number_of_mipmap_levels {
+            let mut buf = [0_u8; 4];
+            io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_level(level)?;
+            let image_size = u32::from_ne_bytes(buf);
+            let buf_size = add_level_size(&mut total, &info.header(), level, image_size, is_cubemap, &options.limits)
+                .at_offset(offset)
+                .in_level(level)? as usize;
+            offset += 4;
+
+            let (width, height, depth) = info.mipmap_size(level);
+            let rows = row_layout(&info.header(), width, height, depth, buf_size);
+
+            for layer in 0..nlayers {
+                for face in 0..nfaces {
+                    let frame_info = FrameInfo {
+                        level,
+                        layer,
+                        face,
+                        pixel_width: width,
+                        pixel_height: height,
+                        pixel_depth: depth,
+                    };
+                    let (block_height, row_size) = match rows {
+                        Some(x) => x,
+                        None => {
+                            check_limit("frame size", buf_size as u64, max_frame_size).at_offset(offset).in_frame(level, layer, face)?;
+                            let mut buf = pool::take(options.buffer_pool.as_ref(), buf_size);
+                            io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_frame(level, layer, face)?;
+                            offset += buf_size as u64;
+                            yield (frame_info, 0..height.saturating_mul(depth), buf);
+                            continue;
+                        }
+                    };
+
+                    let block_rows = height.div_ceil(block_height);
+                    let rows_per_chunk = (max_chunk_size / row_size).clamp(1, block_rows as usize) as u32;
+                    for z in 0..depth {
+                        let mut row = 0;
+                        while row < block_rows {
+                            let n = min(rows_per_chunk, block_rows - row);
+                            let mut buf = pool::take(options.buffer_pool.as_ref(), n as usize * row_size);
+                            io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_frame(level, layer, face)?;
+                            offset += buf.len() as u64;
+                            let start = z * height + row * block_height;
+                            let end = z * height + min(height, (row + n).saturating_mul(block_height));
+                            yield (frame_info.clone(), start..end, buf);
+                            row += n;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn new_level_stream(
+    read: impl AsyncRead + Unpin,
+    info: &HeaderInfo,
+    generate_mipmaps: Option<mipmap::Filter>,
+    options: &DecoderOptions,
+) -> impl Stream<Item = Result<(LevelInfo, Vec<u8>)>> + Unpin {
+    use async_stream::try_stream;
+    use futures_core::stream::Stream as _;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let info = info.clone();
+    let options = options.clone();
+
+    Box::pin(try_stream! {
+        let mut read = read;
+        let pool = options.buffer_pool.clone();
+
+        // Generated levels come frame by frame
+        if generate_mipmaps.is_some() {
+            let mut frames = new_async_stream(read, &info, generate_mipmaps, &options);
+            let mut current: Option<(LevelInfo, Vec<u8>)> = None;
+            while let Some(frame) = poll_fn(|cx| Pin::new(&mut frames).poll_next(cx)).await {
+                let (frame, buf) = frame?;
+                if current.as_ref().map(|(level, _)| level.level) != Some(frame.level) {
+                    if let Some(level) = current.take() {
+                        yield level;
+                    }
+                    let level = level_info(&info, frame.level, buf.len() as u32);
+                    let data = Vec::with_capacity(level.size());
+                    current = Some((level, data));
+                }
+                if let Some((_, data)) = &mut current {
+                    data.extend_from_slice(&buf);
+                }
+                if let Some(pool) = &pool {
+                    pool.put(buf);
+                }
+            }
+            if let Some(level) = current {
+                yield level;
+            }
+            return;
+        }
+
+        let mut offset = info.header().data_offset();
+        let mut total = 0;
+        for level in 0..info.number_of_mipmap_levels {
+            let level = read_level_info(&mut read, &info, level, offset, &mut total, &options).await?;
+            offset += 4;
+            let mut buf = pool::take(pool.as_ref(), level.size());
+            read_level_data(&mut read, &level, &mut buf, offset).await?;
+            offset += buf.len() as u64;
+            yield (level, buf);
+        }
+    })
+}
+
+/// Layout of a level whose frames have `frame_size` bytes
+fn level_info(info: &HeaderInfo, level: u32, frame_size: u32) -> LevelInfo {
+    use std::cmp::max;
+
+    let (pixel_width, pixel_height, pixel_depth) = info.mipmap_size(level);
+    LevelInfo {
+        level,
+        pixel_width,
+        pixel_height,
+        pixel_depth,
+        number_of_layers: max(1, info.number_of_array_elements),
+        number_of_faces: max(1, info.number_of_faces),
+        frame_size,
+    }
+}
+
+/// Read the `imageSize` field of a level at `offset`, adding the size
+/// of the level to `total`
+async fn read_level_info(
+    read: &mut (impl AsyncRead + Unpin),
+    info: &HeaderInfo,
+    level: u32,
+    offset: u64,
+    total: &mut u64,
+    options: &DecoderOptions,
+) -> Result<LevelInfo> {
+    let is_cubemap = info.header().is_cubemap();
+
+    let mut buf = [0_u8; 4];
+    io::read_exact(read, &mut buf)
+        .await
+        .at_offset(offset)
+        .in_level(level)?;
+    let image_size = u32::from_ne_bytes(buf);
+    let frame_size = add_level_size(
+        total,
+        &info.header(),
+        level,
+        image_size,
+        is_cubemap,
+        &options.limits,
+    )
+    .at_offset(offset)
+    .in_level(level)?;
+    Ok(level_info(info, level, frame_size))
+}
+
+/// Read the frames of a level starting at `offset` into `buf`
+async fn read_level_data(
+    read: &mut (impl AsyncRead + Unpin),
+    level: &LevelInfo,
+    buf: &mut [u8],
+    offset: u64,
+) -> Result<()> {
+    for (frame, range) in level.frames() {
+        let frame_offset = offset + range.start as u64;
+        io::read_exact(read, &mut buf[range])
+            .await
+            .at_offset(frame_offset)
+            .in_frame(frame.level, frame.layer, frame.face)?;
+    }
+    Ok(())
+}
+
+/// Block height and size of a row of blocks (or of a padded row of
+/// pixels) of an image, or `None` if the data is not stored row by row
+fn row_layout(
+    header: &Header<'_>,
+    width: u32,
+    height: u32,
+    depth: u32,
+    buf_size: usize,
+) -> Option<(u32, usize)> {
+    let (block_height, row_size) = if header.gl_type != 0 {
+        let pixel_size = convert::pixel_size(header.gl_format, header.gl_type)?;
+        (1, (width as usize * pixel_size + 3) & !3)
+    } else if format::is_pvrtc(header.gl_internal_format) {
+        return None;
+    } else {
+        let block = format::compressed_block_size(header.gl_internal_format)?;
+        let row_size = width.div_ceil(block.width) as usize * block.bytes as usize;
+        (block.height, row_size)
+    };
+    let rows = height.div_ceil(block_height) as usize * depth as usize;
+    if row_size == 0 || row_size.checked_mul(rows) != Some(buf_size) {
+        return None;
+    }
+    // Rows are numbered with u32
+    height.checked_mul(depth)?;
+    Some((block_height, row_size))
+}
+
+fn new_async_stream(
+    read: impl AsyncRead + Unpin,
+    info: &HeaderInfo,
+    generate_mipmaps: Option<mipmap::Filter>,
+    options: &DecoderOptions,
+) -> impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin {
+    use async_stream::try_stream;
+    use byteorder::{ByteOrder as _, NativeEndian as NE};
+    use std::cmp::max;
+
+    // Prepare parameters for the stream
+    let pixel_width = info.pixel_width;
+    let pixel_height = info.pixel_height;
+    let pixel_depth = info.pixel_depth;
+    let nlayers = max(1, info.number_of_array_elements);
+    let nfaces = max(1, info.number_of_faces);
+    let nlevels = if generate_mipmaps.is_some() {
+        1
+    } else {
+        info.number_of_mipmap_levels
+    };
+    let generator_info = generate_mipmaps.map(|filter| (info.clone(), filter));
+    let limits = options.limits.clone();
+    let lenient = options.lenient;
+    let pool = options.buffer_pool.clone();
+    let header = info.header().without_key_values();
+    let data_offset = info.header().data_offset();
+    let is_cubemap = info.header().is_cubemap();
+
+    Box::pin(try_stream! {
+        let mut read = read;
+        let mut level0 = vec![];
+        let mut offset = data_offset;
+        let mut total = 0;
+        for level in 0..nlevels {
+            let image_size = {
+                let mut buf = [0_u8; 4];
+                io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_level(level)?;
+                NE::read_u32(&buf)
+            };
+
+            // dimensions of the current mipmap level
+            let pixel_width = max(1, pixel_width.checked_shr(level).unwrap_or(0));
+            let pixel_height = max(1, pixel_height.checked_shr(level).unwrap_or(0));
+            let pixel_depth = max(1, pixel_depth.checked_shr(level).unwrap_or(0));
+
+            // Compute buffer size
+            let buf_size = add_level_size(&mut total, &header, level, image_size, is_cubemap, &limits)
+                .at_offset(offset)
+                .in_level(level)? as usize;
+            offset += 4;
+
+            // Read pixels
+            for layer in 0..nlayers {
+                for face in 0..nfaces {
+                    let mut buf = pool::take(pool.as_ref(), buf_size);
+                    let result = io::read_exact(&mut read, &mut buf).await.at_offset(offset).in_frame(level, layer, face);
+                    match result {
+                        // Level 0 cannot be completed: yield what has been read
+                        Err(e) if lenient && e.is_truncation() => {
+                            for frame in level0 {
+                                yield frame;
+                            }
+                            Err(e)?;
+                            return;
+                        }
+                        result => result?,
+                    };
+                    offset += buf_size as u64;
+                    let frame_info = FrameInfo {
+                        level,
+                        layer,
+                        face,
+                        pixel_width,
+                        pixel_height,
+                        pixel_depth,
+                    };
+                    match &generator_info {
+                        Some(_) => level0.push((frame_info, buf)),
+                        None => yield (frame_info, buf),
+                    }
+                }
+            }
+        }
+
+        // Yield level 0 followed by the generated levels
+        if let Some((info, filter)) = generator_info {
+            let mut chains = vec![];
+            for (frame_info, buf) in &level0 {
+                let frames = mipmap::generate(&info, frame_info, buf, filter)
+                    .in_frame(frame_info.level, frame_info.layer, frame_info.face)?;
+                chains.push(frames.into_iter());
+            }
+            for frame in level0 {
+                yield frame;
+            }
+            for _ in 1..info.number_of_mipmap_levels {
+                for chain in &mut chains {
+                    yield chain.next().unwrap();
+                }
+            }
+        }
+    })
+}
+
+async fn read_header_async(
+    reader: &mut (impl AsyncRead + Unpin),
+    options: &DecoderOptions,
+) -> Result<HeaderInfo> {
+    let mut buf = [0_u8; 64];
+    io::read_exact(reader, &mut buf).await.at_offset(0)?;
+    let generate_mipmaps = options.generate_mipmaps.is_some();
+    let (header, key_value_bytes) = parse::parse_header(&buf, &options.limits, generate_mipmaps)?;
+
+    let mut kvbuf = vec![0; key_value_bytes as usize];
+    io::read_exact(reader, &mut kvbuf).await.at_offset(64)?;
+    header_info(header, kvbuf)
+}
+
+/// Check the key/value data read after a header
+fn header_info(header: parse::Header<'_>, kvbuf: Vec<u8>) -> Result<HeaderInfo> {
+    parse::check_key_value_data(&kvbuf)?;
+    let mut info = HeaderInfo::from(header);
+    info.key_value_data = KeyValueData { raw: kvbuf };
+    Ok(info)
+}
@@ -0,0 +1,176 @@
+//! KTX Encoder
+//!
+//! Writes frames laid out like those of the `Decoder` stream back into
+//! a KTX 1.1 or KTX 2.0 file.
+
+use crate::error::{bail, ResultExt as _};
+use crate::io::{self, AsyncWrite};
+use crate::{format, ktx2, ErrorKind, FrameInfo, HeaderInfo, Result, ENDIANNESS, MAGIC};
+use std::convert::TryFrom as _;
+
+/// KTX encoder
+pub struct Encoder<W> {
+    write: W,
+}
+
+impl<W> Encoder<W> {
+    pub fn new(write: W) -> Self {
+        Encoder { write }
+    }
+}
+
+impl<W> Encoder<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Write a KTX 1.1 file
+    ///
+    /// `frames` must be ordered like the frames of the `Decoder`
+    /// stream: by level, then by layer, then by face, with rows of
+    /// uncompressed data padded to 4 bytes. All frames of a level must
+    /// have the same size.
+    ///
+    /// Returns the writer.
+    pub async fn write_async<B: AsRef<[u8]>>(
+        self,
+        info: &HeaderInfo,
+        frames: &[(FrameInfo, B)],
+    ) -> Result<W> {
+        let level_sizes = check_frames(info, frames)?;
+        let is_cubemap = info.header().is_cubemap();
+        let frames_per_level = frames.len() / level_sizes.len();
+        let mut image_sizes = Vec::with_capacity(level_sizes.len());
+        for (level, &frame_size) in level_sizes.iter().enumerate() {
+            let image_size = if is_cubemap {
+                frame_size as u64
+            } else {
+                frame_size as u64 * frames_per_level as u64
+            };
+            match u32::try_from(image_size) {
+                Ok(x) => image_sizes.push(x),
+                Err(_) => {
+                    let e = ErrorKind::ImageSizeOverflow(image_size);
+                    return Err(e).in_level(level as u32);
+                }
+            }
+        }
+        let mut write = self.write;
+
+        let mut header = Vec::with_capacity(64);
+        header.extend_from_slice(&MAGIC);
+        for x in [
+            ENDIANNESS,
+            info.gl_type,
+            info.gl_type_size,
+            info.gl_format,
+            info.gl_internal_format,
+            info.gl_base_internal_format,
+            info.pixel_width,
+            info.pixel_height,
+            info.pixel_depth,
+            info.number_of_array_elements,
+            info.number_of_faces,
+            info.number_of_mipmap_levels,
+            info.key_value_data.raw.len() as u32,
+        ]
+        .iter()
+        {
+            header.extend_from_slice(&x.to_ne_bytes());
+        }
+        io::write_all(&mut write, &header).await?;
+        io::write_all(&mut write, &info.key_value_data.raw).await?;
+
+        let levels = frames.chunks(frames_per_level).zip(&level_sizes);
+        for ((level_frames, &frame_size), &image_size) in levels.zip(&image_sizes) {
+            io::write_all(&mut write, &image_size.to_ne_bytes()).await?;
+            for (_, buf) in level_frames {
+                io::write_all(&mut write, buf.as_ref()).await?;
+                // cubePadding
+                if is_cubemap {
+                    io::write_all(&mut write, padding(frame_size)).await?;
+                }
+            }
+            // mipPadding
+            if !is_cubemap {
+                io::write_all(&mut write, padding(image_size as usize)).await?;
+            }
+        }
+
+        io::flush(&mut write).await?;
+        Ok(write)
+    }
+
+    /// Write a KTX 2.0 file
+    ///
+    /// Takes the same input as `write_async`. Only uncompressed formats
+    /// with a `VkFormat` equivalent are supported (see
+    /// `ktx2::vk_format`); the key/value data is converted to KTX 2.0
+    /// conventions.
+    ///
+    /// Returns the writer.
+    pub async fn write_ktx2_async<B: AsRef<[u8]>>(
+        self,
+        info: &HeaderInfo,
+        frames: &[(FrameInfo, B)],
+    ) -> Result<W> {
+        check_frames(info, frames)?;
+        let data = ktx2::encode(info, frames)?;
+        let mut write = self.write;
+        io::write_all(&mut write, &data).await?;
+        io::flush(&mut write).await?;
+        Ok(write)
+    }
+}
+
+/// Check the order and sizes of the frames, and return the frame size
+/// of each level
+fn check_frames<B: AsRef<[u8]>>(
+    info: &HeaderInfo,
+    frames: &[(FrameInfo, B)],
+) -> Result<Vec<usize>> {
+    use std::cmp::max;
+
+    let nlevels = max(1, info.number_of_mipmap_levels);
+    let nlayers = max(1, info.number_of_array_elements);
+    let nfaces = max(1, info.number_of_faces);
+    if nlevels > 32 {
+        bail!(ErrorKind::InvalidNumberOfMipmapLevels(nlevels));
+    }
+    let expected = nlevels as usize * nlayers as usize * nfaces as usize;
+    if frames.len() != expected {
+        bail!(ErrorKind::InvalidFrameCount(expected, frames.len()));
+    }
+
+    let mut level_sizes = vec![];
+    let mut frames = frames.iter();
+    for level in 0..nlevels {
+        let (width, height, depth) = info.mipmap_size(level);
+        let mut level_size = format::image_size(info, width, height, depth).map(|x| x as usize);
+        for layer in 0..nlayers {
+            for face in 0..nfaces {
+                let (frame, buf) = frames.next().unwrap();
+                if (frame.level, frame.layer, frame.face) != (level, layer, face) {
+                    bail!(ErrorKind::FrameOutOfOrder(
+                        frame.level,
+                        frame.layer,
+                        frame.face
+                    ));
+                }
+                let size = buf.as_ref().len();
+                match level_size {
+                    Some(expect) if expect != size => {
+                        bail!(ErrorKind::InvalidBufferSize(expect, size))
+                    }
+                    Some(_) => {}
+                    None => level_size = Some(size),
+                }
+            }
+        }
+        level_sizes.push(level_size.unwrap());
+    }
+    Ok(level_sizes)
+}
+
+fn padding(size: usize) -> &'static [u8] {
+    &[0; 3][..(4 - size % 4) % 4]
+}
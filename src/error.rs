@@ -0,0 +1,303 @@
+//! Errors
+//!
+//! An `Error` is an `ErrorKind` together with where it happened, as far
+//! as it is known: the byte offset in the file, and the level, layer
+//! and face of the frame being decoded.
+
+use crate::gl;
+use alloc::string::String;
+use core::fmt;
+
+/// Result of the operations of this crate
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Error of the operations of this crate
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    level: Option<u32>,
+    layer_face: Option<(u32, u32)>,
+}
+
+/// What went wrong
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Error of the reader or the writer
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The data ends before the end of the file (see `parse`)
+    UnexpectedEnd,
+    /// The file does not start with the KTX identifier
+    InvalidFormat([u8; 12]),
+    /// The endianness field (expected, actual) is not the native one
+    MismatchedEndianness(u32, u32),
+    /// `numberOfMipmapLevels` (or the KTX 2.0 `levelCount`) is invalid
+    InvalidNumberOfMipmapLevels(u32),
+    /// `numberOfFaces` is not 0, 1 or 6
+    InvalidNumberOfFaces(u32),
+    /// `imageSize` (image size, layers, faces) does not split into
+    /// 4-byte aligned frames
+    InvalidImageSize(u32, u32, u32),
+    /// `imageSize` (expected, actual) does not match the size of the
+    /// images of a known format
+    MismatchedImageSize(u64, u32),
+    /// The key/value data holds an invalid pair
+    InvalidKeyValueData,
+    /// The dimensions, faces and array elements match no kind of
+    /// texture (see `TextureKind`)
+    InvalidDimensions(&'static str),
+    /// Unsupported `glInternalFormat`
+    UnsupportedFormat(u32),
+    /// Size of a buffer (expected, actual)
+    InvalidBufferSize(usize, usize),
+    /// Unsupported `glFormat` and `glType`
+    UnsupportedPixelFormat(u32, u32),
+    /// A value of the file (name, value, limit) exceeds a `Limits` field
+    LimitExceeded(&'static str, u64, u64),
+    /// The blocks of a frame of a compressed format (`glInternalFormat`)
+    /// cannot be flipped losslessly (see `orientation::reorient`)
+    UnflippableBlocks(u32),
+    /// Number of frames (expected, actual) given to the `Encoder`
+    InvalidFrameCount(usize, usize),
+    /// A frame given to the `Encoder` (level, layer, face) is not in
+    /// the order of the `Decoder` stream
+    FrameOutOfOrder(u32, u32, u32),
+    /// The `imageSize` of a level does not fit in 32 bits
+    ImageSizeOverflow(u64),
+    /// `FrameOrder::SmallestFirst` was asked of a reader that cannot
+    /// seek (use `Decoder::read_seekable_async`)
+    NeedsSeek,
+    /// `DecoderOptions::generate_mipmaps` was asked of the synchronous
+    /// `Decoder::read`
+    NeedsAsyncReader,
+    /// Any other invalid data or argument
+    Msg(String),
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ErrorKind {
+        self.kind
+    }
+
+    /// Byte offset in the file of the data that failed
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    /// Mipmap level being decoded
+    pub fn level(&self) -> Option<u32> {
+        self.level
+    }
+
+    /// Array layer being decoded
+    pub fn layer(&self) -> Option<u32> {
+        self.layer_face.map(|(layer, _)| layer)
+    }
+
+    /// Cubemap face being decoded
+    pub fn face(&self) -> Option<u32> {
+        self.layer_face.map(|(_, face)| face)
+    }
+
+    /// Whether the input ended before the end of the data
+    pub fn is_truncation(&self) -> bool {
+        match &self.kind {
+            #[cfg(feature = "std")]
+            ErrorKind::Io(e) => e.kind() == std::io::ErrorKind::UnexpectedEof,
+            ErrorKind::UnexpectedEnd => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        match (self.level, self.layer_face) {
+            (Some(level), Some((layer, face))) => {
+                write!(f, " (level {}, layer {}, face {})", level, layer, face)
+            }
+            (Some(level), None) => write!(f, " (level {})", level),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Symbolic name of a GL enum, or its value if it has none
+struct FormatName(u32);
+
+impl fmt::Display for FormatName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match gl::name(self.0) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#X}", self.0),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            ErrorKind::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                write!(f, "unexpected end of file")
+            }
+            #[cfg(feature = "std")]
+            ErrorKind::Io(e) => write!(f, "I/O error: {}", e),
+            ErrorKind::UnexpectedEnd => write!(f, "unexpected end of file"),
+            ErrorKind::InvalidFormat(magic) => {
+                write!(f, "not a KTX file (identifier {:02X?})", magic)
+            }
+            ErrorKind::MismatchedEndianness(expect, actual) => write!(
+                f,
+                "endianness {:#010X} is not the native {:#010X}",
+                actual, expect
+            ),
+            ErrorKind::InvalidNumberOfMipmapLevels(n) => {
+                write!(f, "invalid number of mipmap levels {}", n)
+            }
+            ErrorKind::InvalidNumberOfFaces(n) => write!(f, "invalid numberOfFaces {}", n),
+            ErrorKind::InvalidImageSize(image_size, nlayers, nfaces) => write!(
+                f,
+                "imageSize {} does not split into 4-byte aligned frames of {} layers and {} faces",
+                image_size, nlayers, nfaces
+            ),
+            ErrorKind::MismatchedImageSize(expect, actual) => {
+                write!(
+                    f,
+                    "imageSize {} does not match the expected {}",
+                    actual, expect
+                )
+            }
+            ErrorKind::InvalidKeyValueData => write!(f, "invalid key/value pair"),
+            ErrorKind::InvalidDimensions(what) => write!(f, "invalid texture: {}", what),
+            ErrorKind::UnsupportedFormat(x) => {
+                write!(f, "unsupported internal format {}", FormatName(*x))
+            }
+            ErrorKind::InvalidBufferSize(expect, actual) => {
+                write!(f, "buffer of {} bytes, expected {}", actual, expect)
+            }
+            ErrorKind::UnsupportedPixelFormat(gl_format, gl_type) => write!(
+                f,
+                "unsupported pixel format {} with type {}",
+                FormatName(*gl_format),
+                FormatName(*gl_type)
+            ),
+            ErrorKind::LimitExceeded(what, value, limit) => {
+                write!(f, "{} of {} exceeds the limit of {}", what, value, limit)
+            }
+            ErrorKind::UnflippableBlocks(x) => {
+                write!(f, "cannot flip the {} blocks losslessly", FormatName(*x))
+            }
+            ErrorKind::InvalidFrameCount(expect, actual) => {
+                write!(f, "expected {} frames, got {}", expect, actual)
+            }
+            ErrorKind::FrameOutOfOrder(level, layer, face) => write!(
+                f,
+                "frame (level {}, layer {}, face {}) is out of order",
+                level, layer, face
+            ),
+            ErrorKind::ImageSizeOverflow(size) => {
+                write!(f, "imageSize {} does not fit in 32 bits", size)
+            }
+            ErrorKind::NeedsSeek => write!(
+                f,
+                "FrameOrder::SmallestFirst needs a seekable reader (read_seekable_async)"
+            ),
+            ErrorKind::NeedsAsyncReader => write!(
+                f,
+                "DecoderOptions::generate_mipmaps needs an asynchronous reader (read_async)"
+            ),
+            ErrorKind::Msg(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            kind,
+            offset: None,
+            level: None,
+            layer_face: None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        ErrorKind::Io(e).into()
+    }
+}
+
+/// Context of the errors of a `Result`. The innermost context wins:
+/// information already in the error is kept.
+pub(crate) trait ResultExt<T> {
+    /// Byte offset in the file
+    fn at_offset(self, offset: u64) -> Result<T>;
+    /// Mipmap level being decoded
+    fn in_level(self, level: u32) -> Result<T>;
+    /// Frame being decoded
+    fn in_frame(self, level: u32, layer: u32, face: u32) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for core::result::Result<T, E> {
+    fn at_offset(self, offset: u64) -> Result<T> {
+        self.map_err(|e| {
+            let mut e = e.into();
+            e.offset.get_or_insert(offset);
+            e
+        })
+    }
+
+    fn in_level(self, level: u32) -> Result<T> {
+        self.map_err(|e| {
+            let mut e = e.into();
+            e.level.get_or_insert(level);
+            e
+        })
+    }
+
+    fn in_frame(self, level: u32, layer: u32, face: u32) -> Result<T> {
+        self.map_err(|e| {
+            let mut e = e.into();
+            if e.level.is_none() {
+                e.level = Some(level);
+                e.layer_face = Some((layer, face));
+            }
+            e
+        })
+    }
+}
+
+/// Return an `Error` made from an `ErrorKind`, or an `ErrorKind::Msg`
+/// formatted like `format!`
+macro_rules! bail {
+    ($fmt:literal $(, $arg:expr)* $(,)?) => {
+        return Err($crate::Error::from($crate::ErrorKind::Msg(alloc::format!($fmt $(, $arg)*))))
+    };
+    ($e:expr) => {
+        return Err($crate::Error::from($e))
+    };
+}
+
+pub(crate) use bail;
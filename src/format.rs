@@ -0,0 +1,285 @@
+//! Texel Block Layout of Internal Formats
+//!
+//! Sizes of compressed texel blocks and of whole images, as needed to
+//! check `imageSize` fields or to walk the blocks of a frame.
+
+use crate::{codec::pvrtc, convert, gl, parse::Header, HeaderInfo};
+
+/// Texel block of a compressed format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockSize {
+    /// Width of a block in pixels
+    pub width: u32,
+    /// Height of a block in pixels
+    pub height: u32,
+    /// Size of a block in bytes
+    pub bytes: u32,
+}
+
+/// Block size of a compressed internal format,
+/// or `None` if the format is unknown or not compressed.
+pub fn compressed_block_size(gl_internal_format: u32) -> Option<BlockSize> {
+    let block = |width, height, bytes| {
+        Some(BlockSize {
+            width,
+            height,
+            bytes,
+        })
+    };
+    match gl_internal_format {
+        gl::COMPRESSED_RGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_RGBA_S3TC_DXT1_EXT
+        | gl::COMPRESSED_SRGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT
+        | gl::COMPRESSED_RED_RGTC1
+        | gl::COMPRESSED_SIGNED_RED_RGTC1
+        | gl::ETC1_RGB8_OES
+        | gl::COMPRESSED_R11_EAC
+        | gl::COMPRESSED_SIGNED_R11_EAC
+        | gl::COMPRESSED_RGB8_ETC2
+        | gl::COMPRESSED_SRGB8_ETC2
+        | gl::COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2
+        | gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2
+        | gl::ATC_RGB_AMD => block(4, 4, 8),
+        gl::COMPRESSED_RGBA_S3TC_DXT3_EXT
+        | gl::COMPRESSED_RGBA_S3TC_DXT5_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT
+        | gl::COMPRESSED_RG_RGTC2
+        | gl::COMPRESSED_SIGNED_RG_RGTC2
+        | gl::COMPRESSED_RGBA_BPTC_UNORM
+        | gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+        | gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT
+        | gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT
+        | gl::COMPRESSED_RG11_EAC
+        | gl::COMPRESSED_SIGNED_RG11_EAC
+        | gl::COMPRESSED_RGBA8_ETC2_EAC
+        | gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC
+        | gl::ATC_RGBA_EXPLICIT_ALPHA_AMD
+        | gl::ATC_RGBA_INTERPOLATED_ALPHA_AMD => block(4, 4, 16),
+        gl::COMPRESSED_RGB_PVRTC_4BPPV1_IMG
+        | gl::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG
+        | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT
+        | gl::COMPRESSED_RGBA_PVRTC_4BPPV2_IMG => block(4, 4, 8),
+        gl::COMPRESSED_RGB_PVRTC_2BPPV1_IMG
+        | gl::COMPRESSED_RGBA_PVRTC_2BPPV1_IMG
+        | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT
+        | gl::COMPRESSED_RGBA_PVRTC_2BPPV2_IMG => block(8, 4, 8),
+        gl::COMPRESSED_RGBA_ASTC_4x4_KHR..=gl::COMPRESSED_RGBA_ASTC_12x12_KHR => {
+            let (w, h) = astc_block(gl_internal_format - gl::COMPRESSED_RGBA_ASTC_4x4_KHR);
+            block(w, h, 16)
+        }
+        gl::COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR..=gl::COMPRESSED_SRGB8_ALPHA8_ASTC_12x12_KHR => {
+            let (w, h) = astc_block(gl_internal_format - gl::COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR);
+            block(w, h, 16)
+        }
+        _ => None,
+    }
+}
+
+fn astc_block(index: u32) -> (u32, u32) {
+    const SIZES: [(u32, u32); 14] = [
+        (4, 4),
+        (5, 4),
+        (5, 5),
+        (6, 5),
+        (6, 6),
+        (8, 5),
+        (8, 6),
+        (8, 8),
+        (10, 5),
+        (10, 6),
+        (10, 8),
+        (10, 10),
+        (12, 10),
+        (12, 12),
+    ];
+    SIZES[index as usize]
+}
+
+/// Size in bytes of one image (a single face of a single layer) of
+/// the given dimensions, including the row padding of uncompressed
+/// formats, or `None` if the format is unknown.
+pub fn image_size(info: &HeaderInfo, width: u32, height: u32, depth: u32) -> Option<u64> {
+    header_image_size(&info.header(), width, height, depth)
+}
+
+/// `image_size` of a `parse::Header`
+pub(crate) fn header_image_size(
+    info: &Header<'_>,
+    width: u32,
+    height: u32,
+    depth: u32,
+) -> Option<u64> {
+    let (width, height, depth) = (u64::from(width), u64::from(height), u64::from(depth));
+
+    if info.gl_type != 0 {
+        let pixel_size = convert::pixel_size(info.gl_format, info.gl_type)? as u64;
+        let row_stride = (width * pixel_size + 3) & !3;
+        return Some(row_stride.saturating_mul(height).saturating_mul(depth));
+    }
+
+    // PVRTC1 images are at least 2x2 blocks
+    let pvrtc_bpp = match info.gl_internal_format {
+        gl::COMPRESSED_RGB_PVRTC_4BPPV1_IMG
+        | gl::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG
+        | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT => Some(pvrtc::Bpp::Four),
+        gl::COMPRESSED_RGB_PVRTC_2BPPV1_IMG
+        | gl::COMPRESSED_RGBA_PVRTC_2BPPV1_IMG
+        | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT => Some(pvrtc::Bpp::Two),
+        _ => None,
+    };
+    if let Some(bpp) = pvrtc_bpp {
+        let size = pvrtc::image_size(width as u32, height as u32, bpp) as u64;
+        return Some(size.saturating_mul(depth));
+    }
+
+    let block = compressed_block_size(info.gl_internal_format)?;
+    let nx = width.div_ceil(u64::from(block.width));
+    let ny = height.div_ceil(u64::from(block.height));
+    Some(
+        (nx * ny)
+            .saturating_mul(depth)
+            .saturating_mul(u64::from(block.bytes)),
+    )
+}
+
+/// Whether blocks of a compressed format are stored in Morton order
+/// (PVRTC) rather than row by row
+#[cfg(feature = "std")]
+pub(crate) fn is_pvrtc(gl_internal_format: u32) -> bool {
+    matches!(
+        gl_internal_format,
+        gl::COMPRESSED_RGB_PVRTC_4BPPV1_IMG
+            | gl::COMPRESSED_RGBA_PVRTC_4BPPV1_IMG
+            | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT
+            | gl::COMPRESSED_RGB_PVRTC_2BPPV1_IMG
+            | gl::COMPRESSED_RGBA_PVRTC_2BPPV1_IMG
+            | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT
+            | gl::COMPRESSED_RGBA_PVRTC_2BPPV2_IMG
+            | gl::COMPRESSED_RGBA_PVRTC_4BPPV2_IMG
+    )
+}
+
+/// `glFormat` and `glType` of an uncompressed sized internal format,
+/// or `None` if the format is unknown or compressed.
+pub fn pixel_format(gl_internal_format: u32) -> Option<(u32, u32)> {
+    let pair = match gl_internal_format {
+        gl::R8 => (gl::RED, gl::UNSIGNED_BYTE),
+        gl::RG8 => (gl::RG, gl::UNSIGNED_BYTE),
+        gl::RGB8 | gl::SRGB8 => (gl::RGB, gl::UNSIGNED_BYTE),
+        gl::RGBA8 | gl::SRGB8_ALPHA8 => (gl::RGBA, gl::UNSIGNED_BYTE),
+        gl::R8_SNORM => (gl::RED, gl::BYTE),
+        gl::RG8_SNORM => (gl::RG, gl::BYTE),
+        gl::RGB8_SNORM => (gl::RGB, gl::BYTE),
+        gl::RGBA8_SNORM => (gl::RGBA, gl::BYTE),
+        gl::R16 => (gl::RED, gl::UNSIGNED_SHORT),
+        gl::RG16 => (gl::RG, gl::UNSIGNED_SHORT),
+        gl::RGB16 => (gl::RGB, gl::UNSIGNED_SHORT),
+        gl::RGBA16 => (gl::RGBA, gl::UNSIGNED_SHORT),
+        gl::R16_SNORM => (gl::RED, gl::SHORT),
+        gl::RG16_SNORM => (gl::RG, gl::SHORT),
+        gl::RGB16_SNORM => (gl::RGB, gl::SHORT),
+        gl::RGBA16_SNORM => (gl::RGBA, gl::SHORT),
+        gl::R16F => (gl::RED, gl::HALF_FLOAT),
+        gl::RG16F => (gl::RG, gl::HALF_FLOAT),
+        gl::RGB16F => (gl::RGB, gl::HALF_FLOAT),
+        gl::RGBA16F => (gl::RGBA, gl::HALF_FLOAT),
+        gl::R32F => (gl::RED, gl::FLOAT),
+        gl::RG32F => (gl::RG, gl::FLOAT),
+        gl::RGB32F => (gl::RGB, gl::FLOAT),
+        gl::RGBA32F => (gl::RGBA, gl::FLOAT),
+        gl::ALPHA8 => (gl::ALPHA, gl::UNSIGNED_BYTE),
+        gl::LUMINANCE8 | gl::SLUMINANCE8 => (gl::LUMINANCE, gl::UNSIGNED_BYTE),
+        gl::LUMINANCE8_ALPHA8 | gl::SLUMINANCE8_ALPHA8 => (gl::LUMINANCE_ALPHA, gl::UNSIGNED_BYTE),
+        gl::RGB565 => (gl::RGB, gl::UNSIGNED_SHORT_5_6_5),
+        gl::RGBA4 => (gl::RGBA, gl::UNSIGNED_SHORT_4_4_4_4),
+        gl::RGB5_A1 => (gl::RGBA, gl::UNSIGNED_SHORT_5_5_5_1),
+        gl::RGB10_A2 => (gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV),
+        gl::R11F_G11F_B10F => (gl::RGB, gl::UNSIGNED_INT_10F_11F_11F_REV),
+        gl::RGB9_E5 => (gl::RGB, gl::UNSIGNED_INT_5_9_9_9_REV),
+        _ => return None,
+    };
+    Some(pair)
+}
+
+/// Whether an internal format stores sRGB-encoded colour
+pub fn is_srgb(gl_internal_format: u32) -> bool {
+    matches!(
+        gl_internal_format,
+        gl::SRGB
+            | gl::SRGB8
+            | gl::SRGB_ALPHA
+            | gl::SRGB8_ALPHA8
+            | gl::SLUMINANCE
+            | gl::SLUMINANCE8
+            | gl::SLUMINANCE_ALPHA
+            | gl::SLUMINANCE8_ALPHA8
+            | gl::COMPRESSED_SRGB_S3TC_DXT1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM
+            | gl::COMPRESSED_SRGB8_ETC2
+            | gl::COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2
+            | gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC
+            | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT
+            | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT
+            | gl::COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT
+            | gl::COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR
+            ..=gl::COMPRESSED_SRGB8_ALPHA8_ASTC_12x12_KHR
+    )
+}
+
+/// `glBaseInternalFormat` of a sized uncompressed or a compressed
+/// internal format, or `None` if the format is unknown.
+pub fn base_internal_format(gl_internal_format: u32) -> Option<u32> {
+    if let Some((gl_format, _)) = pixel_format(gl_internal_format) {
+        return Some(gl_format);
+    }
+    let base = match gl_internal_format {
+        gl::COMPRESSED_RED_RGTC1
+        | gl::COMPRESSED_SIGNED_RED_RGTC1
+        | gl::COMPRESSED_R11_EAC
+        | gl::COMPRESSED_SIGNED_R11_EAC => gl::RED,
+        gl::COMPRESSED_RG_RGTC2
+        | gl::COMPRESSED_SIGNED_RG_RGTC2
+        | gl::COMPRESSED_RG11_EAC
+        | gl::COMPRESSED_SIGNED_RG11_EAC => gl::RG,
+        gl::COMPRESSED_RGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_SRGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_RGB_BPTC_SIGNED_FLOAT
+        | gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT
+        | gl::ETC1_RGB8_OES
+        | gl::COMPRESSED_RGB8_ETC2
+        | gl::COMPRESSED_SRGB8_ETC2
+        | gl::COMPRESSED_RGB_PVRTC_4BPPV1_IMG
+        | gl::COMPRESSED_RGB_PVRTC_2BPPV1_IMG
+        | gl::COMPRESSED_SRGB_PVRTC_2BPPV1_EXT
+        | gl::COMPRESSED_SRGB_PVRTC_4BPPV1_EXT
+        | gl::ATC_RGB_AMD => gl::RGB,
+        _ if compressed_block_size(gl_internal_format).is_some() => gl::RGBA,
+        _ => return None,
+    };
+    Some(base)
+}
+
+/// `glTypeSize` of a pixel type: the size of the data type, the size
+/// of a pixel for packed types, and 1 for compressed formats
+/// (`gl_type` 0).
+pub fn type_size(gl_format: u32, gl_type: u32) -> u32 {
+    match gl_type {
+        0 | gl::UNSIGNED_BYTE | gl::BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT | gl::HALF_FLOAT_OES => 2,
+        gl::UNSIGNED_INT | gl::INT | gl::FLOAT => 4,
+        // Packed types
+        _ => convert::pixel_size(gl_format, gl_type).unwrap_or(1) as u32,
+    }
+}
@@ -0,0 +1,223 @@
+//! OpenGL enumerations found in KTX headers
+//!
+//! `name` returns the symbolic name of a value, e.g. `GL_RGBA8`.
+
+#![allow(non_upper_case_globals)]
+
+enumerations! {
+    "GL_";
+    // Pixel types
+    BYTE = 0x1400,
+    UNSIGNED_BYTE = 0x1401,
+    SHORT = 0x1402,
+    UNSIGNED_SHORT = 0x1403,
+    INT = 0x1404,
+    UNSIGNED_INT = 0x1405,
+    FLOAT = 0x1406,
+    HALF_FLOAT = 0x140B,
+    HALF_FLOAT_OES = 0x8D61,
+    UNSIGNED_BYTE_3_3_2 = 0x8032,
+    UNSIGNED_SHORT_4_4_4_4 = 0x8033,
+    UNSIGNED_SHORT_5_5_5_1 = 0x8034,
+    UNSIGNED_INT_8_8_8_8 = 0x8035,
+    UNSIGNED_INT_10_10_10_2 = 0x8036,
+    UNSIGNED_BYTE_2_3_3_REV = 0x8362,
+    UNSIGNED_SHORT_5_6_5 = 0x8363,
+    UNSIGNED_SHORT_5_6_5_REV = 0x8364,
+    UNSIGNED_SHORT_4_4_4_4_REV = 0x8365,
+    UNSIGNED_SHORT_1_5_5_5_REV = 0x8366,
+    UNSIGNED_INT_8_8_8_8_REV = 0x8367,
+    UNSIGNED_INT_2_10_10_10_REV = 0x8368,
+    UNSIGNED_INT_24_8 = 0x84FA,
+    UNSIGNED_INT_10F_11F_11F_REV = 0x8C3B,
+    UNSIGNED_INT_5_9_9_9_REV = 0x8C3E,
+    FLOAT_32_UNSIGNED_INT_24_8_REV = 0x8DAD,
+
+    // Pixel formats (and unsized internal formats)
+    STENCIL_INDEX = 0x1901,
+    DEPTH_COMPONENT = 0x1902,
+    RED = 0x1903,
+    GREEN = 0x1904,
+    BLUE = 0x1905,
+    ALPHA = 0x1906,
+    RGB = 0x1907,
+    RGBA = 0x1908,
+    LUMINANCE = 0x1909,
+    LUMINANCE_ALPHA = 0x190A,
+    BGR = 0x80E0,
+    BGRA = 0x80E1,
+    RG = 0x8227,
+    RG_INTEGER = 0x8228,
+    DEPTH_STENCIL = 0x84F9,
+    RED_INTEGER = 0x8D94,
+    RGB_INTEGER = 0x8D98,
+    RGBA_INTEGER = 0x8D99,
+    BGR_INTEGER = 0x8D9A,
+    BGRA_INTEGER = 0x8D9B,
+
+    // Sized internal formats
+    R3_G3_B2 = 0x2A10,
+    ALPHA8 = 0x803C,
+    ALPHA16 = 0x803E,
+    LUMINANCE8 = 0x8040,
+    LUMINANCE16 = 0x8042,
+    LUMINANCE8_ALPHA8 = 0x8045,
+    LUMINANCE16_ALPHA16 = 0x8048,
+    RGB4 = 0x804F,
+    RGB5 = 0x8050,
+    RGB8 = 0x8051,
+    RGB10 = 0x8052,
+    RGB12 = 0x8053,
+    RGB16 = 0x8054,
+    RGBA2 = 0x8055,
+    RGBA4 = 0x8056,
+    RGB5_A1 = 0x8057,
+    RGBA8 = 0x8058,
+    RGB10_A2 = 0x8059,
+    RGBA12 = 0x805A,
+    RGBA16 = 0x805B,
+    DEPTH_COMPONENT16 = 0x81A5,
+    DEPTH_COMPONENT24 = 0x81A6,
+    DEPTH_COMPONENT32 = 0x81A7,
+    R8 = 0x8229,
+    R16 = 0x822A,
+    RG8 = 0x822B,
+    RG16 = 0x822C,
+    R16F = 0x822D,
+    R32F = 0x822E,
+    RG16F = 0x822F,
+    RG32F = 0x8230,
+    R8I = 0x8231,
+    R8UI = 0x8232,
+    R16I = 0x8233,
+    R16UI = 0x8234,
+    R32I = 0x8235,
+    R32UI = 0x8236,
+    RG8I = 0x8237,
+    RG8UI = 0x8238,
+    RG16I = 0x8239,
+    RG16UI = 0x823A,
+    RG32I = 0x823B,
+    RG32UI = 0x823C,
+    RGBA32F = 0x8814,
+    RGB32F = 0x8815,
+    RGBA16F = 0x881A,
+    RGB16F = 0x881B,
+    DEPTH24_STENCIL8 = 0x88F0,
+    R11F_G11F_B10F = 0x8C3A,
+    RGB9_E5 = 0x8C3D,
+    SRGB = 0x8C40,
+    SRGB8 = 0x8C41,
+    SRGB_ALPHA = 0x8C42,
+    SRGB8_ALPHA8 = 0x8C43,
+    SLUMINANCE_ALPHA = 0x8C44,
+    SLUMINANCE8_ALPHA8 = 0x8C45,
+    SLUMINANCE = 0x8C46,
+    SLUMINANCE8 = 0x8C47,
+    DEPTH_COMPONENT32F = 0x8CAC,
+    DEPTH32F_STENCIL8 = 0x8CAD,
+    STENCIL_INDEX8 = 0x8D48,
+    RGB565 = 0x8D62,
+    RGBA32UI = 0x8D70,
+    RGB32UI = 0x8D71,
+    RGBA16UI = 0x8D76,
+    RGB16UI = 0x8D77,
+    RGBA8UI = 0x8D7C,
+    RGB8UI = 0x8D7D,
+    RGBA32I = 0x8D82,
+    RGB32I = 0x8D83,
+    RGBA16I = 0x8D88,
+    RGB16I = 0x8D89,
+    RGBA8I = 0x8D8E,
+    RGB8I = 0x8D8F,
+    R8_SNORM = 0x8F94,
+    RG8_SNORM = 0x8F95,
+    RGB8_SNORM = 0x8F96,
+    RGBA8_SNORM = 0x8F97,
+    R16_SNORM = 0x8F98,
+    RG16_SNORM = 0x8F99,
+    RGB16_SNORM = 0x8F9A,
+    RGBA16_SNORM = 0x8F9B,
+    RGB10_A2UI = 0x906F,
+
+    // Compressed internal formats: S3TC (EXT_texture_compression_s3tc, EXT_texture_sRGB)
+    COMPRESSED_RGB_S3TC_DXT1_EXT = 0x83F0,
+    COMPRESSED_RGBA_S3TC_DXT1_EXT = 0x83F1,
+    COMPRESSED_RGBA_S3TC_DXT3_EXT = 0x83F2,
+    COMPRESSED_RGBA_S3TC_DXT5_EXT = 0x83F3,
+    COMPRESSED_SRGB_S3TC_DXT1_EXT = 0x8C4C,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT = 0x8C4D,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT = 0x8C4E,
+    COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT = 0x8C4F,
+
+    // Compressed internal formats: RGTC and BPTC
+    COMPRESSED_RED_RGTC1 = 0x8DBB,
+    COMPRESSED_SIGNED_RED_RGTC1 = 0x8DBC,
+    COMPRESSED_RG_RGTC2 = 0x8DBD,
+    COMPRESSED_SIGNED_RG_RGTC2 = 0x8DBE,
+    COMPRESSED_RGBA_BPTC_UNORM = 0x8E8C,
+    COMPRESSED_SRGB_ALPHA_BPTC_UNORM = 0x8E8D,
+    COMPRESSED_RGB_BPTC_SIGNED_FLOAT = 0x8E8E,
+    COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT = 0x8E8F,
+
+    // Compressed internal formats: ETC1, ETC2 and EAC
+    ETC1_RGB8_OES = 0x8D64,
+    COMPRESSED_R11_EAC = 0x9270,
+    COMPRESSED_SIGNED_R11_EAC = 0x9271,
+    COMPRESSED_RG11_EAC = 0x9272,
+    COMPRESSED_SIGNED_RG11_EAC = 0x9273,
+    COMPRESSED_RGB8_ETC2 = 0x9274,
+    COMPRESSED_SRGB8_ETC2 = 0x9275,
+    COMPRESSED_RGB8_PUNCHTHROUGH_ALPHA1_ETC2 = 0x9276,
+    COMPRESSED_SRGB8_PUNCHTHROUGH_ALPHA1_ETC2 = 0x9277,
+    COMPRESSED_RGBA8_ETC2_EAC = 0x9278,
+    COMPRESSED_SRGB8_ALPHA8_ETC2_EAC = 0x9279,
+
+    // Compressed internal formats: PVRTC
+    // (IMG_texture_compression_pvrtc, IMG_texture_compression_pvrtc2, EXT_pvrtc_sRGB)
+    COMPRESSED_RGB_PVRTC_4BPPV1_IMG = 0x8C00,
+    COMPRESSED_RGB_PVRTC_2BPPV1_IMG = 0x8C01,
+    COMPRESSED_RGBA_PVRTC_4BPPV1_IMG = 0x8C02,
+    COMPRESSED_RGBA_PVRTC_2BPPV1_IMG = 0x8C03,
+    COMPRESSED_SRGB_PVRTC_2BPPV1_EXT = 0x8A54,
+    COMPRESSED_SRGB_PVRTC_4BPPV1_EXT = 0x8A55,
+    COMPRESSED_SRGB_ALPHA_PVRTC_2BPPV1_EXT = 0x8A56,
+    COMPRESSED_SRGB_ALPHA_PVRTC_4BPPV1_EXT = 0x8A57,
+    COMPRESSED_RGBA_PVRTC_2BPPV2_IMG = 0x9137,
+    COMPRESSED_RGBA_PVRTC_4BPPV2_IMG = 0x9138,
+
+    // Compressed internal formats: ATC (AMD_compressed_ATC_texture)
+    ATC_RGBA_INTERPOLATED_ALPHA_AMD = 0x87EE,
+    ATC_RGB_AMD = 0x8C92,
+    ATC_RGBA_EXPLICIT_ALPHA_AMD = 0x8C93,
+
+    // Compressed internal formats: ASTC (KHR_texture_compression_astc_ldr)
+    COMPRESSED_RGBA_ASTC_4x4_KHR = 0x93B0,
+    COMPRESSED_RGBA_ASTC_5x4_KHR = 0x93B1,
+    COMPRESSED_RGBA_ASTC_5x5_KHR = 0x93B2,
+    COMPRESSED_RGBA_ASTC_6x5_KHR = 0x93B3,
+    COMPRESSED_RGBA_ASTC_6x6_KHR = 0x93B4,
+    COMPRESSED_RGBA_ASTC_8x5_KHR = 0x93B5,
+    COMPRESSED_RGBA_ASTC_8x6_KHR = 0x93B6,
+    COMPRESSED_RGBA_ASTC_8x8_KHR = 0x93B7,
+    COMPRESSED_RGBA_ASTC_10x5_KHR = 0x93B8,
+    COMPRESSED_RGBA_ASTC_10x6_KHR = 0x93B9,
+    COMPRESSED_RGBA_ASTC_10x8_KHR = 0x93BA,
+    COMPRESSED_RGBA_ASTC_10x10_KHR = 0x93BB,
+    COMPRESSED_RGBA_ASTC_12x10_KHR = 0x93BC,
+    COMPRESSED_RGBA_ASTC_12x12_KHR = 0x93BD,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_4x4_KHR = 0x93D0,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_5x4_KHR = 0x93D1,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_5x5_KHR = 0x93D2,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_6x5_KHR = 0x93D3,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_6x6_KHR = 0x93D4,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_8x5_KHR = 0x93D5,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_8x6_KHR = 0x93D6,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_8x8_KHR = 0x93D7,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_10x5_KHR = 0x93D8,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_10x6_KHR = 0x93D9,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_10x8_KHR = 0x93DA,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_10x10_KHR = 0x93DB,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_12x10_KHR = 0x93DC,
+    COMPRESSED_SRGB8_ALPHA8_ASTC_12x12_KHR = 0x93DD,
+}
@@ -0,0 +1,407 @@
+//! OpenGL uploads through `glow`
+//!
+//! Requires the `glow` feature.
+//!
+//! `Upload::new` chooses the texture target, the `TexImage*` or
+//! `CompressedTexImage*` function and the formats of a header for an
+//! `Api`, and `Upload::calls` lists the calls uploading a level, all
+//! without a context. `upload_level` runs them on a
+//! `glow::HasContext`, and `upload_async` creates a texture and
+//! uploads the levels as they are decoded.
+
+// The glow calls are unsafe
+#![allow(unsafe_code)]
+
+use crate::error::bail;
+use crate::io::AsyncRead;
+use crate::{gl, Decoder, ErrorKind, HeaderInfo, LevelInfo, Result, TextureKind};
+use ::glow::{HasContext, PixelUnpackData};
+use std::ops::Range;
+
+/// Flavour of OpenGL of a context
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    /// Desktop OpenGL
+    Gl,
+    /// OpenGL ES 2.0 and WebGL 1: 2D and cubemap textures only, unsized
+    /// internal formats, no `TEXTURE_MAX_LEVEL` and no swizzle
+    Gles2,
+    /// OpenGL ES 3.0, 3.1 and WebGL 2: no 1D textures and no cubemap
+    /// arrays
+    Gles3,
+    /// OpenGL ES 3.2: no 1D textures
+    Gles32,
+}
+
+impl Api {
+    /// API of a context
+    pub fn of(gl: &impl HasContext) -> Api {
+        let version = gl.version();
+        match (version.is_embedded, version.major, version.minor) {
+            (false, _, _) => Api::Gl,
+            (true, 0..=2, _) => Api::Gles2,
+            (true, 3, 0..=1) => Api::Gles3,
+            (true, _, _) => Api::Gles32,
+        }
+    }
+}
+
+/// `glTexImage*` function uploading the images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    TexImage1D,
+    TexImage2D,
+    TexImage3D,
+    CompressedTexImage1D,
+    CompressedTexImage2D,
+    CompressedTexImage3D,
+}
+
+/// How to upload the levels of a texture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    /// Texture target, ex: `GL_TEXTURE_CUBE_MAP`
+    pub target: u32,
+    pub function: Function,
+    /// `internalformat` argument: `glInternalFormat`, or `glFormat`
+    /// for uncompressed formats on ES 2.0
+    pub internal_format: u32,
+    /// `format` argument (0 for compressed formats)
+    pub format: u32,
+    /// `type` argument (0 for compressed formats), `HALF_FLOAT_OES`
+    /// for `HALF_FLOAT` on ES 2.0
+    pub gl_type: u32,
+    /// `GL_UNPACK_ALIGNMENT` of the data
+    pub unpack_alignment: i32,
+    /// `GL_TEXTURE_MAX_LEVEL`, except on ES 2.0
+    pub max_level: Option<i32>,
+    /// `GL_TEXTURE_SWIZZLE_R/G/B/A` from `KTXswizzle`, unless it is
+    /// missing, invalid or the identity, and except on ES 2.0
+    pub swizzle: Option<[u32; 4]>,
+}
+
+/// A `glTexImage*` call uploading a level or a face of a level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageCall {
+    pub function: Function,
+    /// Image target, ex: `GL_TEXTURE_CUBE_MAP_POSITIVE_X + face`
+    pub target: u32,
+    pub level: i32,
+    /// `width`, `height` and `depth` arguments, as many as the function
+    /// takes (layers count as height of 1D arrays and as depth of 2D
+    /// and cubemap arrays, by layer-face)
+    pub size: [i32; 3],
+    /// Range of the data in the level
+    pub range: Range<usize>,
+}
+
+impl Upload {
+    /// Choose how to upload a texture with a header `info` on a
+    /// context of `api`
+    ///
+    /// Fails if the header does not match a texture target (ex: a 3D
+    /// cubemap) or if the API does not have the target.
+    pub fn new(info: &HeaderInfo, api: Api) -> Result<Upload> {
+        use ::glow::*;
+        use std::cmp::max;
+
+        // Array layers take one more dimension
+        let kind = info.kind()?;
+        let dimensions = kind.dimensions() + u32::from(kind.is_array());
+        let target = match kind {
+            TextureKind::Texture1D => TEXTURE_1D,
+            TextureKind::Texture1DArray => TEXTURE_1D_ARRAY,
+            TextureKind::Texture2D => TEXTURE_2D,
+            TextureKind::Texture2DArray => TEXTURE_2D_ARRAY,
+            TextureKind::Texture3D => TEXTURE_3D,
+            TextureKind::Cube => TEXTURE_CUBE_MAP,
+            TextureKind::CubeArray => TEXTURE_CUBE_MAP_ARRAY,
+        };
+        let supported = match api {
+            Api::Gl => true,
+            Api::Gles2 => target == TEXTURE_2D || target == TEXTURE_CUBE_MAP,
+            Api::Gles3 => kind.dimensions() > 1 && kind != TextureKind::CubeArray,
+            Api::Gles32 => kind.dimensions() > 1,
+        };
+        if !supported {
+            bail!(
+                "{} textures are not available on {:?}",
+                target_name(target),
+                api
+            );
+        }
+
+        let compressed = info.gl_type == 0;
+        let function = match (dimensions, compressed) {
+            (1, false) => Function::TexImage1D,
+            (2, false) => Function::TexImage2D,
+            (_, false) => Function::TexImage3D,
+            (1, true) => Function::CompressedTexImage1D,
+            (2, true) => Function::CompressedTexImage2D,
+            (_, true) => Function::CompressedTexImage3D,
+        };
+
+        // ES 2.0 takes the format as internal format
+        let (internal_format, gl_type) = match api {
+            Api::Gles2 if !compressed => {
+                let gl_type = match info.gl_type {
+                    gl::HALF_FLOAT => gl::HALF_FLOAT_OES,
+                    x => x,
+                };
+                (info.gl_format, gl_type)
+            }
+            _ => (info.gl_internal_format, info.gl_type),
+        };
+
+        let (max_level, swizzle) = match api {
+            Api::Gles2 => (None, None),
+            _ => {
+                let max_level = max(1, info.number_of_mipmap_levels) as i32 - 1;
+                let swizzle = info
+                    .key_value_data
+                    .get("KTXswizzle")
+                    .and_then(parse_swizzle);
+                (Some(max_level), swizzle)
+            }
+        };
+
+        Ok(Upload {
+            target,
+            function,
+            internal_format,
+            format: info.gl_format,
+            gl_type,
+            unpack_alignment: 4,
+            max_level,
+            swizzle,
+        })
+    }
+
+    /// Calls uploading a level: one for each face of a cubemap, one for
+    /// the whole level otherwise
+    pub fn calls(&self, level: &LevelInfo) -> Vec<ImageCall> {
+        let (width, height, depth) = (
+            level.pixel_width as i32,
+            level.pixel_height as i32,
+            level.pixel_depth as i32,
+        );
+        let layers = level.number_of_layers as i32;
+        let call = |target, size, range| ImageCall {
+            function: self.function,
+            target,
+            level: level.level as i32,
+            size,
+            range,
+        };
+        match self.target {
+            ::glow::TEXTURE_CUBE_MAP => level
+                .frames()
+                .map(|(frame, range)| {
+                    let target = ::glow::TEXTURE_CUBE_MAP_POSITIVE_X + frame.face;
+                    call(target, [width, height, 0], range)
+                })
+                .collect(),
+            target => {
+                let size = match target {
+                    ::glow::TEXTURE_1D => [width, 0, 0],
+                    ::glow::TEXTURE_1D_ARRAY => [width, layers, 0],
+                    ::glow::TEXTURE_2D => [width, height, 0],
+                    ::glow::TEXTURE_3D => [width, height, depth],
+                    ::glow::TEXTURE_2D_ARRAY => [width, height, layers],
+                    _ => [width, height, layers * level.number_of_faces as i32],
+                };
+                vec![call(target, size, 0..level.size())]
+            }
+        }
+    }
+}
+
+/// Parse a `KTXswizzle` value (ex: `bgra`, `rrr1`), or `None` if it is
+/// invalid or the identity
+pub fn parse_swizzle(value: &[u8]) -> Option<[u32; 4]> {
+    let value = value.strip_suffix(&[0]).unwrap_or(value);
+    if value.len() != 4 || value == b"rgba" {
+        return None;
+    }
+    let mut swizzle = [0; 4];
+    for (x, c) in swizzle.iter_mut().zip(value) {
+        *x = match c {
+            b'r' => ::glow::RED,
+            b'g' => ::glow::GREEN,
+            b'b' => ::glow::BLUE,
+            b'a' => ::glow::ALPHA,
+            b'0' => ::glow::ZERO,
+            b'1' => ::glow::ONE,
+            _ => return None,
+        };
+    }
+    Some(swizzle)
+}
+
+fn target_name(target: u32) -> &'static str {
+    match target {
+        ::glow::TEXTURE_1D => "GL_TEXTURE_1D",
+        ::glow::TEXTURE_1D_ARRAY => "GL_TEXTURE_1D_ARRAY",
+        ::glow::TEXTURE_2D => "GL_TEXTURE_2D",
+        ::glow::TEXTURE_2D_ARRAY => "GL_TEXTURE_2D_ARRAY",
+        ::glow::TEXTURE_3D => "GL_TEXTURE_3D",
+        ::glow::TEXTURE_CUBE_MAP => "GL_TEXTURE_CUBE_MAP",
+        _ => "GL_TEXTURE_CUBE_MAP_ARRAY",
+    }
+}
+
+/// Upload a level, with `data` as yielded by
+/// `Decoder::read_levels_async`, to the texture bound to
+/// `upload.target`
+///
+/// Expects `GL_UNPACK_ALIGNMENT` to be `upload.unpack_alignment`.
+///
+/// # Safety
+///
+/// `gl` must be current on this thread, as for any `glow` call.
+pub unsafe fn upload_level<C: HasContext>(
+    gl: &C,
+    upload: &Upload,
+    level: &LevelInfo,
+    data: &[u8],
+) -> Result<()> {
+    if data.len() != level.size() {
+        bail!(ErrorKind::InvalidBufferSize(level.size(), data.len()));
+    }
+    let internal_format = upload.internal_format as i32;
+    let (format, gl_type) = (upload.format, upload.gl_type);
+    for call in upload.calls(level) {
+        let [width, height, depth] = call.size;
+        let (target, level) = (call.target, call.level);
+        let data = &data[call.range];
+        let size = data.len() as i32;
+        match call.function {
+            Function::TexImage1D => {
+                let data = PixelUnpackData::Slice(Some(data));
+                gl.tex_image_1d(
+                    target,
+                    level,
+                    internal_format,
+                    width,
+                    0,
+                    format,
+                    gl_type,
+                    data,
+                )
+            }
+            Function::TexImage2D => {
+                let data = PixelUnpackData::Slice(Some(data));
+                gl.tex_image_2d(
+                    target,
+                    level,
+                    internal_format,
+                    width,
+                    height,
+                    0,
+                    format,
+                    gl_type,
+                    data,
+                )
+            }
+            Function::TexImage3D => {
+                let data = PixelUnpackData::Slice(Some(data));
+                gl.tex_image_3d(
+                    target,
+                    level,
+                    internal_format,
+                    width,
+                    height,
+                    depth,
+                    0,
+                    format,
+                    gl_type,
+                    data,
+                )
+            }
+            Function::CompressedTexImage1D => {
+                gl.compressed_tex_image_1d(target, level, internal_format, width, 0, size, data)
+            }
+            Function::CompressedTexImage2D => gl.compressed_tex_image_2d(
+                target,
+                level,
+                internal_format,
+                width,
+                height,
+                0,
+                size,
+                data,
+            ),
+            Function::CompressedTexImage3D => gl.compressed_tex_image_3d(
+                target,
+                level,
+                internal_format,
+                width,
+                height,
+                depth,
+                0,
+                size,
+                data,
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Decode a file and upload it to a new texture as its levels arrive
+///
+/// Returns the header, how it was uploaded and the texture, which is
+/// left bound to `Upload::target`. `GL_UNPACK_ALIGNMENT` is restored.
+///
+/// # Safety
+///
+/// `gl` must be current on this thread, as for any `glow` call.
+pub async unsafe fn upload_async<C, R>(
+    gl: &C,
+    decoder: Decoder<R>,
+) -> Result<(HeaderInfo, Upload, C::Texture)>
+where
+    C: HasContext,
+    R: AsyncRead + Unpin,
+{
+    use futures_core::stream::Stream as _;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let (info, mut levels) = decoder.read_levels_async().await?;
+    let upload = Upload::new(&info, Api::of(gl))?;
+
+    let texture = gl.create_texture().map_err(ErrorKind::Msg)?;
+    gl.bind_texture(upload.target, Some(texture));
+    let unpack_alignment = gl.get_parameter_i32(::glow::UNPACK_ALIGNMENT);
+    gl.pixel_store_i32(::glow::UNPACK_ALIGNMENT, upload.unpack_alignment);
+
+    let mut result = Ok(());
+    while let Some(level) = poll_fn(|cx| Pin::new(&mut levels).poll_next(cx)).await {
+        result = level.and_then(|(level, data)| upload_level(gl, &upload, &level, &data));
+        if result.is_err() {
+            break;
+        }
+    }
+    gl.pixel_store_i32(::glow::UNPACK_ALIGNMENT, unpack_alignment);
+    if let Err(e) = result {
+        gl.delete_texture(texture);
+        return Err(e);
+    }
+
+    if let Some(max_level) = upload.max_level {
+        gl.tex_parameter_i32(upload.target, ::glow::TEXTURE_MAX_LEVEL, max_level);
+    }
+    // GL_TEXTURE_SWIZZLE_RGBA is not in OpenGL ES
+    if let Some(swizzle) = upload.swizzle {
+        let names = [
+            ::glow::TEXTURE_SWIZZLE_R,
+            ::glow::TEXTURE_SWIZZLE_G,
+            ::glow::TEXTURE_SWIZZLE_B,
+            ::glow::TEXTURE_SWIZZLE_A,
+        ];
+        for (name, x) in names.iter().zip(&swizzle) {
+            gl.tex_parameter_i32(upload.target, *name, *x as i32);
+        }
+    }
+    Ok((info, upload, texture))
+}
@@ -0,0 +1,173 @@
+//! Integration with the `image` crate
+//!
+//! Requires the `image` feature.
+//!
+//! Compressed frames are decompressed with the software decoders in
+//! `codec`; uncompressed frames are converted with `convert`.
+
+use crate::{codec, convert, gl, Decoder, FrameInfo, HeaderInfo, Result};
+use ::image::error::{DecodingError, ImageFormatHint};
+use ::image::{
+    ColorType, DynamicImage, ImageBuffer, ImageDecoder, ImageError, ImageResult, Luma, LumaA,
+};
+use std::io::Read;
+
+/// `image::ImageDecoder` for KTX files
+///
+/// Yields level 0, layer 0, face 0 of the texture (the first z slice
+/// for 3D textures).
+pub struct KtxDecoder {
+    image: DynamicImage,
+}
+
+impl KtxDecoder {
+    /// Read and decode a KTX file
+    pub fn new(mut read: impl Read) -> ImageResult<Self> {
+        let mut data = Vec::new();
+        read.read_to_end(&mut data)?;
+        let image = block_on(decode_first_frame(&data)).map_err(decoding_error)?;
+        Ok(KtxDecoder { image })
+    }
+}
+
+impl ImageDecoder for KtxDecoder {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.image.width(), self.image.height())
+    }
+
+    fn color_type(&self) -> ColorType {
+        self.image.color()
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
+        buf.copy_from_slice(self.image.as_bytes());
+        Ok(())
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+/// Convert a frame from the `Decoder` stream into a `DynamicImage`
+///
+/// Uncompressed floating-point formats yield 32-bit float images;
+/// everything else yields 8-bit images. Only the first z slice of
+/// 3D textures is kept.
+pub fn frame_to_image(info: &HeaderInfo, frame: &FrameInfo, buf: &[u8]) -> Result<DynamicImage> {
+    let width = frame.pixel_width;
+    let height = frame.pixel_height;
+    let npixels = (width * height) as usize;
+    let (format, is_float) = if info.gl_type == 0 {
+        (info.gl_base_internal_format, false)
+    } else {
+        let is_float = matches!(
+            info.gl_type,
+            gl::HALF_FLOAT
+                | gl::HALF_FLOAT_OES
+                | gl::FLOAT
+                | gl::UNSIGNED_INT_10F_11F_11F_REV
+                | gl::UNSIGNED_INT_5_9_9_9_REV
+        );
+        (info.gl_format, is_float)
+    };
+    let has_alpha = matches!(
+        format,
+        gl::ALPHA | gl::LUMINANCE_ALPHA | gl::RGBA | gl::BGRA
+    );
+
+    if is_float {
+        let mut pixels = convert::to_rgba32f(info, frame, buf)?;
+        pixels.truncate(npixels * 4);
+        let image = ImageBuffer::from_raw(width, height, pixels).unwrap();
+        let image = DynamicImage::ImageRgba32F(image);
+        return Ok(if has_alpha {
+            image
+        } else {
+            DynamicImage::ImageRgb32F(image.to_rgb32f())
+        });
+    }
+
+    let mut pixels = if info.gl_type == 0 {
+        codec::decode_rgba8(info, frame, buf)?
+    } else {
+        convert::to_rgba8(info, frame, buf)?
+    };
+    pixels.truncate(npixels * 4);
+
+    // Use the smallest colour type that holds the format
+    let image = match format {
+        gl::LUMINANCE => {
+            let luma = pixels.chunks_exact(4).map(|px| px[0]).collect();
+            DynamicImage::ImageLuma8(
+                ImageBuffer::<Luma<u8>, _>::from_raw(width, height, luma).unwrap(),
+            )
+        }
+        gl::LUMINANCE_ALPHA => {
+            let luma_alpha = pixels
+                .chunks_exact(4)
+                .flat_map(|px| [px[0], px[3]])
+                .collect();
+            DynamicImage::ImageLumaA8(
+                ImageBuffer::<LumaA<u8>, _>::from_raw(width, height, luma_alpha).unwrap(),
+            )
+        }
+        _ => {
+            let image =
+                DynamicImage::ImageRgba8(ImageBuffer::from_raw(width, height, pixels).unwrap());
+            if has_alpha {
+                image
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            }
+        }
+    };
+    Ok(image)
+}
+
+async fn decode_first_frame(data: &[u8]) -> Result<DynamicImage> {
+    use futures_core::stream::Stream as _;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let (info, mut stream) = Decoder::new(data).read_async().await?;
+    match poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        Some(frame) => {
+            let (frame, buf) = frame?;
+            frame_to_image(&info, &frame, &buf)
+        }
+        None => Err(crate::ErrorKind::Io(std::io::ErrorKind::UnexpectedEof.into()).into()),
+    }
+}
+
+fn decoding_error(e: crate::Error) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Name("KTX".into()),
+        e.to_string(),
+    ))
+}
+
+/// Run a future to completion on the current thread
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(x) => return x,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! I/O Traits
+//!
+//! The decoders and encoders of this crate read and write through the
+//! minimal traits of this module rather than the traits of a specific
+//! runtime. They are implemented for:
+//!
+//! - all the readers and writers of tokio 0.2 (`tokio02` feature,
+//!   enabled by default); without it, byte slices, `std::io::Cursor`
+//!   and `Vec<u8>`;
+//! - tokio 1.x readers and writers wrapped in `Tokio1` (`tokio1` feature);
+//! - `futures-io` readers and writers wrapped in `FuturesIo`
+//!   (`futures-io` feature).
+
+use std::future::poll_fn;
+use std::io;
+pub use std::io::SeekFrom;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Reads bytes asynchronously
+pub trait AsyncRead {
+    /// Read into `buf`, returning the number of bytes read
+    /// (0 at the end of the input)
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>>;
+}
+
+/// Seeks asynchronously
+///
+/// A seek that returns `Poll::Pending` is polled again with the same
+/// position until it completes.
+pub trait AsyncSeek {
+    /// Seek to `pos`, returning the new position from the start
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>>;
+}
+
+/// Writes bytes asynchronously
+pub trait AsyncWrite {
+    /// Write from `buf`, returning the number of bytes written
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>>;
+
+    /// Flush the buffered data
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>;
+}
+
+#[cfg(feature = "tokio02")]
+impl<T: tokio::io::AsyncRead + ?Sized> AsyncRead for T {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        tokio::io::AsyncRead::poll_read(self, cx, buf)
+    }
+}
+
+#[cfg(feature = "tokio02")]
+impl<T: tokio::io::AsyncSeek + ?Sized> AsyncSeek for T {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        // tokio 0.2 restarts a pending seek on `start_seek`
+        match tokio::io::AsyncSeek::start_seek(self.as_mut(), cx, pos) {
+            Poll::Ready(Ok(())) => tokio::io::AsyncSeek::poll_complete(self, cx),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio02")]
+impl<T: tokio::io::AsyncWrite + ?Sized> AsyncWrite for T {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(self, cx)
+    }
+}
+
+// Without tokio 0.2, the in-memory readers and writers it would
+// provide, so the same types work with or without the feature
+
+#[cfg(not(feature = "tokio02"))]
+impl AsyncRead for &[u8] {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Read::read(self.get_mut(), buf))
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsRef<[u8]> + Unpin> AsyncRead for io::Cursor<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Read::read(self.get_mut(), buf))
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsRef<[u8]> + Unpin> AsyncSeek for io::Cursor<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        Poll::Ready(io::Seek::seek(self.get_mut(), pos))
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl AsyncWrite for Vec<u8> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncRead + Unpin + ?Sized> AsyncRead for &mut T {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_read(cx, buf)
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncSeek + Unpin + ?Sized> AsyncSeek for &mut T {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut **self.get_mut()).poll_seek(cx, pos)
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncWrite + Unpin + ?Sized> AsyncWrite for &mut T {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut **self.get_mut()).poll_flush(cx)
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncRead + Unpin + ?Sized> AsyncRead for Box<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_read(cx, buf)
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncSeek + Unpin + ?Sized> AsyncSeek for Box<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut **self.get_mut()).poll_seek(cx, pos)
+    }
+}
+
+#[cfg(not(feature = "tokio02"))]
+impl<T: AsyncWrite + Unpin + ?Sized> AsyncWrite for Box<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut **self.get_mut()).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut **self.get_mut()).poll_flush(cx)
+    }
+}
+
+/// Reader, seeker or writer of tokio 1.x
+///
+/// ```ignore
+/// let file = tokio::fs::File::open("example.ktx").await?;
+/// let decoder = ktx_async::Decoder::new(Tokio1::new(file));
+/// ```
+#[cfg(feature = "tokio1")]
+#[derive(Debug)]
+pub struct Tokio1<T> {
+    inner: T,
+    seeking: bool,
+}
+
+#[cfg(feature = "tokio1")]
+impl<T> Tokio1<T> {
+    pub fn new(inner: T) -> Self {
+        Tokio1 {
+            inner,
+            seeking: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[cfg(feature = "tokio1")]
+impl<T: tokio1::io::AsyncRead + Unpin> AsyncRead for Tokio1<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut buf = tokio1::io::ReadBuf::new(buf);
+        match Pin::new(&mut self.get_mut().inner).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) => Poll::Ready(Ok(buf.filled().len())),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(feature = "tokio1")]
+impl<T: tokio1::io::AsyncSeek + Unpin> AsyncSeek for Tokio1<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let this = self.get_mut();
+        let mut inner = Pin::new(&mut this.inner);
+        if !this.seeking {
+            // Wait for any previous operation before starting the seek
+            match inner.as_mut().poll_complete(cx) {
+                Poll::Ready(Ok(_)) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+            inner.as_mut().start_seek(pos)?;
+            this.seeking = true;
+        }
+        let result = inner.poll_complete(cx);
+        if result.is_ready() {
+            this.seeking = false;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "tokio1")]
+impl<T: tokio1::io::AsyncWrite + Unpin> AsyncWrite for Tokio1<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+}
+
+/// Reader, seeker or writer of `futures-io`
+///
+/// ```ignore
+/// let file = async_std::fs::File::open("example.ktx").await?;
+/// let decoder = ktx_async::Decoder::new(FuturesIo::new(file));
+/// ```
+#[cfg(feature = "futures-io")]
+#[derive(Debug)]
+pub struct FuturesIo<T>(T);
+
+#[cfg(feature = "futures-io")]
+impl<T> FuturesIo<T> {
+    pub fn new(inner: T) -> Self {
+        FuturesIo(inner)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncRead + Unpin> AsyncRead for FuturesIo<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncSeek + Unpin> AsyncSeek for FuturesIo<T> {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        Pin::new(&mut self.get_mut().0).poll_seek(cx, pos)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl<T: futures_io::AsyncWrite + Unpin> AsyncWrite for FuturesIo<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+}
+
+/// Fill `buf`, failing with `UnexpectedEof` at the end of the input
+pub(crate) async fn read_exact<R>(read: &mut R, buf: &mut [u8]) -> io::Result<usize>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut pos = 0;
+    while pos < buf.len() {
+        let result = poll_fn(|cx| Pin::new(&mut *read).poll_read(cx, &mut buf[pos..])).await;
+        match result {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => pos += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(pos)
+}
+
+/// Discard `n` bytes, returning how many were there before the end
+/// of the input
+pub(crate) async fn skip<R>(read: &mut R, n: u64) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut buf = [0_u8; 4096];
+    let mut skipped = 0;
+    while skipped < n {
+        let len = std::cmp::min(n - skipped, buf.len() as u64) as usize;
+        let result = poll_fn(|cx| Pin::new(&mut *read).poll_read(cx, &mut buf[..len])).await;
+        match result {
+            Ok(0) => break,
+            Ok(n) => skipped += n as u64,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(skipped)
+}
+
+pub(crate) async fn seek<S>(seek: &mut S, pos: SeekFrom) -> io::Result<u64>
+where
+    S: AsyncSeek + Unpin + ?Sized,
+{
+    poll_fn(|cx| Pin::new(&mut *seek).poll_seek(cx, pos)).await
+}
+
+pub(crate) async fn write_all<W>(write: &mut W, buf: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let mut pos = 0;
+    while pos < buf.len() {
+        let result = poll_fn(|cx| Pin::new(&mut *write).poll_write(cx, &buf[pos..])).await;
+        match result {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => pos += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn flush<W>(write: &mut W) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    poll_fn(|cx| Pin::new(&mut *write).poll_flush(cx)).await
+}
@@ -0,0 +1,516 @@
+//! KTX 2.0 Container
+//!
+//! http://github.khronos.org/KTX-Specification/
+//!
+//! Only the header, the index and the key/value data are read. Data
+//! format descriptors, supercompression global data and the mip levels
+//! themselves are not interpreted.
+//!
+//! Files are written with `Encoder::write_ktx2_async`.
+
+/*
+File Structure:
+
+Byte[12] identifier
+UInt32 vkFormat
+UInt32 typeSize
+UInt32 pixelWidth
+UInt32 pixelHeight
+UInt32 pixelDepth
+UInt32 layerCount
+UInt32 faceCount
+UInt32 levelCount
+UInt32 supercompressionScheme
+
+// Index
+UInt32 dfdByteOffset
+UInt32 dfdByteLength
+UInt32 kvdByteOffset
+UInt32 kvdByteLength
+UInt64 sgdByteOffset
+UInt64 sgdByteLength
+
+// Level Index
+struct {
+    UInt64 byteOffset
+    UInt64 byteLength
+    UInt64 uncompressedByteLength
+} levels[max(1, levelCount)]
+
+// Data Format Descriptor
+// Key/Value Data
+// Supercompression Global Data
+// Mip Level Array (smallest level first)
+
+All numbers are little-endian.
+*/
+
+use crate::error::bail;
+use crate::error::ResultExt as _;
+use crate::io::{self, AsyncRead};
+use crate::parse::check_limit;
+use crate::{format, gl, vk, ErrorKind, FrameInfo, KeyValueData, Limits, Result};
+
+pub const MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// KTX 2.0 Header Info
+#[derive(Debug, Clone)]
+pub struct HeaderInfo {
+    /// The `VkFormat` of the texture, see `vk`.
+    /// `VK_FORMAT_UNDEFINED` for Basis Universal and other formats
+    /// that only have a data format descriptor.
+    pub vk_format: u32,
+    /// Size of the data type in bytes used for endianness conversion,
+    /// 1 for block-compressed formats.
+    pub type_size: u32,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+    pub pixel_depth: u32,
+    /// 0 if the texture is not an array texture
+    pub layer_count: u32,
+    /// 6 for cubemaps and cubemap arrays, 1 otherwise
+    pub face_count: u32,
+    /// 0 requests that the mipmap pyramid is generated at load time
+    pub level_count: u32,
+    /// See `supercompression_scheme_name`
+    pub supercompression_scheme: u32,
+    pub dfd_byte_offset: u32,
+    pub dfd_byte_length: u32,
+    pub kvd_byte_offset: u32,
+    pub kvd_byte_length: u32,
+    pub sgd_byte_offset: u64,
+    pub sgd_byte_length: u64,
+    /// One entry per level, starting with level 0 (the largest)
+    pub levels: Vec<LevelIndex>,
+    pub key_value_data: KeyValueData,
+}
+
+/// Location of a mip level in a KTX 2.0 file
+#[derive(Debug, Clone, Copy)]
+pub struct LevelIndex {
+    /// Offset from the start of the file
+    pub byte_offset: u64,
+    /// Size of the (possibly supercompressed) data of the level
+    pub byte_length: u64,
+    /// Size of the data after supercompression is undone
+    pub uncompressed_byte_length: u64,
+}
+
+/// Symbolic name of a `supercompressionScheme` value
+pub fn supercompression_scheme_name(value: u32) -> Option<&'static str> {
+    match value {
+        0 => Some("None"),
+        1 => Some("BasisLZ"),
+        2 => Some("Zstandard"),
+        3 => Some("ZLIB"),
+        _ => None,
+    }
+}
+
+/// Read the header, the index and the key/value data
+///
+/// Reading stops at the end of the key/value data.
+pub async fn read_header_async(reader: impl AsyncRead + Unpin) -> Result<HeaderInfo> {
+    read_header_with_limits_async(reader, &Limits::default()).await
+}
+
+/// Read the header, the index and the key/value data, checking
+/// `kvdByteLength` against `Limits::max_key_value_bytes`
+pub async fn read_header_with_limits_async(
+    mut reader: impl AsyncRead + Unpin,
+    limits: &Limits,
+) -> Result<HeaderInfo> {
+    use byteorder::{ByteOrder as _, LittleEndian as LE};
+
+    let buf = {
+        let mut v = [0_u8; 80];
+        io::read_exact(&mut reader, &mut v).await.at_offset(0)?;
+        v
+    };
+
+    // Check magic
+    {
+        let magic: &[u8] = &buf[0..12];
+        if magic != MAGIC {
+            let mut m = [0_u8; 12];
+            m.copy_from_slice(magic);
+            return Err(ErrorKind::InvalidFormat(m)).at_offset(0);
+        }
+    }
+
+    let vk_format = LE::read_u32(&buf[12..16]);
+    let type_size = LE::read_u32(&buf[16..20]);
+    let pixel_width = LE::read_u32(&buf[20..24]);
+    let pixel_height = LE::read_u32(&buf[24..28]);
+    let pixel_depth = LE::read_u32(&buf[28..32]);
+    let layer_count = LE::read_u32(&buf[32..36]);
+    let face_count = LE::read_u32(&buf[36..40]);
+    let level_count = LE::read_u32(&buf[40..44]);
+    let supercompression_scheme = LE::read_u32(&buf[44..48]);
+    let dfd_byte_offset = LE::read_u32(&buf[48..52]);
+    let dfd_byte_length = LE::read_u32(&buf[52..56]);
+    let kvd_byte_offset = LE::read_u32(&buf[56..60]);
+    let kvd_byte_length = LE::read_u32(&buf[60..64]);
+    let sgd_byte_offset = LE::read_u64(&buf[64..72]);
+    let sgd_byte_length = LE::read_u64(&buf[72..80]);
+
+    // A 32-bit dimension has at most 32 levels
+    if level_count > 32 {
+        return Err(ErrorKind::InvalidNumberOfMipmapLevels(level_count)).at_offset(40);
+    }
+
+    let nlevels = std::cmp::max(1, level_count) as usize;
+    let mut index = vec![0_u8; nlevels * 24];
+    io::read_exact(&mut reader, &mut index)
+        .await
+        .at_offset(80)?;
+    let levels: Vec<LevelIndex> = index
+        .chunks_exact(24)
+        .map(|x| LevelIndex {
+            byte_offset: LE::read_u64(&x[0..8]),
+            byte_length: LE::read_u64(&x[8..16]),
+            uncompressed_byte_length: LE::read_u64(&x[16..24]),
+        })
+        .collect();
+
+    let mut kvbuf = vec![];
+    if kvd_byte_length > 0 {
+        check_limit(
+            "kvdByteLength",
+            kvd_byte_length.into(),
+            limits.max_key_value_bytes.into(),
+        )
+        .at_offset(60)?;
+
+        // The key/value data lies between the level index and the
+        // supercompression global data or the levels
+        let pos = 80 + index.len() as u64;
+        let kvd_byte_offset = u64::from(kvd_byte_offset);
+        let kvd_end = kvd_byte_offset + u64::from(kvd_byte_length);
+        let sgd = Some(sgd_byte_offset).filter(|_| sgd_byte_length > 0);
+        let next_section = levels
+            .iter()
+            .filter(|x| x.byte_length > 0)
+            .map(|x| x.byte_offset)
+            .chain(sgd)
+            .min()
+            .unwrap_or(u64::MAX);
+        if kvd_byte_offset < pos || kvd_end > next_section {
+            return Err(ErrorKind::InvalidKeyValueData).at_offset(56);
+        }
+
+        // Skip the data format descriptor
+        let skipped = io::skip(&mut reader, kvd_byte_offset - pos).await?;
+        if skipped < kvd_byte_offset - pos {
+            let offset = pos + skipped;
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)).at_offset(offset);
+        }
+
+        kvbuf = vec![0_u8; kvd_byte_length as usize];
+        io::read_exact(&mut reader, &mut kvbuf)
+            .await
+            .at_offset(kvd_byte_offset)?;
+        kvbuf = native_endian_key_values(kvbuf);
+    }
+
+    Ok(HeaderInfo {
+        vk_format,
+        type_size,
+        pixel_width,
+        pixel_height,
+        pixel_depth,
+        layer_count,
+        face_count,
+        level_count,
+        supercompression_scheme,
+        dfd_byte_offset,
+        dfd_byte_length,
+        kvd_byte_offset,
+        kvd_byte_length,
+        sgd_byte_offset,
+        sgd_byte_length,
+        levels,
+        key_value_data: KeyValueData { raw: kvbuf },
+    })
+}
+
+/// Rewrite the little-endian `keyAndValueByteLength` fields of KTX 2.0
+/// key/value data in native endianness, as `KeyValueData` expects.
+fn native_endian_key_values(mut raw: Vec<u8>) -> Vec<u8> {
+    use byteorder::{ByteOrder as _, LittleEndian as LE, NativeEndian as NE};
+
+    let mut pos = 0;
+    while pos + 4 <= raw.len() {
+        let len = LE::read_u32(&raw[pos..pos + 4]);
+        NE::write_u32(&mut raw[pos..pos + 4], len);
+        pos += 4 + ((len as usize + 3) & !3);
+    }
+    raw
+}
+
+/// `VkFormat` equivalent of an uncompressed sized internal format that
+/// the encoder can write, or `None`.
+pub fn vk_format(gl_internal_format: u32) -> Option<u32> {
+    let vk_format = match gl_internal_format {
+        gl::R8 => vk::R8_UNORM,
+        gl::R8_SNORM => vk::R8_SNORM,
+        gl::RG8 => vk::R8G8_UNORM,
+        gl::RG8_SNORM => vk::R8G8_SNORM,
+        gl::RGB8 => vk::R8G8B8_UNORM,
+        gl::RGB8_SNORM => vk::R8G8B8_SNORM,
+        gl::SRGB8 => vk::R8G8B8_SRGB,
+        gl::RGBA8 => vk::R8G8B8A8_UNORM,
+        gl::RGBA8_SNORM => vk::R8G8B8A8_SNORM,
+        gl::SRGB8_ALPHA8 => vk::R8G8B8A8_SRGB,
+        gl::R16 => vk::R16_UNORM,
+        gl::R16_SNORM => vk::R16_SNORM,
+        gl::R16F => vk::R16_SFLOAT,
+        gl::RG16 => vk::R16G16_UNORM,
+        gl::RG16_SNORM => vk::R16G16_SNORM,
+        gl::RG16F => vk::R16G16_SFLOAT,
+        gl::RGB16 => vk::R16G16B16_UNORM,
+        gl::RGB16_SNORM => vk::R16G16B16_SNORM,
+        gl::RGB16F => vk::R16G16B16_SFLOAT,
+        gl::RGBA16 => vk::R16G16B16A16_UNORM,
+        gl::RGBA16_SNORM => vk::R16G16B16A16_SNORM,
+        gl::RGBA16F => vk::R16G16B16A16_SFLOAT,
+        gl::R32F => vk::R32_SFLOAT,
+        gl::RG32F => vk::R32G32_SFLOAT,
+        gl::RGB32F => vk::R32G32B32_SFLOAT,
+        gl::RGBA32F => vk::R32G32B32A32_SFLOAT,
+        _ => return None,
+    };
+    Some(vk_format)
+}
+
+/// Lay out a texture described by a KTX 1.1 header as a KTX 2.0 file
+pub(crate) fn encode<B: AsRef<[u8]>>(
+    info: &crate::HeaderInfo,
+    frames: &[(FrameInfo, B)],
+) -> Result<Vec<u8>> {
+    use byteorder::{LittleEndian as LE, WriteBytesExt as _};
+    use std::cmp::max;
+
+    let vk_format = match vk_format(info.gl_internal_format) {
+        Some(x) => x,
+        None => bail!(ErrorKind::UnsupportedFormat(info.gl_internal_format)),
+    };
+    let (gl_format, gl_type) = format::pixel_format(info.gl_internal_format).unwrap();
+    let ncomponents = match gl_format {
+        gl::RED => 1,
+        gl::RG => 2,
+        gl::RGB => 3,
+        _ => 4,
+    };
+    let component_size = match gl_type {
+        gl::UNSIGNED_BYTE | gl::BYTE => 1,
+        gl::UNSIGNED_SHORT | gl::SHORT | gl::HALF_FLOAT => 2,
+        _ => 4,
+    };
+    let pixel_size = ncomponents * component_size;
+
+    let dfd = data_format_descriptor(
+        ncomponents,
+        component_size,
+        gl_type,
+        format::is_srgb(info.gl_internal_format),
+    );
+    let kvd = key_value_data(info);
+
+    let nlevels = max(1, info.number_of_mipmap_levels) as usize;
+    let frames_per_level = frames.len() / nlevels;
+
+    // Level data without row padding
+    let levels: Vec<Vec<u8>> = frames
+        .chunks(frames_per_level)
+        .map(|level_frames| {
+            let (frame, _) = &level_frames[0];
+            let row_size = frame.pixel_width as usize * pixel_size;
+            let row_stride = (row_size + 3) & !3;
+            let mut data = vec![];
+            for (_, buf) in level_frames {
+                for row in buf.as_ref().chunks_exact(row_stride) {
+                    data.extend_from_slice(&row[..row_size]);
+                }
+            }
+            data
+        })
+        .collect();
+
+    let dfd_byte_offset = 80 + 24 * nlevels;
+    let kvd_byte_offset = dfd_byte_offset + dfd.len();
+    let mut data_offset = kvd_byte_offset + kvd.len();
+
+    // Levels are stored smallest first, aligned to lcm(pixel_size, 4)
+    let alignment = match pixel_size % 4 {
+        0 => pixel_size,
+        2 => pixel_size * 2,
+        _ => pixel_size * 4,
+    };
+    let mut level_offsets = vec![0; nlevels];
+    for (level, level_data) in levels.iter().enumerate().rev() {
+        data_offset = data_offset.div_ceil(alignment) * alignment;
+        level_offsets[level] = data_offset;
+        data_offset += level_data.len();
+    }
+
+    let mut out = Vec::with_capacity(data_offset);
+    out.extend_from_slice(&MAGIC);
+    for x in [
+        vk_format,
+        component_size as u32,
+        info.pixel_width,
+        info.pixel_height,
+        info.pixel_depth,
+        info.number_of_array_elements,
+        info.number_of_faces,
+        info.number_of_mipmap_levels,
+        0, // supercompressionScheme
+        dfd_byte_offset as u32,
+        dfd.len() as u32,
+        if kvd.is_empty() {
+            0
+        } else {
+            kvd_byte_offset as u32
+        },
+        kvd.len() as u32,
+    ]
+    .iter()
+    {
+        out.write_u32::<LE>(*x).unwrap();
+    }
+    out.write_u64::<LE>(0).unwrap(); // sgdByteOffset
+    out.write_u64::<LE>(0).unwrap(); // sgdByteLength
+    for (level_data, &offset) in levels.iter().zip(&level_offsets) {
+        out.write_u64::<LE>(offset as u64).unwrap();
+        out.write_u64::<LE>(level_data.len() as u64).unwrap();
+        out.write_u64::<LE>(level_data.len() as u64).unwrap();
+    }
+    out.extend_from_slice(&dfd);
+    out.extend_from_slice(&kvd);
+    for (level_data, &offset) in levels.iter().zip(&level_offsets).rev() {
+        out.resize(offset, 0);
+        out.extend_from_slice(level_data);
+    }
+    Ok(out)
+}
+
+/// Basic data format descriptor of an uncompressed format with
+/// `ncomponents` components of equal size, in R, G, B, A order
+fn data_format_descriptor(
+    ncomponents: usize,
+    component_size: usize,
+    gl_type: u32,
+    is_srgb: bool,
+) -> Vec<u8> {
+    use byteorder::{LittleEndian as LE, WriteBytesExt as _};
+
+    const KHR_DF_MODEL_RGBSDA: u32 = 1;
+    const KHR_DF_PRIMARIES_BT709: u32 = 1;
+    const KHR_DF_TRANSFER_LINEAR: u32 = 1;
+    const KHR_DF_TRANSFER_SRGB: u32 = 2;
+    const KHR_DF_SAMPLE_DATATYPE_LINEAR: u32 = 0x10;
+    const KHR_DF_SAMPLE_DATATYPE_SIGNED: u32 = 0x40;
+    const KHR_DF_SAMPLE_DATATYPE_FLOAT: u32 = 0x80;
+    const CHANNELS: [u32; 4] = [0, 1, 2, 15];
+
+    let block_size = 24 + 16 * ncomponents as u32;
+    let bits = component_size as u32 * 8;
+    let transfer = if is_srgb {
+        KHR_DF_TRANSFER_SRGB
+    } else {
+        KHR_DF_TRANSFER_LINEAR
+    };
+
+    let mut dfd = vec![];
+    let mut push = |x: u32| dfd.write_u32::<LE>(x).unwrap();
+    push(4 + block_size); // dfdTotalSize
+    push(0); // vendorId, descriptorType
+    push(2 | (block_size << 16)); // versionNumber, descriptorBlockSize
+    push(KHR_DF_MODEL_RGBSDA | (KHR_DF_PRIMARIES_BT709 << 8) | (transfer << 16));
+    push(0); // texelBlockDimension
+    push((ncomponents * component_size) as u32); // bytesPlane0
+    push(0);
+    for (i, &channel) in CHANNELS[..ncomponents].iter().enumerate() {
+        let (qualifiers, lower, upper) = match gl_type {
+            gl::BYTE | gl::SHORT => {
+                let max = (1_u32 << (bits - 1)) - 1;
+                (KHR_DF_SAMPLE_DATATYPE_SIGNED, max.wrapping_neg(), max)
+            }
+            gl::HALF_FLOAT | gl::FLOAT => (
+                KHR_DF_SAMPLE_DATATYPE_FLOAT | KHR_DF_SAMPLE_DATATYPE_SIGNED,
+                (-1.0_f32).to_bits(),
+                1.0_f32.to_bits(),
+            ),
+            _ => (0, 0, u32::MAX >> (32 - bits)),
+        };
+        // Alpha is never sRGB-encoded
+        let qualifiers = if is_srgb && channel == 15 {
+            qualifiers | KHR_DF_SAMPLE_DATATYPE_LINEAR
+        } else {
+            qualifiers
+        };
+        push((i as u32 * bits) | ((bits - 1) << 16) | ((channel | qualifiers) << 24));
+        push(0); // samplePosition
+        push(lower);
+        push(upper);
+    }
+    dfd
+}
+
+/// Key/value data in KTX 2.0 conventions: sorted by key, little-endian
+/// lengths, and `KTXorientation` in its short form (e.g. `rd`)
+fn key_value_data(info: &crate::HeaderInfo) -> Vec<u8> {
+    use byteorder::{LittleEndian as LE, WriteBytesExt as _};
+
+    let ndims = if info.pixel_depth > 0 {
+        3
+    } else if info.pixel_height > 0 {
+        2
+    } else {
+        1
+    };
+
+    let mut entries: Vec<(&str, Vec<u8>)> = info
+        .key_value_data
+        .iter()
+        .map(|(key, value)| match key {
+            "KTXorientation" => (key, short_orientation(value, ndims)),
+            _ => (key, value.to_vec()),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut kvd = vec![];
+    for (key, value) in entries {
+        let len = key.len() + 1 + value.len();
+        kvd.write_u32::<LE>(len as u32).unwrap();
+        kvd.extend_from_slice(key.as_bytes());
+        kvd.push(0);
+        kvd.extend_from_slice(&value);
+        kvd.resize((kvd.len() + 3) & !3, 0);
+    }
+    kvd
+}
+
+/// Convert a KTX 1.1 orientation such as `S=r,T=d` into `rd`
+fn short_orientation(value: &[u8], ndims: usize) -> Vec<u8> {
+    let text = value.strip_suffix(&[0]).unwrap_or(value);
+    let short: Option<Vec<u8>> = text
+        .split(|&b| b == b',')
+        .take(ndims)
+        .map(|x| match x {
+            [_, b'=', c] => Some(*c),
+            _ => None,
+        })
+        .collect();
+    match short {
+        Some(mut x) => {
+            x.push(0);
+            x
+        }
+        None => value.to_vec(),
+    }
+}
@@ -89,116 +89,170 @@ end
 // given in cubePadding and is retained for the same reason.
 //
 
-#![recursion_limit = "256"]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![recursion_limit = "1024"]
 #![deny(unsafe_code)]
 
-use error_chain::{bail, error_chain};
-use futures_core::stream::Stream;
-use tokio::io::{AsyncRead, AsyncReadExt as _};
+extern crate alloc;
 
-/// KTX decoder
-pub struct Decoder<R> {
-    read: R,
+/// Define `u32` constants along with a lookup of their symbolic names
+macro_rules! enumerations {
+    ($prefix:literal; $($name:ident = $value:literal,)*) => {
+        $(pub const $name: u32 = $value;)*
+
+        /// Symbolic name of a value
+        pub fn name(value: u32) -> Option<&'static str> {
+            match value {
+                $($value => Some(concat!($prefix, stringify!($name))),)*
+                _ => None,
+            }
+        }
+
+        /// Value of a symbolic name, with or without the prefix
+        pub fn from_name(name: &str) -> Option<u32> {
+            let name = name.strip_prefix($prefix).unwrap_or(name);
+            $(if name == stringify!($name) {
+                return Some($value);
+            })*
+            None
+        }
+    };
+}
+
+#[cfg(feature = "std")]
+pub mod array;
+pub mod codec;
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod cubemap;
+#[cfg(feature = "std")]
+mod decoder;
+#[cfg(feature = "std")]
+mod encoder;
+mod error;
+pub mod format;
+pub mod gl;
+#[cfg(feature = "glow")]
+pub mod glow;
+#[cfg(feature = "image")]
+pub mod image;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod ktx2;
+#[cfg(feature = "std")]
+pub mod mipmap;
+pub mod orientation;
+pub mod parse;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+pub mod trim;
+#[cfg(feature = "std")]
+pub mod validate;
+pub mod vk;
+
+#[cfg(feature = "std")]
+pub use decoder::{Chunk, Decoder, DecoderOptions, FrameOrder, TextureData};
+#[cfg(feature = "std")]
+pub use encoder::Encoder;
+pub use error::{Error, ErrorKind, Result};
+pub use parse::Entries;
+#[cfg(feature = "std")]
+pub use pool::BufferPool;
+#[cfg(feature = "std")]
+pub use validate::validate;
+
+use alloc::vec::Vec;
+
+/// Limits on the resources a `Decoder` may use
+///
+/// The header fields are checked before anything is allocated, and
+/// `imageSize` fields before the frames of their level are read.
+/// Exceeding a limit fails with `ErrorKind::LimitExceeded`.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Largest `pixelWidth`, `pixelHeight` and `pixelDepth`
+    pub max_dimension: u32,
+    /// Largest `numberOfArrayElements`
+    pub max_layers: u32,
+    /// Largest `numberOfMipmapLevels`, including generated levels
+    pub max_levels: u32,
+    /// Largest `bytesOfKeyValueData`
+    pub max_key_value_bytes: u32,
+    /// Largest size of all the frames together; `read_chunks_async`
+    /// only checks it against the frames it reads whole
+    pub max_total_bytes: u64,
 }
 
-impl<R> Decoder<R> {
-    pub fn new(read: R) -> Self {
-        Decoder { read }
+impl Limits {
+    /// No limits beyond those of the format, for trusted files such as
+    /// the ones given to the command-line tools
+    pub fn relaxed() -> Self {
+        Limits {
+            max_dimension: u32::MAX,
+            max_layers: u32::MAX,
+            max_levels: 32,
+            max_key_value_bytes: u32::MAX,
+            max_total_bytes: u64::MAX,
+        }
     }
 }
 
-impl<R> Decoder<R>
-where
-    R: AsyncRead + Unpin,
-{
-    /// Read the header and the following frames asynchronously
-    pub async fn read_async(
-        self,
-    ) -> Result<(
-        HeaderInfo,
-        impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin,
-    )> {
-        let mut read = self.read;
-
-        // Read the header
-        let info = read_header_async(&mut read).await?;
-
-        // Create the stream of the frames
-        let stream = new_async_stream(read, &info);
-
-        Ok((info, stream))
+impl Default for Limits {
+    /// 2 GiB of frames: enough for a 16384x16384 RGBA8 texture with
+    /// mipmaps (1.33 GiB)
+    fn default() -> Self {
+        Limits {
+            max_dimension: 1 << 16,
+            max_layers: 1 << 16,
+            max_levels: 32,
+            max_key_value_bytes: 1 << 24,
+            max_total_bytes: 2 << 30,
+        }
     }
 }
 
-fn new_async_stream(
-    read: impl AsyncRead + Unpin,
-    info: &HeaderInfo,
-) -> impl Stream<Item = Result<(FrameInfo, Vec<u8>)>> + Unpin {
-    use async_stream::try_stream;
-    use byteorder::{ByteOrder as _, NativeEndian as NE};
-    use std::cmp::max;
-
-    // Prepare parameters for the stream
-    let pixel_width = info.pixel_width;
-    let pixel_height = info.pixel_height;
-    let pixel_depth = info.pixel_depth;
-    let nlayers = max(1, info.number_of_array_elements);
-    let nfaces = max(1, info.number_of_faces);
-    let nlevels = info.number_of_mipmap_levels;
-
-    // Check if it is a non-array cubemap
-    let is_cubemap = info.number_of_faces == 6 && info.number_of_array_elements == 0;
-
-    Box::pin(try_stream! {
-        let mut read = read;
-        for level in 0..nlevels {
-            let image_size = {
-                let mut buf = [0_u8; 4];
-                let nread = read.read_exact(&mut buf).await?;
-                assert_eq!(nread, 4);
-                NE::read_u32(&buf)
-            };
-
-            // FIXME: what if image_size is not 4-byte aligned?
-            assert!(image_size % 4 == 0);
-
-            // dimensions of the current mipmap level
-            let pixel_width = max(1, pixel_width >> level);
-            let pixel_height = max(1, pixel_height >> level);
-            let pixel_depth = max(1, pixel_depth >> level);
-
-            // Compute buffer size
-            let face_size = if is_cubemap {
-                image_size
-            } else {
-                assert!(image_size % nlayers == 0);
-                let layer_size = image_size / nlayers;
-                assert!(layer_size % 4 == 0);
-                assert!(layer_size % nfaces == 0);
-                layer_size / nfaces
-            };
-            assert!(face_size % 4 == 0);
-            let buf_size = face_size as usize;
-
-            // Read pixels
-            for layer in 0..nlayers {
-                for face in 0..nfaces {
-                    let mut buf = vec![0_u8; buf_size];
-                    let nread = read.read_exact(&mut buf).await?;
-                    assert_eq!(nread, buf_size);
-                    let frame_info = FrameInfo {
-                        level,
-                        layer,
-                        face,
-                        pixel_width,
-                        pixel_height,
-                        pixel_depth,
-                    };
-                    yield (frame_info, buf);
-                }
-            }
+/// Kind of texture, from the dimensions, the number of faces and the
+/// number of array elements of the header (see `HeaderInfo::kind`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextureKind {
+    /// `pixelHeight` is 0 (`GL_TEXTURE_1D`)
+    Texture1D,
+    /// `GL_TEXTURE_1D_ARRAY`
+    Texture1DArray,
+    /// `pixelDepth` is 0 (`GL_TEXTURE_2D`)
+    Texture2D,
+    /// `GL_TEXTURE_2D_ARRAY`
+    Texture2DArray,
+    /// `pixelDepth` is not 0 (`GL_TEXTURE_3D`)
+    Texture3D,
+    /// 6 square faces (`GL_TEXTURE_CUBE_MAP`)
+    Cube,
+    /// `GL_TEXTURE_CUBE_MAP_ARRAY`
+    CubeArray,
+}
+
+impl TextureKind {
+    pub fn is_array(self) -> bool {
+        matches!(
+            self,
+            TextureKind::Texture1DArray | TextureKind::Texture2DArray | TextureKind::CubeArray
+        )
+    }
+
+    pub fn is_cube(self) -> bool {
+        matches!(self, TextureKind::Cube | TextureKind::CubeArray)
+    }
+
+    /// Number of dimensions of an image: 1, 2 or 3
+    pub fn dimensions(self) -> u32 {
+        match self {
+            TextureKind::Texture1D | TextureKind::Texture1DArray => 1,
+            TextureKind::Texture3D => 3,
+            _ => 2,
         }
-    })
+    }
 }
 
 /// KTX Frame Info
@@ -216,6 +270,55 @@ pub struct FrameInfo {
     pub pixel_depth: u32,
 }
 
+/// KTX Level Info
+///
+/// The data of a level holds its frames one after the other, in file
+/// order: by array layer, then by cubemap face.
+#[derive(Debug, Clone)]
+pub struct LevelInfo {
+    /// mip-map level
+    pub level: u32,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+    pub pixel_depth: u32,
+    /// Number of array layers (1 if not an array)
+    pub number_of_layers: u32,
+    /// Number of cubemap faces (1 if not a cubemap)
+    pub number_of_faces: u32,
+    /// Size of each frame in bytes
+    pub frame_size: u32,
+}
+
+impl LevelInfo {
+    /// Size of the data of the level in bytes
+    pub fn size(&self) -> usize {
+        self.frame_size as usize * self.number_of_layers as usize * self.number_of_faces as usize
+    }
+
+    /// Frames of the level and their ranges in the data of the level
+    pub fn frames(&self) -> impl Iterator<Item = (FrameInfo, core::ops::Range<usize>)> + '_ {
+        let frame_size = self.frame_size as usize;
+        (0..self.number_of_layers)
+            .flat_map(move |layer| (0..self.number_of_faces).map(move |face| (layer, face)))
+            .enumerate()
+            .map(move |(i, (layer, face))| {
+                let frame = FrameInfo {
+                    level: self.level,
+                    layer,
+                    face,
+                    pixel_width: self.pixel_width,
+                    pixel_height: self.pixel_height,
+                    pixel_depth: self.pixel_depth,
+                };
+                (frame, i * frame_size..(i + 1) * frame_size)
+            })
+    }
+}
+
+/// A frame and its data, as yielded by the `Decoder` stream
+#[cfg(feature = "std")]
+pub(crate) type Frame = (FrameInfo, Vec<u8>);
+
 /// KTX Header Info
 #[derive(Debug, Clone)]
 pub struct HeaderInfo {
@@ -317,93 +420,59 @@ pub struct HeaderInfo {
 
 impl HeaderInfo {
     pub fn mipmap_size(&self, level: u32) -> (u32, u32, u32) {
-        use std::cmp::max;
-        let w = max(1, self.pixel_width >> level);
-        let h = max(1, self.pixel_height >> level);
-        let d = max(1, self.pixel_depth >> level);
-        (w, h, d)
+        self.header().mipmap_size(level)
     }
-}
-
-async fn read_header_async(mut reader: impl AsyncRead + Unpin) -> Result<HeaderInfo> {
-    use byteorder::{ByteOrder as _, NativeEndian as NE};
 
-    let buf = {
-        let mut v = [0_u8; 64];
-        let nread = reader.read_exact(&mut v).await?;
-        assert_eq!(nread, 64);
-        v
-    };
-
-    // Check magic
-    {
-        let magic: &[u8] = &buf[0..12];
-        if magic != MAGIC {
-            let mut m = [0_u8; 12];
-            m.copy_from_slice(magic);
-            bail!(ErrorKind::InvalidFormat(m));
-        }
+    /// Kind of the texture, see `parse::Header::kind`
+    pub fn kind(&self) -> Result<TextureKind> {
+        self.header().kind()
     }
 
-    let endianness = NE::read_u32(&buf[12..16]);
-    let gl_type = NE::read_u32(&buf[16..20]);
-    let gl_type_size = NE::read_u32(&buf[20..24]);
-    let gl_format = NE::read_u32(&buf[24..28]);
-    let gl_internal_format = NE::read_u32(&buf[28..32]);
-    let gl_base_internal_format = NE::read_u32(&buf[32..36]);
-    let pixel_width = NE::read_u32(&buf[36..40]);
-    let pixel_height = NE::read_u32(&buf[40..44]);
-    let pixel_depth = NE::read_u32(&buf[44..48]);
-    let number_of_array_elements = NE::read_u32(&buf[48..52]);
-    let number_of_faces = NE::read_u32(&buf[52..56]);
-    let number_of_mipmap_levels = NE::read_u32(&buf[56..60]);
-    let bytes_of_key_value_data = NE::read_u32(&buf[60..64]);
-    let bytes_of_key_value_data = force_align(bytes_of_key_value_data);
-
-    if number_of_mipmap_levels == 0 {
-        bail!(ErrorKind::InvalidNumberOfMipmapLevels(
-            number_of_mipmap_levels
-        ));
+    /// The header, borrowing the key/value data
+    pub fn header(&self) -> parse::Header<'_> {
+        parse::Header {
+            gl_type: self.gl_type,
+            gl_type_size: self.gl_type_size,
+            gl_format: self.gl_format,
+            gl_internal_format: self.gl_internal_format,
+            gl_base_internal_format: self.gl_base_internal_format,
+            pixel_width: self.pixel_width,
+            pixel_height: self.pixel_height,
+            pixel_depth: self.pixel_depth,
+            number_of_array_elements: self.number_of_array_elements,
+            number_of_faces: self.number_of_faces,
+            number_of_mipmap_levels: self.number_of_mipmap_levels,
+            key_value_data: &self.key_value_data.raw,
+        }
     }
 
-    if endianness != ENDIANNESS {
-        bail!(ErrorKind::MismatchedEndianness(ENDIANNESS, endianness));
+    /// Orientation from the `KTXorientation` key, or the default
+    /// (`S=r,T=d,R=i`) if the key is missing or malformed
+    pub fn orientation(&self) -> orientation::Orientation {
+        self.key_value_data
+            .get("KTXorientation")
+            .and_then(orientation::Orientation::parse)
+            .unwrap_or_default()
     }
-
-    let mut kvbuf = vec![0; bytes_of_key_value_data as usize];
-    let nread = reader.read_exact(&mut kvbuf).await?;
-    assert_eq!(nread, bytes_of_key_value_data as _);
-
-    let info = HeaderInfo {
-        gl_type,
-        gl_type_size,
-        gl_format,
-        gl_internal_format,
-        gl_base_internal_format,
-        pixel_width,
-        pixel_height,
-        pixel_depth,
-        number_of_array_elements,
-        number_of_faces,
-        number_of_mipmap_levels,
-        key_value_data: KeyValueData { raw: kvbuf },
-    };
-    Ok(info)
 }
 
-error_chain! {
-    types {
-        Error, ErrorKind, ResultExt, Result;
-    }
-    foreign_links {
-        Io(::std::io::Error);
-    }
-    errors {
-        InvalidFormat(magic: [u8;12]) {
-        }
-        MismatchedEndianness(expect: u32, actual: u32) {
-        }
-        InvalidNumberOfMipmapLevels(v: u32) {
+impl From<parse::Header<'_>> for HeaderInfo {
+    fn from(header: parse::Header<'_>) -> Self {
+        HeaderInfo {
+            gl_type: header.gl_type,
+            gl_type_size: header.gl_type_size,
+            gl_format: header.gl_format,
+            gl_internal_format: header.gl_internal_format,
+            gl_base_internal_format: header.gl_base_internal_format,
+            pixel_width: header.pixel_width,
+            pixel_height: header.pixel_height,
+            pixel_depth: header.pixel_depth,
+            number_of_array_elements: header.number_of_array_elements,
+            number_of_faces: header.number_of_faces,
+            number_of_mipmap_levels: header.number_of_mipmap_levels,
+            key_value_data: KeyValueData {
+                raw: header.key_value_data.to_vec(),
+            },
         }
     }
 }
@@ -413,50 +482,36 @@ const MAGIC: [u8; 12] = [
 ];
 const ENDIANNESS: u32 = 0x0403_0201;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct KeyValueData {
     raw: Vec<u8>,
 }
 
-pub struct Entries<'a>(&'a [u8]);
-
 impl KeyValueData {
-    pub fn iter(&self) -> Entries {
+    pub fn iter(&self) -> Entries<'_> {
         Entries(&self.raw)
     }
-}
-
-impl<'a> Iterator for Entries<'a> {
-    type Item = (&'a str, &'a [u8]);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        use byteorder::{ByteOrder, NativeEndian};
-        use std::str::from_utf8;
+    /// Append a key/value pair.
+    /// String values should include their NUL terminator.
+    pub fn push(&mut self, key: &str, value: &[u8]) {
+        let len = key.len() + 1 + value.len();
+        self.raw.extend_from_slice(&(len as u32).to_ne_bytes());
+        self.raw.extend_from_slice(key.as_bytes());
+        self.raw.push(0);
+        self.raw.extend_from_slice(value);
+        self.raw
+            .resize(force_align(self.raw.len() as u32) as usize, 0);
+    }
 
-        if self.0.is_empty() {
-            return None;
-        }
-        let (len_bytes, resting) = self.0.split_at(4);
-        let len = NativeEndian::read_u32(len_bytes);
-        let (kv, nextbuf) = resting.split_at(force_align(len) as usize);
-        let (kv, _padding) = kv.split_at(len as usize);
-        self.0 = nextbuf;
-        let nul_idx = kv
-            .iter()
-            .enumerate()
-            .filter(|(_, x)| **x == 0)
-            .map(|(i, _)| i)
-            .nth(0)
-            .unwrap();
-        let (key, value) = kv.split_at(nul_idx);
-        let value = value.split_at(1).1;
-        let key = from_utf8(key).unwrap();
-        Some((key, value))
+    /// Value of the first pair with the given key
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
     }
 }
 
-impl std::fmt::Debug for KeyValueData {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl core::fmt::Debug for KeyValueData {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(f, "KeyValueData[")?;
         for (key, value) in self.iter() {
             write!(f, "({:?}, bytes(len={})), ", key, value.len())?;
@@ -0,0 +1,259 @@
+//! Mipmap Generation for Uncompressed Textures
+//!
+//! Each level is resampled from the previous one with a separable
+//! filter. Non-power-of-two sizes round down (`max(1, size >> 1)`, as
+//! in OpenGL), so a source pixel may contribute to two destination
+//! pixels with fractional weights. Samples outside the image are
+//! clamped to the edge.
+//!
+//! sRGB formats are filtered in linear space; alpha is always linear.
+
+use crate::{convert, format, FrameInfo, HeaderInfo, Result};
+use std::f64::consts::PI;
+
+/// Resampling filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Average of the covered pixels. Fast and free of ringing,
+    /// but blurrier than the windowed sinc filters.
+    Box,
+    /// Lanczos-windowed sinc with 3 lobes
+    Lanczos3,
+    /// Kaiser-windowed sinc (width 3, alpha 4)
+    Kaiser,
+}
+
+impl Filter {
+    /// Radius of the filter in destination pixels
+    fn radius(self) -> f64 {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Lanczos3 | Filter::Kaiser => 3.0,
+        }
+    }
+
+    fn weight(self, x: f64) -> f64 {
+        match self {
+            Filter::Box => {
+                if x.abs() <= 0.5 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Filter::Lanczos3 => {
+                if x.abs() < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+            Filter::Kaiser => {
+                const WIDTH: f64 = 3.0;
+                const ALPHA: f64 = 4.0;
+                let t = x / WIDTH;
+                if t.abs() < 1.0 {
+                    sinc(x) * bessel_i0(ALPHA * (1.0 - t * t).sqrt()) / bessel_i0(ALPHA)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+/// Number of levels in a full mipmap pyramid
+pub fn level_count(width: u32, height: u32, depth: u32) -> u32 {
+    let size = width.max(height).max(depth).max(1);
+    32 - size.leading_zeros()
+}
+
+/// Downsample RGBA32F pixels (`width * height * depth * 4` values,
+/// in linear space) to the next mipmap level.
+///
+/// Returns the pixels and the size of the next level.
+pub fn downsample(
+    pixels: &[f32],
+    (width, height, depth): (u32, u32, u32),
+    filter: Filter,
+) -> (Vec<f32>, (u32, u32, u32)) {
+    let next = |x: u32| std::cmp::max(1, x >> 1);
+    let mut size = [width as usize, height as usize, depth as usize];
+    let mut pixels = pixels[..size.iter().product::<usize>() * 4].to_vec();
+    for axis in 0..3 {
+        let dst_len = next(size[axis] as u32) as usize;
+        if dst_len != size[axis] {
+            pixels = resample_axis(&pixels, size, axis, dst_len, filter);
+            size[axis] = dst_len;
+        }
+    }
+    let size = (size[0] as u32, size[1] as u32, size[2] as u32);
+    (pixels, size)
+}
+
+/// Generate all smaller levels of an uncompressed frame from the
+/// `Decoder` stream, down to 1x1.
+///
+/// The frames are returned in level order and keep the layer and the
+/// face of `frame`.
+pub fn generate(
+    info: &HeaderInfo,
+    frame: &FrameInfo,
+    buf: &[u8],
+    filter: Filter,
+) -> Result<Vec<(FrameInfo, Vec<u8>)>> {
+    let is_srgb = format::is_srgb(info.gl_internal_format);
+    let mut pixels = convert::to_rgba32f(info, frame, buf)?;
+    if is_srgb {
+        for px in pixels.chunks_exact_mut(4) {
+            for c in &mut px[..3] {
+                *c = srgb_to_linear(*c);
+            }
+        }
+    }
+
+    let mut size = (frame.pixel_width, frame.pixel_height, frame.pixel_depth);
+    let nlevels = frame.level + level_count(size.0, size.1, size.2);
+    let mut frames = vec![];
+    for level in frame.level + 1..nlevels {
+        let (next_pixels, next_size) = downsample(&pixels, size, filter);
+        pixels = next_pixels;
+        size = next_size;
+
+        let mut encoded = pixels.clone();
+        if is_srgb {
+            for px in encoded.chunks_exact_mut(4) {
+                for c in &mut px[..3] {
+                    *c = linear_to_srgb(*c);
+                }
+            }
+        }
+        let (width, height, depth) = size;
+        let buf =
+            convert::from_rgba32f(info.gl_format, info.gl_type, width, height, depth, &encoded)?;
+        let frame = FrameInfo {
+            level,
+            pixel_width: width,
+            pixel_height: height,
+            pixel_depth: depth,
+            ..frame.clone()
+        };
+        frames.push((frame, buf));
+    }
+    Ok(frames)
+}
+
+/// Resample one axis of an RGBA image from `size[axis]` to `dst_len` pixels
+fn resample_axis(
+    pixels: &[f32],
+    size: [usize; 3],
+    axis: usize,
+    dst_len: usize,
+    filter: Filter,
+) -> Vec<f32> {
+    let src_len = size[axis];
+    let weights = weights(src_len, dst_len, filter);
+
+    // Distance between neighbouring pixels along the axis
+    let stride = size[..axis].iter().product::<usize>();
+    let mut dst_size = size;
+    dst_size[axis] = dst_len;
+    let outer = size[axis + 1..].iter().product::<usize>();
+
+    let mut out = vec![0.0_f32; dst_size.iter().product::<usize>() * 4];
+    for o in 0..outer {
+        for inner in 0..stride {
+            for (i, taps) in weights.iter().enumerate() {
+                let mut sum = [0.0_f64; 4];
+                for &(j, w) in taps {
+                    let src = ((o * src_len + j) * stride + inner) * 4;
+                    for c in 0..4 {
+                        sum[c] += f64::from(pixels[src + c]) * w;
+                    }
+                }
+                let dst = ((o * dst_len + i) * stride + inner) * 4;
+                for c in 0..4 {
+                    out[dst + c] = sum[c] as f32;
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Normalized filter taps `(source index, weight)` of each destination pixel
+fn weights(src_len: usize, dst_len: usize, filter: Filter) -> Vec<Vec<(usize, f64)>> {
+    let scale = src_len as f64 / dst_len as f64;
+    (0..dst_len)
+        .map(|i| {
+            let center = (i as f64 + 0.5) * scale;
+            let radius = filter.radius() * scale;
+            let first = (center - radius).floor() as isize;
+            let last = (center + radius).ceil() as isize;
+
+            let mut taps: Vec<(usize, f64)> = vec![];
+            for j in first..last {
+                let w = match filter {
+                    // Exact coverage of the source pixel by the destination pixel
+                    Filter::Box => {
+                        let lo = (j as f64).max(center - radius);
+                        let hi = (j as f64 + 1.0).min(center + radius);
+                        (hi - lo).max(0.0)
+                    }
+                    _ => filter.weight((j as f64 + 0.5 - center) / scale),
+                };
+                if w == 0.0 {
+                    continue;
+                }
+                // Clamp to the edge
+                let j = j.clamp(0, src_len as isize - 1) as usize;
+                match taps.iter_mut().find(|(k, _)| *k == j) {
+                    Some(tap) => tap.1 += w,
+                    None => taps.push((j, w)),
+                }
+            }
+            let total: f64 = taps.iter().map(|(_, w)| w).sum();
+            for tap in &mut taps {
+                tap.1 /= total;
+            }
+            taps
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Modified Bessel function of the first kind, order 0
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)).powi(2);
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,346 @@
+//! Orientation of Texture Data
+//!
+//! `KTXorientation` records in which directions the texture coordinates
+//! S, T and R increase in the stored data, e.g. `S=r,T=d` (KTX 1.1) or
+//! `rd` (KTX 2.0): the first row is the top of the image. `reorient`
+//! rewrites frames into another orientation.
+//!
+//! Uncompressed frames are flipped pixel by pixel. Block-compressed
+//! frames are flipped by reordering the blocks and the pixels inside
+//! each block, which is lossless for BC1–BC5 (S3TC and RGTC) and ETC1.
+//! Other compressed formats cannot be flipped without re-encoding.
+//!
+//! Flipping an ETC1 block across its two subblocks swaps their base
+//! colors. In differential mode the second color is stored as a
+//! difference of -4 to 3 from the first, so blocks with a difference
+//! of -4 cannot be swapped and make the flip fail.
+
+use crate::error::bail;
+use crate::{convert, format, gl, ErrorKind, FrameInfo, HeaderInfo, Result};
+use alloc::{vec, vec::Vec};
+
+/// Directions in which the texture coordinates increase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Orientation {
+    /// S increases to the right (`r`) rather than to the left (`l`)
+    pub right: bool,
+    /// T increases downwards (`d`) rather than upwards (`u`)
+    pub down: bool,
+    /// R increases into the screen (`i`) rather than out of it (`o`)
+    pub into: bool,
+}
+
+impl Default for Orientation {
+    /// `S=r,T=d,R=i`, the orientation of files without `KTXorientation`
+    fn default() -> Self {
+        Orientation {
+            right: true,
+            down: true,
+            into: true,
+        }
+    }
+}
+
+impl Orientation {
+    /// Parse a `KTXorientation` value in the KTX 1.1 form (`S=r,T=d`)
+    /// or in the KTX 2.0 form (`rd`), with or without a NUL terminator.
+    ///
+    /// Axes that are not given keep their default direction.
+    pub fn parse(value: &[u8]) -> Option<Orientation> {
+        let value = value.strip_suffix(&[0]).unwrap_or(value);
+        let value = core::str::from_utf8(value).ok()?;
+        let letters: Vec<(Option<char>, char)> = if value.contains('=') {
+            value
+                .split(',')
+                .map(|part| match part.as_bytes() {
+                    [axis, b'=', c] => Some((Some(*axis as char), *c as char)),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?
+        } else {
+            value.chars().map(|c| (None, c)).collect()
+        };
+        if letters.len() > 3 {
+            return None;
+        }
+
+        let mut orientation = Orientation::default();
+        for (i, (axis, c)) in letters.into_iter().enumerate() {
+            if matches!(axis, Some(axis) if axis != ['S', 'T', 'R'][i]) {
+                return None;
+            }
+            match (i, c) {
+                (0, 'r') | (0, 'l') => orientation.right = c == 'r',
+                (1, 'd') | (1, 'u') => orientation.down = c == 'd',
+                (2, 'i') | (2, 'o') => orientation.into = c == 'i',
+                _ => return None,
+            }
+        }
+        Some(orientation)
+    }
+}
+
+/// Axes to mirror
+#[derive(Clone, Copy)]
+struct Flip {
+    x: bool,
+    y: bool,
+    z: bool,
+}
+
+/// Rewrite a frame of the `Decoder` stream from the orientation of the
+/// file (`HeaderInfo::orientation`) into `to`.
+///
+/// Fails with `UnsupportedFormat` if the frame has to be flipped but
+/// the compressed format cannot be flipped losslessly, and with
+/// `UnflippableBlocks` if the blocks of this frame cannot: frames can
+/// only be flipped along an axis whose size is a multiple of the block
+/// size, or which fits in a single block, and some ETC1 blocks cannot
+/// be flipped across their subblocks.
+pub fn reorient(
+    info: &HeaderInfo,
+    frame: &FrameInfo,
+    buf: &[u8],
+    to: Orientation,
+) -> Result<Vec<u8>> {
+    let from = info.orientation();
+    let flip = Flip {
+        x: from.right != to.right,
+        y: from.down != to.down,
+        z: from.into != to.into,
+    };
+    if !(flip.x || flip.y || flip.z) {
+        return Ok(buf.to_vec());
+    }
+    let size = (
+        frame.pixel_width as usize,
+        frame.pixel_height as usize,
+        frame.pixel_depth as usize,
+    );
+    if info.gl_type == 0 {
+        return flip_blocks(info.gl_internal_format, buf, size, flip);
+    }
+    match convert::pixel_size(info.gl_format, info.gl_type) {
+        Some(pixel_size) => flip_pixels(buf, pixel_size, size, flip),
+        None => bail!(ErrorKind::UnsupportedPixelFormat(
+            info.gl_format,
+            info.gl_type
+        )),
+    }
+}
+
+fn flip_pixels(
+    buf: &[u8],
+    pixel_size: usize,
+    (width, height, depth): (usize, usize, usize),
+    flip: Flip,
+) -> Result<Vec<u8>> {
+    // Rows are padded to 4 bytes
+    let row_size = width * pixel_size;
+    let row_stride = (row_size + 3) & !3;
+    let expect = row_stride * height * depth;
+    if buf.len() != expect {
+        bail!(ErrorKind::InvalidBufferSize(expect, buf.len()));
+    }
+
+    let mut out = buf.to_vec();
+    for z in 0..depth {
+        for y in 0..height {
+            let src = (z * height + y) * row_stride;
+            let src = &buf[src..src + row_size];
+            let dst = (mirror(z, depth, flip.z) * height + mirror(y, height, flip.y)) * row_stride;
+            let dst = &mut out[dst..dst + row_size];
+            if flip.x {
+                let pixels = src.chunks_exact(pixel_size);
+                for (s, d) in pixels.zip(dst.chunks_exact_mut(pixel_size).rev()) {
+                    d.copy_from_slice(s);
+                }
+            } else {
+                dst.copy_from_slice(src);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Flip the pixels of a block; `extent` is the number of pixels of the
+/// image in the block, which is smaller than the block for small mips.
+type FlipBlock = fn(&mut [u8], Flip, (usize, usize)) -> Result<()>;
+
+fn flip_blocks(
+    gl_internal_format: u32,
+    buf: &[u8],
+    (width, height, depth): (usize, usize, usize),
+    flip: Flip,
+) -> Result<Vec<u8>> {
+    let flip_block: FlipBlock = match gl_internal_format {
+        gl::COMPRESSED_RGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_RGBA_S3TC_DXT1_EXT
+        | gl::COMPRESSED_SRGB_S3TC_DXT1_EXT
+        | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT => flip_bc1,
+        gl::COMPRESSED_RGBA_S3TC_DXT3_EXT | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT => flip_bc2,
+        gl::COMPRESSED_RGBA_S3TC_DXT5_EXT | gl::COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT => flip_bc3,
+        gl::COMPRESSED_RED_RGTC1 | gl::COMPRESSED_SIGNED_RED_RGTC1 => flip_bc4,
+        gl::COMPRESSED_RG_RGTC2 | gl::COMPRESSED_SIGNED_RG_RGTC2 => flip_bc5,
+        gl::ETC1_RGB8_OES => flip_etc1,
+        _ => bail!(ErrorKind::UnsupportedFormat(gl_internal_format)),
+    };
+    let block = format::compressed_block_size(gl_internal_format).unwrap();
+    let (block_width, block_height) = (block.width as usize, block.height as usize);
+    let block_size = block.bytes as usize;
+
+    let columns = width.div_ceil(block_width);
+    let rows = height.div_ceil(block_height);
+    let expect = columns * rows * depth * block_size;
+    if buf.len() != expect {
+        bail!(ErrorKind::InvalidBufferSize(expect, buf.len()));
+    }
+
+    // Pixels of a partial block would have to move into another block
+    if (flip.x && columns > 1 && width % block_width != 0)
+        || (flip.y && rows > 1 && height % block_height != 0)
+    {
+        bail!(ErrorKind::UnflippableBlocks(gl_internal_format));
+    }
+    let extent = (width.min(block_width), height.min(block_height));
+
+    let mut out = vec![0_u8; buf.len()];
+    let blocks = buf.chunks_exact(block_size);
+    for (i, src) in blocks.enumerate() {
+        let x = i % columns;
+        let y = i / columns % rows;
+        let z = i / columns / rows;
+        let j = (mirror(z, depth, flip.z) * rows + mirror(y, rows, flip.y)) * columns
+            + mirror(x, columns, flip.x);
+        let dst = &mut out[j * block_size..(j + 1) * block_size];
+        dst.copy_from_slice(src);
+        flip_block(dst, flip, extent)?;
+    }
+    Ok(out)
+}
+
+/// Mirror the index `i` of the first `n` positions
+fn mirror(i: usize, n: usize, flip: bool) -> usize {
+    if flip && i < n {
+        n - 1 - i
+    } else {
+        i
+    }
+}
+
+/// Move the `bits`-bit indices of a 4x4 block, stored row by row from
+/// the least significant bits
+fn flip_indices(indices: u64, bits: usize, flip: Flip, (w, h): (usize, usize)) -> u64 {
+    let mask = (1 << bits) - 1;
+    let mut out = 0;
+    for y in 0..4 {
+        for x in 0..4 {
+            let index = (indices >> ((y * 4 + x) * bits)) & mask;
+            let (x, y) = (mirror(x, w, flip.x), mirror(y, h, flip.y));
+            out |= index << ((y * 4 + x) * bits);
+        }
+    }
+    out
+}
+
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, &b| (acc << 8) | u64::from(b))
+}
+
+fn write_le(bytes: &mut [u8], value: u64) {
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = (value >> (i * 8)) as u8;
+    }
+}
+
+/// BC1 (DXT1): two RGB565 endpoints and 2-bit indices
+fn flip_bc1(block: &mut [u8], flip: Flip, extent: (usize, usize)) -> Result<()> {
+    let indices = flip_indices(read_le(&block[4..8]), 2, flip, extent);
+    write_le(&mut block[4..8], indices);
+    Ok(())
+}
+
+/// BC2 (DXT3): 4-bit explicit alpha and a BC1 color block
+fn flip_bc2(block: &mut [u8], flip: Flip, extent: (usize, usize)) -> Result<()> {
+    let alpha = flip_indices(read_le(&block[..8]), 4, flip, extent);
+    write_le(&mut block[..8], alpha);
+    flip_bc1(&mut block[8..], flip, extent)
+}
+
+/// BC3 (DXT5): a BC4 alpha block and a BC1 color block
+fn flip_bc3(block: &mut [u8], flip: Flip, extent: (usize, usize)) -> Result<()> {
+    flip_bc4(&mut block[..8], flip, extent)?;
+    flip_bc1(&mut block[8..], flip, extent)
+}
+
+/// BC4 (RGTC1): two 8-bit endpoints and 3-bit indices
+fn flip_bc4(block: &mut [u8], flip: Flip, extent: (usize, usize)) -> Result<()> {
+    let indices = flip_indices(read_le(&block[2..8]), 3, flip, extent);
+    write_le(&mut block[2..8], indices);
+    Ok(())
+}
+
+/// BC5 (RGTC2): two BC4 blocks
+fn flip_bc5(block: &mut [u8], flip: Flip, extent: (usize, usize)) -> Result<()> {
+    flip_bc4(&mut block[..8], flip, extent)?;
+    flip_bc4(&mut block[8..], flip, extent)
+}
+
+/// ETC1: two subblocks (2x4 side by side, or 4x2 stacked if the flip
+/// bit is set) with a base color and a table codeword each, and 2-bit
+/// indices stored column by column in two bit planes
+fn flip_etc1(block: &mut [u8], flip: Flip, (w, h): (usize, usize)) -> Result<()> {
+    let mut bits = u64::from_be_bytes([
+        block[0], block[1], block[2], block[3], block[4], block[5], block[6], block[7],
+    ]);
+    let differential = (bits >> 33) & 1 == 1;
+    let stacked = (bits >> 32) & 1 == 1;
+
+    // Pixel (x, y) is bit x * 4 + y of both planes
+    let mut indices = 0_u64;
+    for x in 0..4 {
+        for y in 0..4 {
+            let (dx, dy) = (mirror(x, w, flip.x), mirror(y, h, flip.y));
+            for plane in [0, 16].iter() {
+                let bit = (bits >> (plane + x * 4 + y)) & 1;
+                indices |= bit << (plane + dx * 4 + dy);
+            }
+        }
+    }
+    bits = (bits & !0xFFFF_FFFF) | indices;
+
+    // Pixels that move across the subblocks swap the subblocks
+    let (across, n) = if stacked { (flip.y, h) } else { (flip.x, w) };
+    if across && n > 2 {
+        if n == 3 {
+            bail!(ErrorKind::UnflippableBlocks(gl::ETC1_RGB8_OES));
+        }
+        // Table codewords
+        let cw1 = (bits >> 37) & 7;
+        let cw2 = (bits >> 34) & 7;
+        bits = (bits & !(0x3F << 34)) | (cw2 << 37) | (cw1 << 34);
+        // Base colors
+        for shift in [56, 48, 40].iter() {
+            let c = (bits >> shift) & 0xFF;
+            let swapped = if differential {
+                // 5-bit base color and a signed 3-bit difference
+                let base = (c >> 3) as i32;
+                let delta = ((c as i32 & 7) ^ 4) - 4;
+                let base2 = base + delta;
+                if !(0..32).contains(&base2) || delta == -4 {
+                    bail!(ErrorKind::UnflippableBlocks(gl::ETC1_RGB8_OES));
+                }
+                ((base2 << 3) | (-delta & 7)) as u64
+            } else {
+                // Two 4-bit base colors
+                ((c & 0xF) << 4) | (c >> 4)
+            };
+            bits = (bits & !(0xFF << shift)) | (swapped << shift);
+        }
+    }
+    block[..8].copy_from_slice(&bits.to_be_bytes());
+    Ok(())
+}
@@ -0,0 +1,562 @@
+//! Parsing of Files in Memory
+//!
+//! The header, the key/value data and the frames of a KTX 1.1 file held
+//! in a `&[u8]`. Frames borrow their data from the slice: nothing here
+//! allocates, and nothing needs `std`. The `Decoder` reads files
+//! through the same checks.
+//!
+//! ```
+//! use ktx_async::{parse, Limits};
+//!
+//! fn upload(data: &[u8]) -> ktx_async::Result<()> {
+//!     let (header, frames) = parse::read(data, &Limits::default())?;
+//!     for frame in frames {
+//!         let (frame, buf) = frame?;
+//!         // glCompressedTexImage2D(..., header.gl_internal_format, buf)
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::error::{bail, ResultExt as _};
+use crate::{format, ErrorKind, FrameInfo, Limits, Result, TextureKind, ENDIANNESS, MAGIC};
+use byteorder::{ByteOrder as _, NativeEndian as NE};
+use core::cmp::max;
+use core::convert::TryFrom as _;
+
+/// Header of a KTX 1.1 file, borrowing its key/value data
+///
+/// The fields are those of `HeaderInfo`.
+#[derive(Debug, Clone, Copy)]
+pub struct Header<'a> {
+    pub gl_type: u32,
+    pub gl_type_size: u32,
+    pub gl_format: u32,
+    pub gl_internal_format: u32,
+    pub gl_base_internal_format: u32,
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+    pub pixel_depth: u32,
+    pub number_of_array_elements: u32,
+    pub number_of_faces: u32,
+    pub number_of_mipmap_levels: u32,
+    /// Key/value pairs, including the padding of the last one
+    pub key_value_data: &'a [u8],
+}
+
+impl<'a> Header<'a> {
+    pub fn mipmap_size(&self, level: u32) -> (u32, u32, u32) {
+        let size = (self.pixel_width, self.pixel_height, self.pixel_depth);
+        mipmap_size(size, level)
+    }
+
+    pub fn key_values(&self) -> Entries<'a> {
+        Entries(self.key_value_data)
+    }
+
+    /// The header without its key/value data
+    pub(crate) fn without_key_values(&self) -> Header<'static> {
+        Header {
+            key_value_data: &[],
+            ..*self
+        }
+    }
+
+    /// Offset of the first `imageSize` field in the file
+    pub fn data_offset(&self) -> u64 {
+        64 + self.key_value_data.len() as u64
+    }
+
+    /// Kind of the texture
+    ///
+    /// Fails with `ErrorKind::InvalidDimensions` at the offset of the
+    /// offending field if no texture has the dimensions: a 1D texture
+    /// with a depth, an array of 3D textures, a 3D cubemap or a
+    /// cubemap with faces that are not square.
+    pub fn kind(&self) -> Result<TextureKind> {
+        match self.classify() {
+            (kind, None) => Ok(kind),
+            (_, Some((what, offset))) => Err(ErrorKind::InvalidDimensions(what)).at_offset(offset),
+        }
+    }
+
+    /// Whether it is a non-array cubemap, whose `imageSize` fields
+    /// hold the size of one face
+    ///
+    /// Unlike `kind`, it holds for cubemaps with invalid dimensions,
+    /// which are still laid out face by face.
+    pub fn is_cubemap(&self) -> bool {
+        self.classify().0 == TextureKind::Cube
+    }
+
+    /// Kind of the texture from its faces and array elements first,
+    /// and what makes the dimensions invalid, at the offset of the
+    /// offending field
+    fn classify(&self) -> (TextureKind, Option<(&'static str, u64)>) {
+        let is_array = self.number_of_array_elements > 0;
+        if self.number_of_faces == 6 {
+            let kind = if is_array {
+                TextureKind::CubeArray
+            } else {
+                TextureKind::Cube
+            };
+            let invalid = if self.pixel_depth > 0 {
+                Some(("3D cubemap", 44))
+            } else if self.pixel_width != self.pixel_height {
+                Some(("cubemap faces are not square", 40))
+            } else {
+                None
+            };
+            return (kind, invalid);
+        }
+        match (self.pixel_height, self.pixel_depth) {
+            (0, 0) if is_array => (TextureKind::Texture1DArray, None),
+            (0, 0) => (TextureKind::Texture1D, None),
+            (0, _) if is_array => (
+                TextureKind::Texture1DArray,
+                Some(("1D texture with a pixelDepth", 44)),
+            ),
+            (0, _) => (
+                TextureKind::Texture1D,
+                Some(("1D texture with a pixelDepth", 44)),
+            ),
+            (_, 0) if is_array => (TextureKind::Texture2DArray, None),
+            (_, 0) => (TextureKind::Texture2D, None),
+            _ if is_array => (TextureKind::Texture3D, Some(("array of 3D textures", 44))),
+            _ => (TextureKind::Texture3D, None),
+        }
+    }
+}
+
+fn mipmap_size((width, height, depth): (u32, u32, u32), level: u32) -> (u32, u32, u32) {
+    let w = max(1, width.checked_shr(level).unwrap_or(0));
+    let h = max(1, height.checked_shr(level).unwrap_or(0));
+    let d = max(1, depth.checked_shr(level).unwrap_or(0));
+    (w, h, d)
+}
+
+/// Parse the header and the key/value data at the start of `data`
+pub fn read_header<'a>(data: &'a [u8], limits: &Limits) -> Result<Header<'a>> {
+    let fixed = match data.get(..64).map(<&[u8; 64]>::try_from) {
+        Some(Ok(x)) => x,
+        _ => return Err(ErrorKind::UnexpectedEnd).at_offset(0),
+    };
+    let (mut header, key_value_bytes) = parse_header(fixed, limits, false)?;
+    let key_value_data = match data.get(64..64 + key_value_bytes as usize) {
+        Some(x) => x,
+        None => return Err(ErrorKind::UnexpectedEnd).at_offset(64),
+    };
+    check_key_value_data(key_value_data)?;
+    header.key_value_data = key_value_data;
+    Ok(header)
+}
+
+/// Parse a whole file: its header, and the frames that follow in file
+/// order (by level, then by array layer, then by cubemap face)
+///
+/// The iterator ends after the first error. A file that ends early
+/// yields its complete frames, then an error for which
+/// `Error::is_truncation` holds.
+pub fn read<'a>(data: &'a [u8], limits: &Limits) -> Result<(Header<'a>, Frames<'a>)> {
+    let header = read_header(data, limits)?;
+    check_total_size(&header, limits)?;
+    let cursor = FrameCursor::new(&header, header.number_of_mipmap_levels, limits);
+    Ok((header, Frames { data, cursor }))
+}
+
+/// Frames of a file in memory, see `read`
+pub struct Frames<'a> {
+    data: &'a [u8],
+    cursor: FrameCursor,
+}
+
+impl<'a> Frames<'a> {
+    fn next_frame(&mut self) -> Result<Option<(FrameInfo, &'a [u8])>> {
+        let data = self.data;
+        let slice = |offset: u64, len: usize| {
+            let start = usize::try_from(offset).ok()?;
+            data.get(start..start.checked_add(len)?)
+        };
+        if let Some((offset, level)) = self.cursor.image_size_field() {
+            let image_size = match slice(offset, 4) {
+                Some(buf) => NE::read_u32(buf),
+                None => {
+                    return Err(ErrorKind::UnexpectedEnd)
+                        .at_offset(offset)
+                        .in_level(level)
+                }
+            };
+            self.cursor.set_image_size(image_size)?;
+        }
+        let (frame, offset, size) = match self.cursor.next_frame() {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        match slice(offset, size) {
+            Some(buf) => Ok(Some((frame, buf))),
+            None => Err(ErrorKind::UnexpectedEnd).at_offset(offset).in_frame(
+                frame.level,
+                frame.layer,
+                frame.face,
+            ),
+        }
+    }
+}
+
+impl<'a> Iterator for Frames<'a> {
+    type Item = Result<(FrameInfo, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.cursor.is_done() {
+            return None;
+        }
+        let result = self.next_frame();
+        if result.is_err() {
+            self.cursor.finish();
+        }
+        result.transpose()
+    }
+}
+
+/// Position in the frames of a file, for decoders that read the
+/// `imageSize` fields and the frames in file order
+///
+/// Before each frame, `image_size_field` tells whether the `imageSize`
+/// field of a new level has to be read first.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameCursor {
+    header: Header<'static>,
+    size: (u32, u32, u32),
+    nlevels: u32,
+    nlayers: u32,
+    nfaces: u32,
+    is_cubemap: bool,
+    limits: Limits,
+    /// Level and index (`layer * nfaces + face`) of the next frame
+    level: u32,
+    index: u32,
+    /// Size of the frames of the current level
+    frame_size: u32,
+    /// Size of the levels so far
+    total: u64,
+    /// Offset of the next field or frame
+    offset: u64,
+}
+
+impl FrameCursor {
+    /// Start at the first `imageSize` field, reading `nlevels` levels
+    pub(crate) fn new(header: &Header<'_>, nlevels: u32, limits: &Limits) -> Self {
+        FrameCursor {
+            header: header.without_key_values(),
+            size: (header.pixel_width, header.pixel_height, header.pixel_depth),
+            nlevels,
+            nlayers: max(1, header.number_of_array_elements),
+            nfaces: max(1, header.number_of_faces),
+            is_cubemap: header.is_cubemap(),
+            limits: limits.clone(),
+            level: 0,
+            index: 0,
+            frame_size: 0,
+            total: 0,
+            offset: header.data_offset(),
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.level >= self.nlevels
+    }
+
+    /// Stop after an error
+    pub(crate) fn finish(&mut self) {
+        self.level = self.nlevels;
+    }
+
+    /// Offset and level of the `imageSize` field to read before the
+    /// next frame, if it starts a level
+    pub(crate) fn image_size_field(&self) -> Option<(u64, u32)> {
+        if self.index == 0 && !self.is_done() {
+            Some((self.offset, self.level))
+        } else {
+            None
+        }
+    }
+
+    /// Set the `imageSize` field of the level starting at the next
+    /// frame, returning the size of its frames
+    pub(crate) fn set_image_size(&mut self, image_size: u32) -> Result<usize> {
+        let is_cubemap = self.is_cubemap;
+        let header = &self.header;
+        self.frame_size = add_level_size(
+            &mut self.total,
+            header,
+            self.level,
+            image_size,
+            is_cubemap,
+            &self.limits,
+        )
+        .at_offset(self.offset)
+        .in_level(self.level)?;
+        self.offset += 4;
+        Ok(self.frame_size as usize)
+    }
+
+    /// Next frame, the offset of its data and its size
+    pub(crate) fn next_frame(&mut self) -> Option<(FrameInfo, u64, usize)> {
+        if self.is_done() {
+            return None;
+        }
+        let level = self.level;
+        let (pixel_width, pixel_height, pixel_depth) = mipmap_size(self.size, level);
+        let frame = FrameInfo {
+            level,
+            layer: self.index / self.nfaces,
+            face: self.index % self.nfaces,
+            pixel_width,
+            pixel_height,
+            pixel_depth,
+        };
+        let offset = self.offset;
+        self.offset += u64::from(self.frame_size);
+        self.index += 1;
+        if self.index == self.nlayers * self.nfaces {
+            self.index = 0;
+            self.level += 1;
+        }
+        Some((frame, offset, self.frame_size as usize))
+    }
+}
+
+/// Parse the 64 bytes of the header before the key/value data,
+/// returning the header without its key/value data and the size of
+/// the key/value data
+///
+/// `numberOfMipmapLevels == 0` is accepted if the levels are to be
+/// generated.
+pub fn parse_header(
+    buf: &[u8; 64],
+    limits: &Limits,
+    generate_mipmaps: bool,
+) -> Result<(Header<'static>, u32)> {
+    // Check magic
+    {
+        let magic: &[u8] = &buf[0..12];
+        if magic != MAGIC {
+            let mut m = [0_u8; 12];
+            m.copy_from_slice(magic);
+            return Err(ErrorKind::InvalidFormat(m)).at_offset(0);
+        }
+    }
+
+    let endianness = NE::read_u32(&buf[12..16]);
+    let gl_type = NE::read_u32(&buf[16..20]);
+    let gl_type_size = NE::read_u32(&buf[20..24]);
+    let gl_format = NE::read_u32(&buf[24..28]);
+    let gl_internal_format = NE::read_u32(&buf[28..32]);
+    let gl_base_internal_format = NE::read_u32(&buf[32..36]);
+    let pixel_width = NE::read_u32(&buf[36..40]);
+    let pixel_height = NE::read_u32(&buf[40..44]);
+    let pixel_depth = NE::read_u32(&buf[44..48]);
+    let number_of_array_elements = NE::read_u32(&buf[48..52]);
+    let number_of_faces = NE::read_u32(&buf[52..56]);
+    let number_of_mipmap_levels = NE::read_u32(&buf[56..60]);
+    let bytes_of_key_value_data = NE::read_u32(&buf[60..64]);
+
+    if number_of_mipmap_levels == 0 && !generate_mipmaps {
+        return Err(ErrorKind::InvalidNumberOfMipmapLevels(
+            number_of_mipmap_levels,
+        ))
+        .at_offset(56);
+    }
+
+    if endianness != ENDIANNESS {
+        return Err(ErrorKind::MismatchedEndianness(ENDIANNESS, endianness)).at_offset(12);
+    }
+
+    if number_of_faces != 0 && number_of_faces != 1 && number_of_faces != 6 {
+        return Err(ErrorKind::InvalidNumberOfFaces(number_of_faces)).at_offset(52);
+    }
+
+    let max_dimension = limits.max_dimension.into();
+    check_limit("pixelWidth", pixel_width.into(), max_dimension).at_offset(36)?;
+    check_limit("pixelHeight", pixel_height.into(), max_dimension).at_offset(40)?;
+    check_limit("pixelDepth", pixel_depth.into(), max_dimension).at_offset(44)?;
+    check_limit(
+        "numberOfArrayElements",
+        number_of_array_elements.into(),
+        limits.max_layers.into(),
+    )
+    .at_offset(48)?;
+    check_limit(
+        "numberOfMipmapLevels",
+        number_of_mipmap_levels.into(),
+        limits.max_levels.into(),
+    )
+    .at_offset(56)?;
+    check_limit(
+        "bytesOfKeyValueData",
+        bytes_of_key_value_data.into(),
+        limits.max_key_value_bytes.into(),
+    )
+    .at_offset(60)?;
+
+    let header = Header {
+        gl_type,
+        gl_type_size,
+        gl_format,
+        gl_internal_format,
+        gl_base_internal_format,
+        pixel_width,
+        pixel_height,
+        pixel_depth,
+        number_of_array_elements,
+        number_of_faces,
+        number_of_mipmap_levels,
+        key_value_data: &[],
+    };
+    Ok((header, crate::force_align(bytes_of_key_value_data)))
+}
+
+pub(crate) fn check_limit(what: &'static str, value: u64, limit: u64) -> Result<()> {
+    if value > limit {
+        bail!(ErrorKind::LimitExceeded(what, value, limit));
+    }
+    Ok(())
+}
+
+/// Check the size of all frames against `Limits::max_total_bytes`, if
+/// the size of the images of the format is known
+pub(crate) fn check_total_size(header: &Header<'_>, limits: &Limits) -> Result<()> {
+    let images = u64::from(max(1, header.number_of_array_elements))
+        * u64::from(max(1, header.number_of_faces));
+    let mut total = 0_u64;
+    for level in 0..header.number_of_mipmap_levels {
+        let (width, height, depth) = header.mipmap_size(level);
+        match format::header_image_size(header, width, height, depth) {
+            Some(size) => total = total.saturating_add(size.saturating_mul(images)),
+            // Checked against the imageSize fields while reading
+            None => return Ok(()),
+        }
+    }
+    check_limit("total frame size", total, limits.max_total_bytes)
+}
+
+/// Size of the frames of a level from its `imageSize` field
+///
+/// `imageSize` is checked against the size of the images if the format
+/// is known, then the size of the level is added to the total and
+/// checked against `Limits::max_total_bytes`.
+pub(crate) fn add_level_size(
+    total: &mut u64,
+    header: &Header<'_>,
+    level: u32,
+    image_size: u32,
+    is_cubemap: bool,
+    limits: &Limits,
+) -> Result<u32> {
+    let nlayers = max(1, header.number_of_array_elements);
+    let nfaces = max(1, header.number_of_faces);
+    let frame_size = face_size(image_size, nlayers, nfaces, is_cubemap)?;
+
+    let (width, height, depth) = header.mipmap_size(level);
+    if let Some(size) = format::header_image_size(header, width, height, depth) {
+        // imageSize is the size of a face of non-array cubemaps
+        let images = if is_cubemap {
+            1
+        } else {
+            u64::from(nlayers) * u64::from(nfaces)
+        };
+        let expected = size.saturating_mul(images);
+        if u64::from(image_size) != expected {
+            bail!(ErrorKind::MismatchedImageSize(expected, image_size));
+        }
+    }
+
+    let level_size = if is_cubemap {
+        u64::from(image_size) * 6
+    } else {
+        u64::from(image_size)
+    };
+    *total += level_size;
+    check_limit("total frame size", *total, limits.max_total_bytes)?;
+    Ok(frame_size)
+}
+
+/// Size of a frame of a level
+fn face_size(image_size: u32, nlayers: u32, nfaces: u32, is_cubemap: bool) -> Result<u32> {
+    let face_size = if is_cubemap {
+        Some(image_size)
+    } else if image_size.is_multiple_of(nlayers) && (image_size / nlayers).is_multiple_of(nfaces) {
+        Some(image_size / nlayers / nfaces)
+    } else {
+        None
+    };
+    match face_size {
+        Some(face_size) if face_size.is_multiple_of(4) => Ok(face_size),
+        _ => bail!(ErrorKind::InvalidImageSize(image_size, nlayers, nfaces)),
+    }
+}
+
+/// Key/value pairs of the key/value data
+pub struct Entries<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Entries<'a> {
+    /// Pairs of key/value data in native endianness, as found after the
+    /// header of a KTX 1.1 file
+    pub fn new(data: &'a [u8]) -> Self {
+        Entries(data)
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (&'a str, &'a [u8]);
+
+    /// Stops at the end of the data or at the first invalid pair
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value, nextbuf) = parse_entry(self.0)?;
+        self.0 = nextbuf;
+        Some((key, value))
+    }
+}
+
+/// Split the first key/value pair off `buf`, returning the key, the
+/// value and the rest of `buf`
+///
+/// Returns `None` if `buf` is empty or the pair is invalid: its size
+/// runs past the end of `buf`, the key has no NUL terminator or is not
+/// UTF-8. The padding of the last pair may be missing.
+fn parse_entry(buf: &[u8]) -> Option<(&str, &[u8], &[u8])> {
+    use core::cmp::min;
+    use core::str::from_utf8;
+
+    if buf.len() < 4 {
+        return None;
+    }
+    let (len_bytes, resting) = buf.split_at(4);
+    let len = NE::read_u32(len_bytes) as usize;
+    if len > resting.len() {
+        return None;
+    }
+    let padded_len = min((len + 3) & !3, resting.len());
+    let (kv, nextbuf) = resting.split_at(padded_len);
+    let kv = &kv[..len];
+    let nul_idx = kv.iter().position(|x| *x == 0)?;
+    let key = from_utf8(&kv[..nul_idx]).ok()?;
+    let value = &kv[nul_idx + 1..];
+    Some((key, value, nextbuf))
+}
+
+/// Check that the key/value data of a header consists of valid pairs
+pub(crate) fn check_key_value_data(mut buf: &[u8]) -> Result<()> {
+    let total = buf.len();
+    // Trailing padding may follow the last pair
+    while buf.iter().any(|x| *x != 0) {
+        match parse_entry(buf) {
+            Some((_, _, nextbuf)) => buf = nextbuf,
+            None => {
+                let offset = 64 + (total - buf.len()) as u64;
+                return Err(ErrorKind::InvalidKeyValueData).at_offset(offset);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Buffer Pooling
+//!
+//! A `Decoder` allocates a new buffer for every frame (or chunk) it
+//! yields. When many files are streamed, a `BufferPool` set in
+//! `DecoderOptions::buffer_pool` lets the buffers of frames that have
+//! been consumed (ex: uploaded to the GPU) be handed back with
+//! `BufferPool::put` and reused for later frames of the same or a
+//! smaller size, in the same file or in the next ones.
+
+use std::sync::{Arc, Mutex};
+
+/// Shared pool of frame buffers
+///
+/// Clones share the same buffers, so one pool can serve any number of
+/// decoders, on any thread. A frame that does not fit in any pooled
+/// buffer is allocated as usual.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    inner: Arc<Mutex<Pool>>,
+}
+
+#[derive(Debug)]
+struct Pool {
+    /// Buffers sorted by capacity
+    buffers: Vec<Vec<u8>>,
+    max_buffers: usize,
+    allocations: u64,
+    reuses: u64,
+}
+
+impl Default for BufferPool {
+    /// Keeps up to 32 buffers
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+impl BufferPool {
+    /// Pool keeping up to `max_buffers` buffers; beyond that the
+    /// smallest ones are dropped
+    pub fn new(max_buffers: usize) -> Self {
+        BufferPool {
+            inner: Arc::new(Mutex::new(Pool {
+                buffers: vec![],
+                max_buffers,
+                allocations: 0,
+                reuses: 0,
+            })),
+        }
+    }
+
+    /// Hand a buffer back to the pool
+    ///
+    /// Any `Vec<u8>` can be put, not only the ones of a `Decoder`.
+    pub fn put(&self, buf: Vec<u8>) {
+        let mut pool = self.lock();
+        if buf.capacity() == 0 || pool.max_buffers == 0 {
+            return;
+        }
+        let index = pool
+            .buffers
+            .partition_point(|x| x.capacity() < buf.capacity());
+        pool.buffers.insert(index, buf);
+        if pool.buffers.len() > pool.max_buffers {
+            pool.buffers.remove(0);
+        }
+    }
+
+    /// Zeroed buffer of `size` bytes, reusing the smallest pooled
+    /// buffer that is large enough
+    pub fn take(&self, size: usize) -> Vec<u8> {
+        let mut pool = self.lock();
+        let index = pool.buffers.partition_point(|x| x.capacity() < size);
+        if index == pool.buffers.len() {
+            pool.allocations += 1;
+            drop(pool);
+            return vec![0_u8; size];
+        }
+        pool.reuses += 1;
+        let mut buf = pool.buffers.remove(index);
+        drop(pool);
+        buf.clear();
+        buf.resize(size, 0);
+        buf
+    }
+
+    /// Number of buffers in the pool
+    pub fn len(&self) -> usize {
+        self.lock().buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of buffers `take` had to allocate, and number it reused
+    pub fn stats(&self) -> (u64, u64) {
+        let pool = self.lock();
+        (pool.allocations, pool.reuses)
+    }
+
+    /// Drop all the pooled buffers
+    pub fn clear(&self) {
+        self.lock().buffers.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Pool> {
+        // The pool is consistent between statements
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Zeroed buffer of `size` bytes, from the pool if there is one
+pub(crate) fn take(pool: Option<&BufferPool>, size: usize) -> Vec<u8> {
+    match pool {
+        Some(pool) => pool.take(size),
+        None => vec![0_u8; size],
+    }
+}
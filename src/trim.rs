@@ -0,0 +1,116 @@
+//! Mipmap Level Trimming
+//!
+//! Removes the largest or the smallest levels of a texture. The frames
+//! of the remaining levels are copied as they are, so any format can be
+//! trimmed without re-encoding; the key/value data is kept.
+
+use crate::error::bail;
+use crate::io::{AsyncRead, AsyncWrite};
+use crate::{Decoder, DecoderOptions, Encoder, Frame, HeaderInfo, Result};
+use std::cmp::max;
+
+/// Levels to remove
+///
+/// The largest levels are dropped first (by `drop_levels`, then by
+/// `max_size`), then the smallest levels by `min_size`. The largest of
+/// the remaining levels is always kept.
+#[derive(Debug, Clone, Default)]
+pub struct TrimOptions {
+    /// Number of largest levels to drop
+    pub drop_levels: u32,
+    /// Drop the largest levels until no dimension exceeds this size
+    pub max_size: Option<u32>,
+    /// Drop the smallest levels whose largest dimension is below this
+    /// size
+    pub min_size: Option<u32>,
+}
+
+/// Remove levels from a texture, given its header and all its frames
+/// in the order of the `Decoder` stream.
+///
+/// The size of the new level 0 becomes the size of the texture; the
+/// frames are renumbered from level 0.
+pub fn trim_levels(
+    info: &HeaderInfo,
+    frames: Vec<Frame>,
+    options: &TrimOptions,
+) -> Result<(HeaderInfo, Vec<Frame>)> {
+    let nlevels = max(1, info.number_of_mipmap_levels);
+    let largest = |level: u32| {
+        let (w, h, d) = info.mipmap_size(level);
+        max(w, max(h, d))
+    };
+
+    let mut first = options.drop_levels;
+    if let Some(max_size) = options.max_size {
+        while first < nlevels && largest(first) > max_size {
+            first += 1;
+        }
+    }
+    if first >= nlevels {
+        bail!(
+            "cannot drop {} of the {} levels of a {}x{} texture",
+            first,
+            nlevels,
+            info.pixel_width,
+            info.pixel_height
+        );
+    }
+    let mut end = nlevels;
+    if let Some(min_size) = options.min_size {
+        while end > first + 1 && largest(end - 1) < min_size {
+            end -= 1;
+        }
+    }
+
+    let (width, height, depth) = info.mipmap_size(first);
+    let info = HeaderInfo {
+        pixel_width: width,
+        // 0 stays 0 for 1D and 2D textures
+        pixel_height: if info.pixel_height == 0 { 0 } else { height },
+        pixel_depth: if info.pixel_depth == 0 { 0 } else { depth },
+        number_of_mipmap_levels: end - first,
+        ..info.clone()
+    };
+    let frames = frames
+        .into_iter()
+        .filter(|(frame, _)| (first..end).contains(&frame.level))
+        .map(|(mut frame, buf)| {
+            frame.level -= first;
+            (frame, buf)
+        })
+        .collect();
+    Ok((info, frames))
+}
+
+/// Read a KTX 1.1 file with the `Decoder` and `decoder_options`, remove
+/// levels with `trim_levels` and write the result as a KTX 1.1 file.
+///
+/// The frames are copied without being interpreted, so the limits can
+/// be `Limits::relaxed()`. Files with `numberOfMipmapLevels == 0` need
+/// `DecoderOptions::generate_mipmaps`: the generated levels are kept.
+///
+/// Returns the writer.
+pub async fn trim_async<R, W>(
+    read: R,
+    write: W,
+    options: &TrimOptions,
+    decoder_options: DecoderOptions,
+) -> Result<W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    use futures_core::stream::Stream as _;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let decoder = Decoder::with_options(read, decoder_options);
+    let (info, mut stream) = decoder.read_async().await?;
+    let mut frames = vec![];
+    while let Some(frame) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+        frames.push(frame?);
+    }
+    let (info, frames) = trim_levels(&info, frames, options)?;
+    Encoder::new(write).write_async(&info, &frames).await
+}
@@ -0,0 +1,454 @@
+//! Conformance Checks for KTX Files
+//!
+//! `validate` walks a whole file in memory and reports every problem
+//! it finds instead of stopping at the first one, so that it can be
+//! used as a gate for asset pipelines.
+
+use crate::{format, ktx2, HeaderInfo, KeyValueData, ENDIANNESS, MAGIC};
+use std::fmt;
+
+/// Keys starting with `KTX` or `ktx` that the KTX 1.1 specification
+/// defines
+const KTX1_KNOWN_KEYS: [&str; 1] = ["KTXorientation"];
+
+/// Keys starting with `KTX` or `ktx` that the KTX 2.0 specification
+/// defines
+const KTX2_KNOWN_KEYS: [&str; 10] = [
+    "KTXorientation",
+    "KTXglFormat",
+    "KTXdxgiFormat__",
+    "KTXmetalPixelFormat",
+    "KTXswizzle",
+    "KTXwriter",
+    "KTXwriterScParams",
+    "KTXastcDecodeMode",
+    "KTXanimData",
+    "KTXcubemapIncomplete",
+];
+
+/// How bad an `Issue` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Allowed by the specification but likely a mistake
+    Warning,
+    /// Violates the specification
+    Error,
+}
+
+/// A problem found by `validate`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Offset from the start of the file of the offending bytes
+    pub offset: u64,
+    pub message: String,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at byte {}: {}",
+            self.severity, self.offset, self.message
+        )
+    }
+}
+
+/// Check a KTX 1.1 or KTX 2.0 file
+///
+/// Returns an empty list for conformant files. Checks carry on past
+/// errors wherever the rest of the file can still be located.
+pub fn validate(data: &[u8]) -> Vec<Issue> {
+    let mut issues = Issues(Vec::new());
+    if data.starts_with(&ktx2::MAGIC) {
+        validate_ktx2(data, &mut issues);
+    } else {
+        validate_ktx1(data, &mut issues);
+    }
+    issues.0
+}
+
+struct Issues(Vec<Issue>);
+
+impl Issues {
+    fn error(&mut self, offset: u64, message: String) {
+        self.push(Severity::Error, offset, message);
+    }
+
+    fn warning(&mut self, offset: u64, message: String) {
+        self.push(Severity::Warning, offset, message);
+    }
+
+    fn push(&mut self, severity: Severity, offset: u64, message: String) {
+        self.0.push(Issue {
+            severity,
+            offset,
+            message,
+        });
+    }
+}
+
+/// Reads integers of the byte order declared in the file
+#[derive(Clone, Copy)]
+struct Reader<'a> {
+    data: &'a [u8],
+    swap: bool,
+}
+
+impl Reader<'_> {
+    fn u32(&self, offset: u64) -> u32 {
+        use byteorder::{ByteOrder as _, NativeEndian as NE};
+        let offset = offset as usize;
+        let x = NE::read_u32(&self.data[offset..offset + 4]);
+        if self.swap {
+            x.swap_bytes()
+        } else {
+            x
+        }
+    }
+}
+
+fn validate_ktx1(data: &[u8], issues: &mut Issues) {
+    if data.len() >= 12 && !data.starts_with(&MAGIC) {
+        issues.error(0, "invalid identifier".into());
+        return;
+    }
+    if data.len() < 64 {
+        issues.error(0, format!("file is truncated to {} bytes", data.len()));
+        return;
+    }
+
+    let endianness = Reader { data, swap: false }.u32(12);
+    let swap = match endianness {
+        ENDIANNESS => false,
+        x if x == ENDIANNESS.swap_bytes() => true,
+        x => {
+            issues.error(12, format!("invalid endianness 0x{:08X}", x));
+            return;
+        }
+    };
+    let r = Reader { data, swap };
+
+    let info = HeaderInfo {
+        gl_type: r.u32(16),
+        gl_type_size: r.u32(20),
+        gl_format: r.u32(24),
+        gl_internal_format: r.u32(28),
+        gl_base_internal_format: r.u32(32),
+        pixel_width: r.u32(36),
+        pixel_height: r.u32(40),
+        pixel_depth: r.u32(44),
+        number_of_array_elements: r.u32(48),
+        number_of_faces: r.u32(52),
+        number_of_mipmap_levels: r.u32(56),
+        key_value_data: KeyValueData::default(),
+    };
+
+    if info.gl_type == 0 {
+        if info.gl_format != 0 {
+            issues.error(24, "glFormat must be 0 for compressed formats".into());
+        }
+        if info.gl_type_size != 1 {
+            issues.error(20, "glTypeSize must be 1 for compressed formats".into());
+        }
+    } else {
+        if info.gl_format == 0 {
+            issues.error(24, "glFormat must not be 0 for uncompressed formats".into());
+        }
+        if ![1, 2, 4].contains(&info.gl_type_size) {
+            issues.error(
+                20,
+                format!("glTypeSize {} is not 1, 2 or 4", info.gl_type_size),
+            );
+        }
+        if info.gl_base_internal_format != info.gl_format {
+            issues.warning(
+                32,
+                "glBaseInternalFormat differs from glFormat of an uncompressed format".into(),
+            );
+        }
+    }
+
+    validate_dimensions(
+        &Dimensions {
+            width: info.pixel_width,
+            height: info.pixel_height,
+            depth: info.pixel_depth,
+            layers: info.number_of_array_elements,
+            faces: info.number_of_faces,
+            levels: info.number_of_mipmap_levels,
+            offsets: [36, 40, 44, 52, 56],
+        },
+        issues,
+    );
+    if info.number_of_mipmap_levels == 0 && info.gl_type == 0 {
+        issues.warning(
+            56,
+            "mipmap generation is usually not possible for compressed formats".into(),
+        );
+    }
+
+    // Key/value data
+    let bytes_of_key_value_data = u64::from(r.u32(60));
+    if bytes_of_key_value_data % 4 != 0 {
+        issues.error(
+            60,
+            format!(
+                "bytesOfKeyValueData {} is not a multiple of 4",
+                bytes_of_key_value_data
+            ),
+        );
+    }
+    let kvd_end = 64 + bytes_of_key_value_data;
+    if kvd_end > data.len() as u64 {
+        issues.error(60, "key/value data runs past the end of the file".into());
+        return;
+    }
+    validate_key_values(r, 64, kvd_end, &KTX1_KNOWN_KEYS, issues);
+
+    // Mipmap levels
+    let is_cubemap = info.header().is_cubemap();
+    let nimages =
+        u64::from(info.number_of_array_elements.max(1)) * u64::from(info.number_of_faces.max(1));
+    let mut pos = (kvd_end + 3) & !3;
+    // Levels beyond 32 were reported above
+    for level in 0..info.number_of_mipmap_levels.clamp(1, 32) {
+        if pos + 4 > data.len() as u64 {
+            issues.error(pos, format!("missing level {}", level));
+            return;
+        }
+        let image_size = u64::from(r.u32(pos));
+        let (width, height, depth) = info.mipmap_size(level);
+        let expected = format::image_size(&info, width, height, depth).map(|size| {
+            if is_cubemap {
+                size
+            } else {
+                size * nimages
+            }
+        });
+        match expected {
+            Some(expected) if image_size != expected => issues.error(
+                pos,
+                format!(
+                    "imageSize of level {} is {}, expected {}",
+                    level, image_size, expected
+                ),
+            ),
+            None if !is_cubemap && image_size % nimages != 0 => issues.error(
+                pos,
+                format!(
+                    "imageSize of level {} is not divisible into {} images",
+                    level, nimages
+                ),
+            ),
+            _ => {}
+        }
+        pos += 4;
+
+        // Faces of non-array cubemaps are padded one by one
+        let chunks = if is_cubemap { 6 } else { 1 };
+        for _ in 0..chunks {
+            let end = pos + image_size;
+            let padded_end = (end + 3) & !3;
+            if padded_end > data.len() as u64 {
+                issues.error(pos, format!("level {} is truncated", level));
+                return;
+            }
+            check_padding(data, end, padded_end, issues);
+            pos = padded_end;
+        }
+    }
+
+    if pos < data.len() as u64 {
+        issues.warning(
+            pos,
+            format!(
+                "{} unexpected bytes after the last level",
+                data.len() as u64 - pos
+            ),
+        );
+    }
+}
+
+fn validate_ktx2(data: &[u8], issues: &mut Issues) {
+    use byteorder::{ByteOrder as _, LittleEndian as LE};
+
+    if data.len() < 80 {
+        issues.error(0, format!("file is truncated to {} bytes", data.len()));
+        return;
+    }
+    let u32_at = |offset: usize| LE::read_u32(&data[offset..offset + 4]);
+
+    let width = u32_at(20);
+    let height = u32_at(24);
+    let depth = u32_at(28);
+    let layers = u32_at(32);
+    let faces = u32_at(36);
+    let levels = u32_at(40);
+    validate_dimensions(
+        &Dimensions {
+            width,
+            height,
+            depth,
+            layers,
+            faces,
+            levels,
+            offsets: [20, 24, 28, 36, 40],
+        },
+        issues,
+    );
+    if levels > 32 {
+        return;
+    }
+
+    // Level index
+    let index_end = 80 + 24 * u64::from(levels.max(1));
+    if index_end > data.len() as u64 {
+        issues.error(80, "level index runs past the end of the file".into());
+        return;
+    }
+    for level in 0..u64::from(levels.max(1)) {
+        let offset = 80 + 24 * level;
+        let byte_offset = LE::read_u64(&data[offset as usize..]);
+        let byte_length = LE::read_u64(&data[offset as usize + 8..]);
+        if byte_offset.saturating_add(byte_length) > data.len() as u64 {
+            issues.error(
+                offset,
+                format!("level {} runs past the end of the file", level),
+            );
+        }
+    }
+
+    // Key/value data
+    let kvd_offset = u64::from(u32_at(56));
+    let kvd_end = kvd_offset + u64::from(u32_at(60));
+    if kvd_end > kvd_offset {
+        if kvd_offset < index_end || kvd_end > data.len() as u64 {
+            issues.error(56, "key/value data is out of bounds".into());
+        } else {
+            let r = Reader {
+                data,
+                swap: cfg!(target_endian = "big"),
+            };
+            validate_key_values(r, kvd_offset, kvd_end, &KTX2_KNOWN_KEYS, issues);
+        }
+    }
+}
+
+struct Dimensions {
+    width: u32,
+    height: u32,
+    depth: u32,
+    layers: u32,
+    faces: u32,
+    levels: u32,
+    /// Offsets of the width, height, depth, face count and level count
+    offsets: [u64; 5],
+}
+
+fn validate_dimensions(d: &Dimensions, issues: &mut Issues) {
+    let [width_offset, height_offset, depth_offset, faces_offset, levels_offset] = d.offsets;
+
+    if d.width == 0 {
+        issues.error(width_offset, "pixelWidth must not be 0".into());
+    }
+    if d.height == 0 && d.depth != 0 {
+        issues.error(depth_offset, "pixelDepth must be 0 for 1D textures".into());
+    }
+    if d.depth != 0 && d.layers != 0 {
+        issues.warning(
+            depth_offset,
+            "arrays of 3D textures are not supported by OpenGL".into(),
+        );
+    }
+    match d.faces {
+        1 => {}
+        6 => {
+            if d.depth != 0 {
+                issues.error(depth_offset, "cubemaps must have a pixelDepth of 0".into());
+            }
+            if d.width != d.height {
+                issues.error(
+                    height_offset,
+                    format!("cubemap faces are not square ({}x{})", d.width, d.height),
+                );
+            }
+        }
+        n => issues.error(
+            faces_offset,
+            format!("number of faces is {}, not 1 or 6", n),
+        ),
+    }
+
+    let max_size = d.width.max(d.height).max(d.depth).max(1);
+    let max_levels = 32 - max_size.leading_zeros();
+    if d.levels > max_levels {
+        issues.error(
+            levels_offset,
+            format!(
+                "{} mipmap levels exceed the maximum of {} for the size",
+                d.levels, max_levels
+            ),
+        );
+    }
+}
+
+/// Check the key/value pairs between `start` and `end`, with the
+/// reserved keys `known_keys` of the container
+fn validate_key_values(r: Reader, start: u64, end: u64, known_keys: &[&str], issues: &mut Issues) {
+    let mut pos = start;
+    let mut keys = Vec::new();
+    while pos < end {
+        if pos + 4 > end {
+            issues.error(pos, "truncated keyAndValueByteSize".into());
+            return;
+        }
+        let len = u64::from(r.u32(pos));
+        let kv_start = pos + 4;
+        let kv_end = kv_start + len;
+        let padded_end = (kv_end + 3) & !3;
+        if padded_end > end {
+            issues.error(pos, "key/value pair runs past the key/value data".into());
+            return;
+        }
+        let kv = &r.data[kv_start as usize..kv_end as usize];
+
+        match kv.iter().position(|&b| b == 0) {
+            None => issues.error(kv_start, "key is not NUL-terminated".into()),
+            Some(n) => match std::str::from_utf8(&kv[..n]) {
+                Err(_) => issues.error(kv_start, "key is not valid UTF-8".into()),
+                Ok(key) => {
+                    if key.starts_with('\u{FEFF}') {
+                        issues.error(kv_start, "key starts with a byte order mark".into());
+                    }
+                    let is_reserved = key.starts_with("KTX") || key.starts_with("ktx");
+                    if is_reserved && !known_keys.contains(&key) {
+                        issues.error(kv_start, format!("reserved key {:?}", key));
+                    }
+                    if keys.contains(&key) {
+                        issues.warning(kv_start, format!("duplicate key {:?}", key));
+                    }
+                    keys.push(key);
+                }
+            },
+        }
+
+        check_padding(r.data, kv_end, padded_end, issues);
+        pos = padded_end;
+    }
+}
+
+fn check_padding(data: &[u8], start: u64, end: u64, issues: &mut Issues) {
+    if data[start as usize..end as usize].iter().any(|&b| b != 0) {
+        issues.warning(start, "padding bytes are not zero".into());
+    }
+}
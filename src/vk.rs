@@ -0,0 +1,491 @@
+//! Vulkan format enumerations (`VkFormat`) found in KTX 2.0 headers,
+//! and buffer-to-image copy regions
+//!
+//! `name` returns the symbolic name of a value, e.g. `VK_FORMAT_R8G8B8A8_UNORM`.
+//!
+//! `buffer_image_copies` computes the `VkBufferImageCopy` regions that
+//! upload a texture from a staging buffer, as plain structs mirroring
+//! the Vulkan ones (ex: to build `ash::vk::BufferImageCopy`).
+//! `pack_rows` removes the row padding Vulkan cannot describe.
+
+#![allow(non_upper_case_globals)]
+
+use crate::error::{bail, ResultExt as _};
+use crate::{convert, format, gl, ErrorKind, HeaderInfo, LevelInfo, Result};
+use alloc::vec::Vec;
+use core::ops::Range;
+
+enumerations! {
+    "VK_FORMAT_";
+    // Core formats
+    UNDEFINED = 0,
+    R4G4_UNORM_PACK8 = 1,
+    R4G4B4A4_UNORM_PACK16 = 2,
+    B4G4R4A4_UNORM_PACK16 = 3,
+    R5G6B5_UNORM_PACK16 = 4,
+    B5G6R5_UNORM_PACK16 = 5,
+    R5G5B5A1_UNORM_PACK16 = 6,
+    B5G5R5A1_UNORM_PACK16 = 7,
+    A1R5G5B5_UNORM_PACK16 = 8,
+    R8_UNORM = 9,
+    R8_SNORM = 10,
+    R8_USCALED = 11,
+    R8_SSCALED = 12,
+    R8_UINT = 13,
+    R8_SINT = 14,
+    R8_SRGB = 15,
+    R8G8_UNORM = 16,
+    R8G8_SNORM = 17,
+    R8G8_USCALED = 18,
+    R8G8_SSCALED = 19,
+    R8G8_UINT = 20,
+    R8G8_SINT = 21,
+    R8G8_SRGB = 22,
+    R8G8B8_UNORM = 23,
+    R8G8B8_SNORM = 24,
+    R8G8B8_USCALED = 25,
+    R8G8B8_SSCALED = 26,
+    R8G8B8_UINT = 27,
+    R8G8B8_SINT = 28,
+    R8G8B8_SRGB = 29,
+    B8G8R8_UNORM = 30,
+    B8G8R8_SNORM = 31,
+    B8G8R8_USCALED = 32,
+    B8G8R8_SSCALED = 33,
+    B8G8R8_UINT = 34,
+    B8G8R8_SINT = 35,
+    B8G8R8_SRGB = 36,
+    R8G8B8A8_UNORM = 37,
+    R8G8B8A8_SNORM = 38,
+    R8G8B8A8_USCALED = 39,
+    R8G8B8A8_SSCALED = 40,
+    R8G8B8A8_UINT = 41,
+    R8G8B8A8_SINT = 42,
+    R8G8B8A8_SRGB = 43,
+    B8G8R8A8_UNORM = 44,
+    B8G8R8A8_SNORM = 45,
+    B8G8R8A8_USCALED = 46,
+    B8G8R8A8_SSCALED = 47,
+    B8G8R8A8_UINT = 48,
+    B8G8R8A8_SINT = 49,
+    B8G8R8A8_SRGB = 50,
+    A8B8G8R8_UNORM_PACK32 = 51,
+    A8B8G8R8_SNORM_PACK32 = 52,
+    A8B8G8R8_USCALED_PACK32 = 53,
+    A8B8G8R8_SSCALED_PACK32 = 54,
+    A8B8G8R8_UINT_PACK32 = 55,
+    A8B8G8R8_SINT_PACK32 = 56,
+    A8B8G8R8_SRGB_PACK32 = 57,
+    A2R10G10B10_UNORM_PACK32 = 58,
+    A2R10G10B10_SNORM_PACK32 = 59,
+    A2R10G10B10_USCALED_PACK32 = 60,
+    A2R10G10B10_SSCALED_PACK32 = 61,
+    A2R10G10B10_UINT_PACK32 = 62,
+    A2R10G10B10_SINT_PACK32 = 63,
+    A2B10G10R10_UNORM_PACK32 = 64,
+    A2B10G10R10_SNORM_PACK32 = 65,
+    A2B10G10R10_USCALED_PACK32 = 66,
+    A2B10G10R10_SSCALED_PACK32 = 67,
+    A2B10G10R10_UINT_PACK32 = 68,
+    A2B10G10R10_SINT_PACK32 = 69,
+    R16_UNORM = 70,
+    R16_SNORM = 71,
+    R16_USCALED = 72,
+    R16_SSCALED = 73,
+    R16_UINT = 74,
+    R16_SINT = 75,
+    R16_SFLOAT = 76,
+    R16G16_UNORM = 77,
+    R16G16_SNORM = 78,
+    R16G16_USCALED = 79,
+    R16G16_SSCALED = 80,
+    R16G16_UINT = 81,
+    R16G16_SINT = 82,
+    R16G16_SFLOAT = 83,
+    R16G16B16_UNORM = 84,
+    R16G16B16_SNORM = 85,
+    R16G16B16_USCALED = 86,
+    R16G16B16_SSCALED = 87,
+    R16G16B16_UINT = 88,
+    R16G16B16_SINT = 89,
+    R16G16B16_SFLOAT = 90,
+    R16G16B16A16_UNORM = 91,
+    R16G16B16A16_SNORM = 92,
+    R16G16B16A16_USCALED = 93,
+    R16G16B16A16_SSCALED = 94,
+    R16G16B16A16_UINT = 95,
+    R16G16B16A16_SINT = 96,
+    R16G16B16A16_SFLOAT = 97,
+    R32_UINT = 98,
+    R32_SINT = 99,
+    R32_SFLOAT = 100,
+    R32G32_UINT = 101,
+    R32G32_SINT = 102,
+    R32G32_SFLOAT = 103,
+    R32G32B32_UINT = 104,
+    R32G32B32_SINT = 105,
+    R32G32B32_SFLOAT = 106,
+    R32G32B32A32_UINT = 107,
+    R32G32B32A32_SINT = 108,
+    R32G32B32A32_SFLOAT = 109,
+    R64_UINT = 110,
+    R64_SINT = 111,
+    R64_SFLOAT = 112,
+    R64G64_UINT = 113,
+    R64G64_SINT = 114,
+    R64G64_SFLOAT = 115,
+    R64G64B64_UINT = 116,
+    R64G64B64_SINT = 117,
+    R64G64B64_SFLOAT = 118,
+    R64G64B64A64_UINT = 119,
+    R64G64B64A64_SINT = 120,
+    R64G64B64A64_SFLOAT = 121,
+    B10G11R11_UFLOAT_PACK32 = 122,
+    E5B9G9R9_UFLOAT_PACK32 = 123,
+    D16_UNORM = 124,
+    X8_D24_UNORM_PACK32 = 125,
+    D32_SFLOAT = 126,
+    S8_UINT = 127,
+    D16_UNORM_S8_UINT = 128,
+    D24_UNORM_S8_UINT = 129,
+    D32_SFLOAT_S8_UINT = 130,
+    BC1_RGB_UNORM_BLOCK = 131,
+    BC1_RGB_SRGB_BLOCK = 132,
+    BC1_RGBA_UNORM_BLOCK = 133,
+    BC1_RGBA_SRGB_BLOCK = 134,
+    BC2_UNORM_BLOCK = 135,
+    BC2_SRGB_BLOCK = 136,
+    BC3_UNORM_BLOCK = 137,
+    BC3_SRGB_BLOCK = 138,
+    BC4_UNORM_BLOCK = 139,
+    BC4_SNORM_BLOCK = 140,
+    BC5_UNORM_BLOCK = 141,
+    BC5_SNORM_BLOCK = 142,
+    BC6H_UFLOAT_BLOCK = 143,
+    BC6H_SFLOAT_BLOCK = 144,
+    BC7_UNORM_BLOCK = 145,
+    BC7_SRGB_BLOCK = 146,
+    ETC2_R8G8B8_UNORM_BLOCK = 147,
+    ETC2_R8G8B8_SRGB_BLOCK = 148,
+    ETC2_R8G8B8A1_UNORM_BLOCK = 149,
+    ETC2_R8G8B8A1_SRGB_BLOCK = 150,
+    ETC2_R8G8B8A8_UNORM_BLOCK = 151,
+    ETC2_R8G8B8A8_SRGB_BLOCK = 152,
+    EAC_R11_UNORM_BLOCK = 153,
+    EAC_R11_SNORM_BLOCK = 154,
+    EAC_R11G11_UNORM_BLOCK = 155,
+    EAC_R11G11_SNORM_BLOCK = 156,
+    ASTC_4x4_UNORM_BLOCK = 157,
+    ASTC_4x4_SRGB_BLOCK = 158,
+    ASTC_5x4_UNORM_BLOCK = 159,
+    ASTC_5x4_SRGB_BLOCK = 160,
+    ASTC_5x5_UNORM_BLOCK = 161,
+    ASTC_5x5_SRGB_BLOCK = 162,
+    ASTC_6x5_UNORM_BLOCK = 163,
+    ASTC_6x5_SRGB_BLOCK = 164,
+    ASTC_6x6_UNORM_BLOCK = 165,
+    ASTC_6x6_SRGB_BLOCK = 166,
+    ASTC_8x5_UNORM_BLOCK = 167,
+    ASTC_8x5_SRGB_BLOCK = 168,
+    ASTC_8x6_UNORM_BLOCK = 169,
+    ASTC_8x6_SRGB_BLOCK = 170,
+    ASTC_8x8_UNORM_BLOCK = 171,
+    ASTC_8x8_SRGB_BLOCK = 172,
+    ASTC_10x5_UNORM_BLOCK = 173,
+    ASTC_10x5_SRGB_BLOCK = 174,
+    ASTC_10x6_UNORM_BLOCK = 175,
+    ASTC_10x6_SRGB_BLOCK = 176,
+    ASTC_10x8_UNORM_BLOCK = 177,
+    ASTC_10x8_SRGB_BLOCK = 178,
+    ASTC_10x10_UNORM_BLOCK = 179,
+    ASTC_10x10_SRGB_BLOCK = 180,
+    ASTC_12x10_UNORM_BLOCK = 181,
+    ASTC_12x10_SRGB_BLOCK = 182,
+    ASTC_12x12_UNORM_BLOCK = 183,
+    ASTC_12x12_SRGB_BLOCK = 184,
+
+    // VK_IMG_format_pvrtc
+    PVRTC1_2BPP_UNORM_BLOCK_IMG = 1000054000,
+    PVRTC1_4BPP_UNORM_BLOCK_IMG = 1000054001,
+    PVRTC2_2BPP_UNORM_BLOCK_IMG = 1000054002,
+    PVRTC2_4BPP_UNORM_BLOCK_IMG = 1000054003,
+    PVRTC1_2BPP_SRGB_BLOCK_IMG = 1000054004,
+    PVRTC1_4BPP_SRGB_BLOCK_IMG = 1000054005,
+    PVRTC2_2BPP_SRGB_BLOCK_IMG = 1000054006,
+    PVRTC2_4BPP_SRGB_BLOCK_IMG = 1000054007,
+}
+
+/// `VK_IMAGE_ASPECT_COLOR_BIT`
+pub const IMAGE_ASPECT_COLOR_BIT: u32 = 0x1;
+/// `VK_IMAGE_ASPECT_DEPTH_BIT`
+pub const IMAGE_ASPECT_DEPTH_BIT: u32 = 0x2;
+/// `VK_IMAGE_ASPECT_STENCIL_BIT`
+pub const IMAGE_ASPECT_STENCIL_BIT: u32 = 0x4;
+
+/// `VkBufferImageCopy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferImageCopy {
+    pub buffer_offset: u64,
+    /// Length of a row in texels, 0 if rows are tightly packed
+    pub buffer_row_length: u32,
+    /// Height of an image in texels, 0 if images are tightly packed
+    pub buffer_image_height: u32,
+    pub image_subresource: ImageSubresourceLayers,
+    pub image_offset: Offset3D,
+    pub image_extent: Extent3D,
+}
+
+/// `VkImageSubresourceLayers`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageSubresourceLayers {
+    pub aspect_mask: u32,
+    pub mip_level: u32,
+    pub base_array_layer: u32,
+    pub layer_count: u32,
+}
+
+/// `VkOffset3D`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Offset3D {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// `VkExtent3D`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Extent3D {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+}
+
+/// Regions copying the levels of a packed layout, such as
+/// `TextureData::levels`, from a staging buffer holding it at
+/// `base_offset` to an image of the format of `info`
+///
+/// Each level is copied by a single region covering all its layers,
+/// cubemap faces being layers `6 * layer + face` of the image, unless
+/// its frames are larger than the tightly packed images Vulkan
+/// expects (small PVRTC1 levels): those get a region per frame.
+/// Rows of uncompressed formats padded to 4 bytes are described with
+/// `buffer_row_length`, tightly packed rows (see `pack_rows`) as is.
+///
+/// Fails if the format is unknown, if an offset is not a multiple of
+/// the texel block size (and of 4 for depth and stencil formats), if
+/// padded rows are not a whole number of texels (ex: RGB8 of width 2;
+/// such levels have to be packed with `pack_rows`), or if a level is
+/// smaller than its images.
+pub fn buffer_image_copies(
+    info: &HeaderInfo,
+    levels: &[(LevelInfo, Range<usize>)],
+    base_offset: u64,
+) -> Result<Vec<BufferImageCopy>> {
+    let aspect_mask = match info.gl_format {
+        gl::DEPTH_COMPONENT => IMAGE_ASPECT_DEPTH_BIT,
+        gl::STENCIL_INDEX => IMAGE_ASPECT_STENCIL_BIT,
+        gl::DEPTH_STENCIL => bail!("depth/stencil data cannot be copied as a single aspect"),
+        _ => IMAGE_ASPECT_COLOR_BIT,
+    };
+    let mut regions = Vec::with_capacity(levels.len());
+    for (level, range) in levels {
+        let result = level_copies(info, level, range, base_offset, aspect_mask, &mut regions);
+        result.in_level(level.level)?;
+    }
+    Ok(regions)
+}
+
+fn level_copies(
+    info: &HeaderInfo,
+    level: &LevelInfo,
+    range: &Range<usize>,
+    base_offset: u64,
+    aspect_mask: u32,
+    regions: &mut Vec<BufferImageCopy>,
+) -> Result<()> {
+    let (width, height, depth) = (level.pixel_width, level.pixel_height, level.pixel_depth);
+    if range.len() != level.size() {
+        bail!(ErrorKind::InvalidBufferSize(level.size(), range.len()));
+    }
+
+    // Texel block size, row length and size of a tightly packed image
+    let (block_bytes, row_length, image_size) = if info.gl_type != 0 {
+        let pixel_size = match convert::pixel_size(info.gl_format, info.gl_type) {
+            Some(x) => x as u64,
+            None => bail!(ErrorKind::UnsupportedPixelFormat(
+                info.gl_format,
+                info.gl_type
+            )),
+        };
+        let row_size = u64::from(width) * pixel_size;
+        let padded_row_size = (row_size + 3) & !3;
+        let rows = u64::from(height).saturating_mul(u64::from(depth));
+        let packed_size = row_size.saturating_mul(rows);
+        if padded_row_size == row_size || u64::from(level.frame_size) == packed_size {
+            (pixel_size, 0, packed_size)
+        } else if padded_row_size.is_multiple_of(pixel_size) {
+            let row_length = (padded_row_size / pixel_size) as u32;
+            (pixel_size, row_length, padded_row_size.saturating_mul(rows))
+        } else {
+            bail!(
+                "rows padded to {} bytes are not a whole number of {}-byte texels",
+                padded_row_size,
+                pixel_size
+            );
+        }
+    } else {
+        let block = match format::compressed_block_size(info.gl_internal_format) {
+            Some(x) => x,
+            None => bail!(ErrorKind::UnsupportedFormat(info.gl_internal_format)),
+        };
+        let blocks = u64::from(width.div_ceil(block.width))
+            .saturating_mul(u64::from(height.div_ceil(block.height)))
+            .saturating_mul(u64::from(depth));
+        let image_size = blocks.saturating_mul(u64::from(block.bytes));
+        (u64::from(block.bytes), 0, image_size)
+    };
+    let alignment = if aspect_mask == IMAGE_ASPECT_COLOR_BIT {
+        block_bytes
+    } else {
+        lcm(block_bytes, 4)
+    };
+
+    let frame_size = u64::from(level.frame_size);
+    if frame_size < image_size {
+        bail!(ErrorKind::InvalidBufferSize(
+            image_size as usize,
+            frame_size as usize
+        ));
+    }
+    let mut push = |offset: usize, base_array_layer: u32, layer_count: u32| {
+        let buffer_offset = base_offset + offset as u64;
+        if !buffer_offset.is_multiple_of(alignment) {
+            bail!(
+                "buffer offset {} is not a multiple of {} bytes",
+                buffer_offset,
+                alignment
+            );
+        }
+        regions.push(BufferImageCopy {
+            buffer_offset,
+            buffer_row_length: row_length,
+            buffer_image_height: 0,
+            image_subresource: ImageSubresourceLayers {
+                aspect_mask,
+                mip_level: level.level,
+                base_array_layer,
+                layer_count,
+            },
+            image_offset: Offset3D::default(),
+            image_extent: Extent3D {
+                width,
+                height,
+                depth,
+            },
+        });
+        Ok(())
+    };
+
+    let layer_count = level.number_of_layers * level.number_of_faces;
+    if frame_size == image_size {
+        push(range.start, 0, layer_count)
+    } else {
+        // The padding after each image has to be skipped
+        for (i, (_, frame_range)) in level.frames().enumerate() {
+            push(range.start + frame_range.start, i as u32, 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy the levels of a packed layout, such as `TextureData::levels`
+/// of `data`, to a new buffer where rows of uncompressed formats are
+/// not padded to 4 bytes
+///
+/// Levels start at multiples of 4 and of the texel block size, so
+/// `buffer_image_copies` can describe every level of the result (ex:
+/// the 2x1 and 1x1 levels of an RGB8 texture) if the staging buffer
+/// holds it at such an offset. Compressed levels are copied as is.
+pub fn pack_rows(
+    info: &HeaderInfo,
+    levels: &[(LevelInfo, Range<usize>)],
+    data: &[u8],
+) -> Result<(Vec<u8>, Levels)> {
+    let pixel_size = if info.gl_type != 0 {
+        match convert::pixel_size(info.gl_format, info.gl_type) {
+            Some(x) => x,
+            None => bail!(ErrorKind::UnsupportedPixelFormat(
+                info.gl_format,
+                info.gl_type
+            )),
+        }
+    } else {
+        0
+    };
+    let alignment = match pixel_size {
+        0 => match format::compressed_block_size(info.gl_internal_format) {
+            Some(x) => lcm(u64::from(x.bytes), 4) as usize,
+            None => bail!(ErrorKind::UnsupportedFormat(info.gl_internal_format)),
+        },
+        x => lcm(x as u64, 4) as usize,
+    };
+
+    let mut packed = Vec::new();
+    let mut packed_levels = Vec::with_capacity(levels.len());
+    for (level, range) in levels {
+        packed.resize(packed.len().div_ceil(alignment) * alignment, 0);
+        let start = packed.len();
+        let result = pack_level_rows(level, range, data, pixel_size, &mut packed);
+        packed_levels.push((result.in_level(level.level)?, start..packed.len()));
+    }
+    Ok((packed, packed_levels))
+}
+
+type Levels = Vec<(LevelInfo, Range<usize>)>;
+
+fn pack_level_rows(
+    level: &LevelInfo,
+    range: &Range<usize>,
+    data: &[u8],
+    pixel_size: usize,
+    packed: &mut Vec<u8>,
+) -> Result<LevelInfo> {
+    let level_data = match data.get(range.clone()) {
+        Some(x) if x.len() == level.size() => x,
+        _ => bail!(ErrorKind::InvalidBufferSize(level.size(), range.len())),
+    };
+    let row_size = level.pixel_width as usize * pixel_size;
+    let padded_row_size = (row_size + 3) & !3;
+    if pixel_size == 0 || padded_row_size == row_size {
+        packed.extend_from_slice(level_data);
+        return Ok(level.clone());
+    }
+
+    let rows = level.pixel_height as usize * level.pixel_depth as usize;
+    if (level.frame_size as usize) < padded_row_size * rows {
+        bail!(ErrorKind::InvalidBufferSize(
+            padded_row_size * rows,
+            level.frame_size as usize
+        ));
+    }
+    for (_, frame_range) in level.frames() {
+        let frame = &level_data[frame_range];
+        for row in frame.chunks(padded_row_size).take(rows) {
+            packed.extend_from_slice(&row[..row_size]);
+        }
+    }
+    Ok(LevelInfo {
+        frame_size: (row_size * rows) as u32,
+        ..level.clone()
+    })
+}
+
+fn lcm(a: u64, b: u64) -> u64 {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        let r = x % y;
+        x = y;
+        y = r;
+    }
+    a / x * b
+}
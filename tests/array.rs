@@ -0,0 +1,120 @@
+extern crate ktx_async as ktx;
+
+mod common;
+
+use ktx::array::{extract_layers, ArrayBuilder};
+use ktx::Encoder;
+use lazy_static::lazy_static;
+
+#[tokio::test]
+async fn test_split_and_rebuild() {
+    for path in [
+        "data/khr/texturearray_etc2_unorm.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+    ]
+    .iter()
+    {
+        let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+        let (info, frames) = common::decode(&data).await;
+        let nlayers = info.number_of_array_elements;
+        assert!(nlayers > 1);
+
+        // Write every layer as a standalone texture
+        let mut builder = ArrayBuilder::new();
+        for layer in 0..nlayers {
+            let (layer_info, layer_frames) =
+                extract_layers(&info, frames.clone(), layer..layer + 1).unwrap();
+            assert_eq!(layer_info.number_of_array_elements, 0);
+            assert!(layer_frames.iter().all(|(frame, _)| frame.layer == 0));
+            let file = Encoder::new(Vec::new())
+                .write_async(&layer_info, &layer_frames)
+                .await
+                .unwrap();
+            assert_eq!(ktx::validate(&file), vec![]);
+
+            let (layer_info, layer_frames) = common::decode(&file).await;
+            builder = builder.texture(layer_info, layer_frames);
+        }
+
+        // Stacking them gives back the original file
+        let rebuilt = builder.write_async(Vec::new()).await.unwrap();
+        assert_eq!(rebuilt, data, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_extract_range() {
+    let path = "data/pvr/array-pvrtc-mipmap.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (info, frames) = common::decode(&data).await;
+
+    let (sub_info, sub_frames) = extract_layers(&info, frames.clone(), 1..3).unwrap();
+    assert_eq!(sub_info.number_of_array_elements, 2);
+    assert_eq!(
+        sub_info.number_of_mipmap_levels,
+        info.number_of_mipmap_levels
+    );
+    let expected: Vec<_> = frames
+        .iter()
+        .filter(|(frame, _)| frame.layer == 1 || frame.layer == 2)
+        .map(|(_, buf)| buf.clone())
+        .collect();
+    let actual: Vec<_> = sub_frames.iter().map(|(_, buf)| buf.clone()).collect();
+    assert_eq!(actual, expected);
+
+    let file = Encoder::new(Vec::new())
+        .write_async(&sub_info, &sub_frames)
+        .await
+        .unwrap();
+    assert_eq!(ktx::validate(&file), vec![]);
+
+    let nlayers = info.number_of_array_elements;
+    assert!(extract_layers(&info, frames.clone(), 2..2).is_err());
+    assert!(extract_layers(&info, frames, 0..nlayers + 1).is_err());
+}
+
+#[tokio::test]
+async fn test_mismatched_textures() {
+    let read = |path: &str| std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (rgb_info, rgb_frames) = common::decode(&read("data/khr/rgb-reference.ktx")).await;
+    let (rgba_info, rgba_frames) = common::decode(&read("data/khr/rgba-reference.ktx")).await;
+    let (mip_info, mip_frames) = common::decode(&read("data/khr/rgb-mipmap-reference.ktx")).await;
+
+    let (info, frames) = ArrayBuilder::new()
+        .texture(rgb_info.clone(), rgb_frames.clone())
+        .texture(rgb_info.clone(), rgb_frames.clone())
+        .build()
+        .unwrap();
+    assert_eq!(info.number_of_array_elements, 2);
+    assert_eq!(frames[1].0.layer, 1);
+
+    let err = ArrayBuilder::new()
+        .texture(rgb_info.clone(), rgb_frames.clone())
+        .texture(rgba_info, rgba_frames)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("internal format"), "{}", err);
+
+    let err = ArrayBuilder::new()
+        .texture(rgb_info.clone(), rgb_frames.clone())
+        .texture(mip_info, mip_frames)
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("texture 1 is"), "{}", err);
+
+    let err = ArrayBuilder::new()
+        .texture(rgb_info, vec![])
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("frames"), "{}", err);
+    assert!(ArrayBuilder::new().build().is_err());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
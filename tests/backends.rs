@@ -0,0 +1,329 @@
+//! Decoding and encoding through the `tokio1` and `futures-io` readers
+//! and writers: the decoder cases of `test.rs` on each of them, and the
+//! same output as in-memory readers over every file
+#![cfg(any(feature = "tokio1", feature = "futures-io"))]
+
+extern crate ktx_async as ktx;
+
+mod common;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::io::{AsyncRead, AsyncSeek, AsyncWrite};
+use ktx::{Chunk, Decoder, DecoderOptions, Encoder, FrameInfo, FrameOrder};
+use lazy_static::lazy_static;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn paths() -> Vec<std::path::PathBuf> {
+    let mut paths = vec![];
+    for dir in ["data/khr", "data/pvr"].iter() {
+        for entry in std::fs::read_dir(PROJECT_DIR.join(dir)).unwrap() {
+            paths.push(entry.unwrap().path());
+        }
+    }
+    paths.sort();
+    paths
+}
+
+fn smallest_first() -> DecoderOptions {
+    DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    }
+}
+
+/// Header, frames and chunks of a file
+#[derive(Debug)]
+struct Decoded {
+    header: String,
+    frames: Frames,
+    chunks: Vec<Chunk>,
+    smallest_first: Frames,
+}
+
+async fn decode<R, S, C>(read: R, seekable: S, chunked: C) -> ktx::Result<Decoded>
+where
+    R: AsyncRead + Unpin,
+    S: AsyncRead + AsyncSeek + Unpin,
+    C: AsyncRead + Unpin,
+{
+    let (info, stream) = Decoder::new(read).read_async().await?;
+    let frames = stream.try_collect().await?;
+    let (_, stream) = Decoder::new(chunked).read_chunks_async(4096).await?;
+    let chunks = stream.try_collect().await?;
+    let decoder = Decoder::with_options(seekable, smallest_first());
+    let (_, stream) = decoder.read_seekable_async().await?;
+    let smallest_first = stream.try_collect().await?;
+    Ok(Decoded {
+        header: format!("{:?}", info),
+        frames,
+        chunks,
+        smallest_first,
+    })
+}
+
+async fn decode_reference(data: &[u8]) -> ktx::Result<Decoded> {
+    use std::io::Cursor;
+    decode(data, Cursor::new(data), data).await
+}
+
+/// Same frames and chunks, or the same error
+fn assert_same(
+    decoded: &ktx::Result<Decoded>,
+    reference: &ktx::Result<Decoded>,
+    path: &std::path::Path,
+) {
+    let (decoded, reference) = match (decoded, reference) {
+        (Ok(decoded), Ok(reference)) => (decoded, reference),
+        (Err(e), Err(expected)) => {
+            assert_eq!(e.to_string(), expected.to_string(), "{}", path.display());
+            return;
+        }
+        _ => panic!(
+            "{}: {:?} {:?}",
+            path.display(),
+            decoded.is_ok(),
+            reference.is_ok()
+        ),
+    };
+    let debug = |x: &Decoded| {
+        let frames = |frames: &Frames| -> Vec<String> {
+            frames
+                .iter()
+                .map(|(frame, buf)| format!("{:?} {:?}", frame, buf))
+                .collect()
+        };
+        let chunks: Vec<_> = x.chunks.iter().map(|x| format!("{:?}", x)).collect();
+        (
+            x.header.clone(),
+            frames(&x.frames),
+            chunks,
+            frames(&x.smallest_first),
+        )
+    };
+    assert!(debug(decoded) == debug(reference), "{}", path.display());
+}
+
+/// Levels of the frames of a stream, then whether it ended with a
+/// truncation error
+async fn levels<S>(mut stream: S) -> Vec<Result<u32, bool>>
+where
+    S: futures_core::Stream<Item = ktx::Result<(FrameInfo, Vec<u8>)>> + Unpin,
+{
+    let mut levels = vec![];
+    loop {
+        match stream.try_next().await {
+            Ok(Some((frame, _))) => levels.push(Ok(frame.level)),
+            Ok(None) => break,
+            Err(e) => {
+                levels.push(Err(e.is_truncation()));
+                break;
+            }
+        }
+    }
+    levels
+}
+
+/// KTX 1.1 file of a single RGBA8 frame, and its KTX 2.0 encoding
+async fn encode<W: AsyncWrite + Unpin>(write1: W, write2: W) -> (W, W) {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/rgba-reference.ktx")).unwrap();
+    let (info, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let frames: Frames = stream.try_collect().await.unwrap();
+    let write1 = Encoder::new(write1)
+        .write_async(&info, &frames)
+        .await
+        .unwrap();
+    let write2 = Encoder::new(write2)
+        .write_ktx2_async(&info, &frames)
+        .await
+        .unwrap();
+    (write1, write2)
+}
+
+#[cfg(feature = "tokio1")]
+mod tokio1_backend {
+    use super::*;
+    use ktx::io::Tokio1;
+    use tokio1::fs::File;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio1::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn test_tokio1_files() {
+        for path in paths() {
+            let data = std::fs::read(&path).unwrap();
+            let reference = block_on(decode_reference(&data));
+            let decoded = block_on(async {
+                let read = Tokio1::new(File::open(&path).await.unwrap());
+                let seekable = Tokio1::new(File::open(&path).await.unwrap());
+                let chunked = Tokio1::new(File::open(&path).await.unwrap());
+                decode(read, seekable, chunked).await
+            });
+            assert_same(&decoded, &reference, &path);
+        }
+    }
+
+    #[test]
+    fn test_tokio1_cases() {
+        block_on(common::cases::all(|path| async move {
+            let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+            Tokio1::new(tokio1::io::BufReader::new(file))
+        }));
+    }
+
+    #[test]
+    fn test_tokio1_send() {
+        block_on(async {
+            let path = PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx");
+            let read = Tokio1::new(File::open(&path).await.unwrap());
+            let future = Decoder::with_options(read, smallest_first()).read_seekable_async();
+            assert_send(&future);
+            let (_, stream) = future.await.unwrap();
+            assert_send(&stream);
+        });
+    }
+
+    #[test]
+    fn test_tokio1_lenient() {
+        let path = PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx");
+        let data = std::fs::read(path).unwrap();
+        let options = DecoderOptions {
+            lenient: true,
+            ..smallest_first()
+        };
+        let read = Tokio1::new(std::io::Cursor::new(&data[..15536]));
+        let (_, stream) =
+            block_on(Decoder::with_options(read, options).read_seekable_async()).unwrap();
+        let frames = block_on(levels(stream));
+        assert_eq!(frames, vec![Ok(1), Ok(0), Err(true)]);
+    }
+
+    /// Seeker whose previous operation completes on the second poll
+    struct Busy {
+        polls: u32,
+        started: Option<std::io::SeekFrom>,
+    }
+
+    impl tokio1::io::AsyncSeek for Busy {
+        fn start_seek(
+            mut self: std::pin::Pin<&mut Self>,
+            pos: std::io::SeekFrom,
+        ) -> std::io::Result<()> {
+            assert!(self.polls >= 2, "seek started before completion");
+            self.started = Some(pos);
+            Ok(())
+        }
+
+        fn poll_complete(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<u64>> {
+            self.polls += 1;
+            match (self.polls, self.started) {
+                (1, _) => {
+                    cx.waker().wake_by_ref();
+                    std::task::Poll::Pending
+                }
+                (_, Some(std::io::SeekFrom::Start(pos))) => std::task::Poll::Ready(Ok(pos)),
+                _ => std::task::Poll::Ready(Ok(0)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokio1_seek_waits() {
+        let mut seek = Tokio1::new(Busy {
+            polls: 0,
+            started: None,
+        });
+        let pos = block_on(std::future::poll_fn(|cx| {
+            std::pin::Pin::new(&mut seek).poll_seek(cx, std::io::SeekFrom::Start(42))
+        }));
+        assert_eq!(pos.unwrap(), 42);
+        assert_eq!(seek.get_ref().polls, 3);
+    }
+
+    #[test]
+    fn test_tokio1_encoder() {
+        let reference = block_on(encode(vec![], vec![]));
+        let (ktx1, ktx2) = block_on(encode(Tokio1::new(vec![]), Tokio1::new(vec![])));
+        let (ktx1, ktx2) = (ktx1.into_inner(), ktx2.into_inner());
+        assert_eq!(ktx1, reference.0);
+        assert_eq!(ktx2, reference.1);
+
+        let read = Tokio1::new(&ktx2[..]);
+        let info = block_on(ktx::ktx2::read_header_async(read)).unwrap();
+        assert_eq!(info.pixel_width, 128);
+    }
+}
+
+#[cfg(feature = "futures-io")]
+mod futures_io_backend {
+    use super::*;
+    use futures_util::io::{AllowStdIo, BufReader, Cursor};
+    use ktx::io::FuturesIo;
+
+    fn open(path: &std::path::Path) -> FuturesIo<BufReader<AllowStdIo<std::fs::File>>> {
+        let file = std::fs::File::open(path).unwrap();
+        FuturesIo::new(BufReader::new(AllowStdIo::new(file)))
+    }
+
+    #[tokio::test]
+    async fn test_futures_io_files() {
+        for path in paths() {
+            let data = std::fs::read(&path).unwrap();
+            let reference = decode_reference(&data).await;
+            let decoded = decode(open(&path), open(&path), open(&path)).await;
+            assert_same(&decoded, &reference, &path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_futures_io_cases() {
+        common::cases::all(|path| async move { open(&PROJECT_DIR.join(path)) }).await;
+    }
+
+    #[tokio::test]
+    async fn test_futures_io_lenient() {
+        let path = PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx");
+        let data = std::fs::read(path).unwrap();
+        let options = DecoderOptions {
+            lenient: true,
+            ..smallest_first()
+        };
+        let read = FuturesIo::new(Cursor::new(&data[..15536]));
+        let decoder = Decoder::with_options(read, options);
+        let (_, stream) = decoder.read_seekable_async().await.unwrap();
+        let frames = levels(stream).await;
+        assert_eq!(frames, vec![Ok(1), Ok(0), Err(true)]);
+    }
+
+    #[tokio::test]
+    async fn test_futures_io_encoder() {
+        let reference = encode(vec![], vec![]).await;
+        let (ktx1, ktx2) = encode(FuturesIo::new(vec![]), FuturesIo::new(vec![])).await;
+        let (ktx1, ktx2) = (ktx1.into_inner(), ktx2.into_inner());
+        assert_eq!(ktx1, reference.0);
+        assert_eq!(ktx2, reference.1);
+
+        let read = FuturesIo::new(&ktx2[..]);
+        let info = ktx::ktx2::read_header_async(read).await.unwrap();
+        assert_eq!(info.pixel_width, 128);
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,95 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{Chunk, Decoder, FrameInfo};
+use lazy_static::lazy_static;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+async fn read_both(path: &str, max_chunk_size: usize) -> (Frames, Vec<Chunk>) {
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let frames = stream.try_collect().await.unwrap();
+    let (_, stream) = Decoder::new(&data[..])
+        .read_chunks_async(max_chunk_size)
+        .await
+        .unwrap();
+    let chunks = stream.try_collect().await.unwrap();
+    (frames, chunks)
+}
+
+/// Check that the chunks cover the frames row by row
+fn check_chunks(frames: &Frames, chunks: &[Chunk], block_height: u32) {
+    let mut chunks = chunks.iter().peekable();
+    for (frame, buf) in frames {
+        let mut data = vec![];
+        let mut next_row = 0;
+        while let Some((chunk_frame, rows, bytes)) = chunks.peek() {
+            if (chunk_frame.level, chunk_frame.layer, chunk_frame.face)
+                != (frame.level, frame.layer, frame.face)
+            {
+                break;
+            }
+            assert_eq!(rows.start, next_row);
+            assert_eq!(rows.start % block_height, 0);
+            assert!(rows.end > rows.start);
+            next_row = rows.end;
+            data.extend_from_slice(bytes);
+            chunks.next();
+        }
+        assert_eq!(next_row, frame.pixel_height * frame.pixel_depth);
+        assert_eq!(&data, buf);
+    }
+    assert!(chunks.next().is_none());
+}
+
+#[tokio::test]
+async fn test_uncompressed_chunks() {
+    // Rows of 270 RGB pixels are padded to 812 bytes
+    let (frames, chunks) = read_both("data/khr/not4_rgb888_srgb.ktx", 4000).await;
+    check_chunks(&frames, &chunks, 1);
+    assert_eq!(chunks[0].1, 0..4);
+    assert_eq!(chunks[0].2.len(), 4 * 812);
+    let mut level0 = chunks.iter().filter(|(frame, _, _)| frame.level == 0);
+    assert_eq!(level0.next_back().unwrap().1, 268..270);
+
+    let (frames, chunks) = read_both("data/khr/rgb-mipmap-reference.ktx", 1000).await;
+    check_chunks(&frames, &chunks, 1);
+    assert!(chunks.iter().all(|(_, _, bytes)| bytes.len() <= 1000));
+    assert!(chunks.len() > frames.len());
+}
+
+#[tokio::test]
+async fn test_compressed_chunks() {
+    let (frames, chunks) = read_both("data/khr/pattern_02_bc2.ktx", 16 * 1024).await;
+    check_chunks(&frames, &chunks, 4);
+    // 64 rows of 256 blocks of 16 bytes, then 2x2 and 1x1 levels in a
+    // partial block
+    assert_eq!(chunks[0].1, 0..16);
+    let last_ranges: Vec<_> = chunks[chunks.len() - 2..]
+        .iter()
+        .map(|(_, rows, _)| rows.clone())
+        .collect();
+    assert_eq!(last_ranges, vec![0..2, 0..1]);
+
+    // A chunk holds at least one row of blocks
+    let (frames, chunks) = read_both("data/khr/texturearray_etc2_unorm.ktx", 1).await;
+    check_chunks(&frames, &chunks, 4);
+    assert_eq!(chunks.len(), frames.len() * 64);
+}
+
+#[tokio::test]
+async fn test_pvrtc_frames_are_single_chunks() {
+    let (frames, chunks) = read_both("data/pvr/array-pvrtc-mipmap.ktx", 64).await;
+    check_chunks(&frames, &chunks, 1);
+    assert_eq!(chunks.len(), frames.len());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "cli")]
+
+extern crate ktx_async as ktx;
+
+use lazy_static::lazy_static;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Create an empty directory for the output of a test
+fn output_dir(test: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn list_dir(dir: &PathBuf) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_extract_png() {
+    let dir = output_dir("extract_png");
+    let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .arg("extract")
+        .arg("-o")
+        .arg(&dir)
+        .arg(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx"))
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+
+    let names = list_dir(&dir);
+    assert_eq!(names.len(), 7);
+    assert_eq!(names[0], "rgb-mipmap-reference_L0_A0_F0.png");
+    let image = image::open(dir.join(&names[2])).unwrap();
+    assert_eq!((image.width(), image.height()), (16, 16));
+}
+
+#[test]
+fn test_extract_raw() {
+    let dir = output_dir("extract_raw");
+    let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "--raw", "-t", "{face}.bin", "-o"])
+        .arg(&dir)
+        .arg(PROJECT_DIR.join("data/khr/cubemap_yokohama_etc2_unorm.ktx"))
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+
+    let names = list_dir(&dir);
+    assert_eq!(
+        names,
+        ["0.bin", "1.bin", "2.bin", "3.bin", "4.bin", "5.bin"]
+    );
+    let face = std::fs::read(dir.join("5.bin")).unwrap();
+    assert_eq!(face.len(), 512 * 512 / 2);
+}
+
+#[test]
+fn test_extract_unsupported() {
+    // ETC1 has no software decoder
+    let dir = output_dir("extract_unsupported");
+    let output = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "-o"])
+        .arg(&dir)
+        .arg(PROJECT_DIR.join("data/khr/etc1.ktx"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--raw"));
+}
+
+#[test]
+fn test_extract_lenient() {
+    // Cut in level 2
+    let dir = output_dir("extract_lenient");
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx")).unwrap();
+    let input = dir.join("cut.ktx");
+    std::fs::write(&input, &data[..15536]).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .output()
+        .unwrap()
+        .status;
+    assert!(!status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "--lenient", "-o"])
+        .arg(&dir)
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("level 2"));
+    assert_eq!(
+        list_dir(&dir),
+        ["cut.ktx", "cut_L0_A0_F0.png", "cut_L1_A0_F0.png"]
+    );
+}
+
+#[test]
+fn test_create() {
+    let dir = output_dir("create");
+    let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "-o"])
+        .arg(&dir)
+        .arg(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx"))
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+    let inputs = list_dir(&dir);
+
+    for output in ["out.ktx", "out.ktx2"].iter() {
+        let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+            .args(["create", "--mipmaps", "-f", "RGB8", "--kv", "tool=test"])
+            .arg("-o")
+            .arg(dir.join(output))
+            .args(inputs.iter().map(|x| dir.join(x)))
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+        let data = std::fs::read(dir.join(output)).unwrap();
+        assert_eq!(ktx::validate(&data), vec![]);
+    }
+
+    // The pixels survive the round trip through PNG
+    let original = std::fs::read(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx")).unwrap();
+    let created = std::fs::read(dir.join("out.ktx")).unwrap();
+    assert_eq!(
+        original[64..],
+        created[created.len() - (original.len() - 64)..]
+    );
+}
+
+#[test]
+fn test_create_cubemap_needs_six_faces() {
+    let dir = output_dir("create_cubemap");
+    let output = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["create", "--cubemap", "--raw", "4x4", "-f", "RGBA8", "-o"])
+        .arg(dir.join("out.ktx"))
+        .arg(PROJECT_DIR.join("data/khr/etc1.ktx"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("6 inputs"));
+}
+
+#[test]
+fn test_create_equirect() {
+    let dir = output_dir("create_equirect");
+    let status = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["extract", "-o"])
+        .arg(&dir)
+        .arg(PROJECT_DIR.join("data/khr/rgb-reference.ktx"))
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+    let input = dir.join(&list_dir(&dir)[0]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["create", "--equirect", "16", "-o"])
+        .arg(dir.join("out.ktx"))
+        .arg(input)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let data = std::fs::read(dir.join("out.ktx")).unwrap();
+    assert_eq!(ktx::validate(&data), vec![]);
+    let u32_at = |i: usize| u32::from_ne_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    assert_eq!(u32_at(28), ktx::gl::SRGB8);
+    assert_eq!((u32_at(36), u32_at(40)), (16, 16));
+    assert_eq!(u32_at(52), 6);
+}
+
+#[test]
+fn test_trim() {
+    let dir = output_dir("trim");
+    let output = Command::new(env!("CARGO_BIN_EXE_ktx"))
+        .args(["trim", "--drop", "1", "--min-size", "8", "-o"])
+        .arg(dir.join("out.ktx"))
+        .arg(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let data = std::fs::read(dir.join("out.ktx")).unwrap();
+    assert_eq!(ktx::validate(&data), vec![]);
+    let u32_at = |i: usize| u32::from_ne_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+    assert_eq!((u32_at(36), u32_at(40)), (32, 32));
+    assert_eq!(u32_at(56), 3);
+}
+
+#[test]
+fn test_info_generated_mipmaps() {
+    // numberOfMipmapLevels == 0 still stores level 0
+    let dir = output_dir("info_generated_mipmaps");
+    let mut data = std::fs::read(PROJECT_DIR.join("data/khr/rgb-reference.ktx")).unwrap();
+    data[56..60].copy_from_slice(&0u32.to_ne_bytes());
+    std::fs::write(dir.join("in.ktx"), &data).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ktxinfo"))
+        .arg("--json")
+        .arg(dir.join("in.ktx"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["header"]["numberOfMipmapLevels"], 0);
+    assert_eq!(report["levels"].as_array().unwrap().len(), 1);
+    assert_eq!(report["levels"][0]["width"], 128);
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
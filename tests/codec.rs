@@ -0,0 +1,143 @@
+#![cfg(feature = "tokio02")]
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::StreamExt as _;
+use ktx::codec::pvrtc::{self, Bpp};
+use ktx::Decoder;
+use lazy_static::lazy_static;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+#[tokio::test]
+async fn test_decode_array_pvrtc() {
+    let path = "data/pvr/array-pvrtc-mipmap.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let reader = BufReader::new(file);
+    let decoder = Decoder::new(reader);
+    let (info, mut stream) = decoder.read_async().await.unwrap();
+
+    // Pixels of the first frame (a lava texture), as decoded by an
+    // independent port of the PowerVR SDK decompressor
+    let reference = [
+        ((0, 0), [193, 2, 0, 255]),
+        ((1, 0), [179, 1, 0, 255]),
+        ((96, 0), [253, 152, 92, 255]),
+        ((37, 5), [150, 0, 0, 255]),
+        ((97, 6), [255, 193, 113, 255]),
+        ((48, 55), [253, 239, 147, 255]),
+        ((128, 128), [154, 0, 0, 255]),
+        ((255, 255), [254, 202, 137, 255]),
+    ];
+
+    let mut nframes = 0;
+    while let Some((frame, buf)) = stream.next().await.map(|r| r.unwrap()) {
+        let pixels = ktx::codec::decode_rgba8(&info, &frame, &buf).unwrap();
+        let expected_len = frame.pixel_width * frame.pixel_height * 4;
+        assert_eq!(pixels.len(), expected_len as usize);
+        if nframes == 0 {
+            for ((x, y), px) in reference.iter() {
+                let i = (y * 256 + x) * 4;
+                assert_eq!(&pixels[i..i + 4], px, "({}, {})", x, y);
+            }
+        }
+        nframes += 1;
+    }
+    assert_eq!(nframes, 9 * 7);
+}
+
+#[test]
+fn test_pvrtc_image_size() {
+    assert_eq!(pvrtc::image_size(256, 256, Bpp::Four), 256 * 256 / 2);
+    assert_eq!(pvrtc::image_size(256, 256, Bpp::Two), 256 * 256 / 4);
+    assert_eq!(pvrtc::image_size(1, 1, Bpp::Four), 32);
+    assert_eq!(pvrtc::image_size(1, 1, Bpp::Two), 32);
+}
+
+#[test]
+fn test_pvrtc_too_short() {
+    let data = vec![0_u8; 31];
+    assert!(pvrtc::decode(&data, 8, 8, Bpp::Four).is_err());
+}
+
+#[test]
+fn test_pvrtc2_direct_modulation() {
+    // Opaque black A, opaque white B
+    let color = 0xFFFF_8000_u32;
+
+    // All pixels fully modulated towards B
+    let data = pvrtc_words(&[(0xFFFF_FFFF, color); 4]);
+    let pixels = pvrtc::decode(&data, 16, 8, Bpp::Two).unwrap();
+    assert_eq!(pixels.len(), 16 * 8 * 4);
+    assert!(pixels.chunks(4).all(|px| px == [255, 255, 255, 255]));
+
+    // All pixels take A
+    let data = pvrtc_words(&[(0x0000_0000, color); 4]);
+    let pixels = pvrtc::decode(&data, 16, 8, Bpp::Two).unwrap();
+    assert!(pixels.chunks(4).all(|px| px == [0, 0, 0, 255]));
+}
+
+#[test]
+fn test_pvrtc2_interpolated_modulation() {
+    // Opaque black A, opaque white B, interpolated modulation
+    let color = 0xFFFF_8001_u32;
+    let data = pvrtc_words(&[(0xFFFF_FFFF, color); 4]);
+    let pixels = pvrtc::decode(&data, 16, 8, Bpp::Two).unwrap();
+    assert!(pixels.chunks(4).all(|px| px == [255, 255, 255, 255]));
+}
+
+#[test]
+fn test_pvrtc4_punch_through() {
+    // Opaque black A, opaque white B, punch-through modulation
+    let color = 0xFFFF_8001_u32;
+    let data = pvrtc_words(&[(0xAAAA_AAAA, color); 4]);
+    let pixels = pvrtc::decode(&data, 8, 8, Bpp::Four).unwrap();
+    assert!(pixels.chunks(4).all(|px| px[3] == 0));
+}
+
+#[test]
+fn test_pvrtc4_bilinear_wrap() {
+    // Word (0, 0) has opaque white A, the others opaque black A.
+    let white = 0x8000_FFFE_u32;
+    let black = 0x8000_8000_u32;
+    let data = pvrtc_words(&[(0, white), (0, black), (0, black), (0, black)]);
+    let pixels = pvrtc::decode(&data, 8, 8, Bpp::Four).unwrap();
+    let red = |x: usize, y: usize| pixels[(y * 8 + x) * 4];
+
+    // Centre of word (0, 0)
+    assert_eq!(red(2, 2), 255);
+    // Centre of word (1, 1)
+    assert_eq!(red(6, 6), 0);
+    // Halfway between words (0, 0) and (1, 0)
+    assert_eq!(red(4, 2), 127);
+    // Halfway between words (1, 0) and (0, 0), across the edge
+    assert_eq!(red(0, 2), 127);
+    assert_eq!(red(2, 0), 127);
+}
+
+#[test]
+fn test_pvrtc4_small_texture() {
+    let color = 0xFFFF_8000_u32;
+    let data = pvrtc_words(&[(0xFFFF_FFFF, color); 4]);
+    let pixels = pvrtc::decode(&data, 1, 1, Bpp::Four).unwrap();
+    assert_eq!(pixels, vec![255, 255, 255, 255]);
+}
+
+/// Build PVRTC data from (modulation, color) words in twiddled order
+fn pvrtc_words(words: &[(u32, u32)]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(words.len() * 8);
+    for &(modulation, color) in words {
+        data.extend_from_slice(&modulation.to_le_bytes());
+        data.extend_from_slice(&color.to_le_bytes());
+    }
+    data
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
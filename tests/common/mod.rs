@@ -0,0 +1,46 @@
+//! Helpers shared by the tests
+#![allow(dead_code)]
+
+pub mod cases;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{gl, Decoder, FrameInfo, HeaderInfo};
+
+/// Header and frames of a file in memory, decoded with the default
+/// options
+pub async fn decode(data: &[u8]) -> (HeaderInfo, Vec<(FrameInfo, Vec<u8>)>) {
+    let (info, stream) = Decoder::new(data).read_async().await.unwrap();
+    let frames = stream.try_collect().await.unwrap();
+    (info, frames)
+}
+
+/// Header of a 4x4 2D texture with one level and no key/value data
+/// (`gl_format` and `gl_type` are 0 for compressed formats)
+pub fn header(gl_internal_format: u32, gl_format: u32, gl_type: u32) -> HeaderInfo {
+    HeaderInfo {
+        gl_type,
+        gl_type_size: 1,
+        gl_format,
+        gl_internal_format,
+        gl_base_internal_format: if gl_format == 0 { gl::RGBA } else { gl_format },
+        pixel_width: 4,
+        pixel_height: 4,
+        pixel_depth: 0,
+        number_of_array_elements: 0,
+        number_of_faces: 1,
+        number_of_mipmap_levels: 1,
+        key_value_data: Default::default(),
+    }
+}
+
+/// First frame of a 2D texture
+pub fn frame(width: u32, height: u32) -> FrameInfo {
+    FrameInfo {
+        level: 0,
+        layer: 0,
+        face: 0,
+        pixel_width: width,
+        pixel_height: height,
+        pixel_depth: 1,
+    }
+}
@@ -0,0 +1,188 @@
+#![cfg(feature = "tokio02")]
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::StreamExt as _;
+use ktx::{convert, gl, Decoder, FrameInfo, HeaderInfo};
+use lazy_static::lazy_static;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+#[tokio::test]
+async fn test_rgb_row_alignment() {
+    let path = "data/khr/not4_rgb888_srgb.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let reader = BufReader::new(file);
+    let decoder = Decoder::new(reader);
+    let (info, mut stream) = decoder.read_async().await.unwrap();
+
+    let (frame, buf) = stream.next().await.map(|r| r.unwrap()).unwrap();
+    assert_eq!(frame.pixel_width, 270);
+    let pixels = convert::to_rgba8(&info, &frame, &buf).unwrap();
+    assert_eq!(pixels.len(), 270 * 270 * 4);
+
+    // Rows are padded from 810 to 812 bytes
+    let row_stride = 812;
+    for y in [0, 1, 269].iter() {
+        let src = &buf[y * row_stride..y * row_stride + 3];
+        let dst = &pixels[y * 270 * 4..y * 270 * 4 + 4];
+        assert_eq!(&dst[..3], src);
+        assert_eq!(dst[3], 255);
+    }
+}
+
+#[tokio::test]
+async fn test_luminance() {
+    let path = "data/khr/luminance-reference-metadata.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let reader = BufReader::new(file);
+    let decoder = Decoder::new(reader);
+    let (info, mut stream) = decoder.read_async().await.unwrap();
+    assert_eq!(info.gl_format, gl::LUMINANCE);
+
+    let (frame, buf) = stream.next().await.map(|r| r.unwrap()).unwrap();
+    let pixels = convert::to_rgba8(&info, &frame, &buf).unwrap();
+    assert_eq!(pixels.len(), 200 * 100 * 4);
+    for (px, &l) in pixels.chunks(4).zip(buf.iter()) {
+        assert_eq!(px, [l, l, l, 255]);
+    }
+}
+
+#[test]
+fn test_packed_565() {
+    let info = header(gl::RGB, gl::UNSIGNED_SHORT_5_6_5);
+    let buf = pixels_u16(&[0xF800, 0x07E0, 0x001F, 0xFFFF]);
+    let pixels = convert::to_rgba8(&info, &frame(4, 1), &buf).unwrap();
+    assert_eq!(
+        pixels,
+        vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 255]
+    );
+
+    // BGR swaps the first and third components
+    let info = header(gl::BGR, gl::UNSIGNED_SHORT_5_6_5);
+    let pixels = convert::to_rgba8(&info, &frame(4, 1), &buf).unwrap();
+    assert_eq!(&pixels[0..4], &[0, 0, 255, 255]);
+}
+
+#[test]
+fn test_packed_4444_and_5551() {
+    let info = header(gl::RGBA, gl::UNSIGNED_SHORT_4_4_4_4);
+    let buf = pixels_u16(&[0xF00F, 0x0F00]);
+    let pixels = convert::to_rgba8(&info, &frame(2, 1), &buf).unwrap();
+    assert_eq!(pixels, vec![255, 0, 0, 255, 0, 255, 0, 0]);
+
+    let info = header(gl::RGBA, gl::UNSIGNED_SHORT_5_5_5_1);
+    let buf = pixels_u16(&[0x003F, 0xF800]);
+    let pixels = convert::to_rgba8(&info, &frame(2, 1), &buf).unwrap();
+    assert_eq!(pixels, vec![0, 0, 255, 255, 255, 0, 0, 0]);
+}
+
+#[test]
+fn test_packed_2_10_10_10_rev() {
+    let info = header(gl::RGBA, gl::UNSIGNED_INT_2_10_10_10_REV);
+    let v: u32 = (3 << 30) | (0x3FF << 10);
+    let pixels = convert::to_rgba32f(&info, &frame(1, 1), &v.to_ne_bytes()).unwrap();
+    assert_eq!(pixels, vec![0.0, 1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn test_packed_float_formats() {
+    // R = 1.0 (exponent 15), G = 2.0 (exponent 16), B = 0.5 (exponent 14)
+    let info = header(gl::RGB, gl::UNSIGNED_INT_10F_11F_11F_REV);
+    let v: u32 = (15 << 6) | ((16 << 6) << 11) | ((14 << 5) << 22);
+    let pixels = convert::to_rgba32f(&info, &frame(1, 1), &v.to_ne_bytes()).unwrap();
+    assert_eq!(pixels, vec![1.0, 2.0, 0.5, 1.0]);
+
+    // Shared exponent: mantissa 256 with exponent 16 => 1.0
+    let info = header(gl::RGB, gl::UNSIGNED_INT_5_9_9_9_REV);
+    let v: u32 = (16 << 27) | 256 | (128 << 9);
+    let pixels = convert::to_rgba32f(&info, &frame(1, 1), &v.to_ne_bytes()).unwrap();
+    assert_eq!(pixels, vec![1.0, 0.5, 0.0, 1.0]);
+
+    // Only RGB is valid for these types
+    let info = header(gl::RGBA, gl::UNSIGNED_INT_5_9_9_9_REV);
+    assert!(convert::to_rgba32f(&info, &frame(1, 1), &v.to_ne_bytes()).is_err());
+}
+
+#[test]
+fn test_half_float() {
+    let info = header(gl::LUMINANCE_ALPHA, gl::HALF_FLOAT);
+    // 1.0, -2.0, 0.5, 65504
+    let buf = pixels_u16(&[0x3C00, 0xC000, 0x3800, 0x7BFF]);
+    let pixels = convert::to_rgba32f(&info, &frame(2, 1), &buf).unwrap();
+    assert_eq!(pixels, vec![1.0, 1.0, 1.0, -2.0, 0.5, 0.5, 0.5, 65504.0]);
+
+    let pixels = convert::to_rgba8(&info, &frame(2, 1), &buf).unwrap();
+    assert_eq!(pixels, vec![255, 255, 255, 0, 128, 128, 128, 255]);
+
+    for gl_type in [gl::HALF_FLOAT, gl::HALF_FLOAT_OES].iter() {
+        assert_eq!(ktx::format::type_size(gl::RGBA, *gl_type), 2);
+    }
+}
+
+#[test]
+fn test_alpha_and_signed() {
+    let info = header(gl::ALPHA, gl::UNSIGNED_BYTE);
+    // 3 bytes of pixels padded to 4 bytes per row
+    let buf = [10, 20, 30, 0, 40, 50, 60, 0];
+    let pixels = convert::to_rgba8(&info, &frame(3, 2), &buf).unwrap();
+    assert_eq!(&pixels[12..16], &[0, 0, 0, 40]);
+
+    let info = header(gl::RG, gl::BYTE);
+    let buf = [127, 0x80, 0, 0];
+    let pixels = convert::to_rgba32f(&info, &frame(1, 1), &buf).unwrap();
+    assert_eq!(pixels, vec![1.0, -1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn test_invalid_input() {
+    let info = header(gl::RGB, gl::UNSIGNED_SHORT_4_4_4_4);
+    assert!(convert::to_rgba8(&info, &frame(1, 1), &[0; 4]).is_err());
+
+    let info = header(gl::RGBA, gl::UNSIGNED_BYTE);
+    assert!(convert::to_rgba8(&info, &frame(2, 2), &[0; 15]).is_err());
+}
+
+fn header(gl_format: u32, gl_type: u32) -> HeaderInfo {
+    HeaderInfo {
+        gl_type,
+        gl_type_size: 1,
+        gl_format,
+        gl_internal_format: 0,
+        gl_base_internal_format: gl_format,
+        pixel_width: 0,
+        pixel_height: 0,
+        pixel_depth: 0,
+        number_of_array_elements: 0,
+        number_of_faces: 1,
+        number_of_mipmap_levels: 1,
+        key_value_data: Default::default(),
+    }
+}
+
+fn frame(pixel_width: u32, pixel_height: u32) -> FrameInfo {
+    FrameInfo {
+        level: 0,
+        layer: 0,
+        face: 0,
+        pixel_width,
+        pixel_height,
+        pixel_depth: 1,
+    }
+}
+
+fn pixels_u16(values: &[u16]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|v| v.to_ne_bytes().to_vec())
+        .collect()
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,138 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::cubemap::{equirectangular_to_faces, CubemapBuilder};
+use ktx::{gl, Decoder, FrameInfo};
+use lazy_static::lazy_static;
+
+#[tokio::test]
+async fn test_builder_roundtrip() {
+    let path = PROJECT_DIR.join("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+    let data = std::fs::read(path).unwrap();
+    let (info, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let frames: Vec<(FrameInfo, Vec<u8>)> = stream.try_collect().await.unwrap();
+
+    let mut builder = CubemapBuilder::new(info.gl_internal_format);
+    for (frame, buf) in frames {
+        builder = builder.face(frame.pixel_width, frame.pixel_height, buf);
+    }
+    let builder = builder.key_value_data(info.key_value_data.clone());
+    let (built, _) = builder.clone().build().unwrap();
+    assert_eq!(built.gl_base_internal_format, gl::RGB);
+    assert_eq!(built.gl_type_size, 1);
+
+    let written = builder.write_async(Vec::new()).await.unwrap();
+    assert_eq!(written, data);
+}
+
+#[test]
+fn test_builder_errors() {
+    let face = |size: u32| vec![0_u8; (size * size * 4) as usize];
+    let faces = |sizes: &[(u32, u32)]| {
+        let mut builder = CubemapBuilder::new(gl::RGBA8);
+        for &(w, h) in sizes {
+            builder = builder.face(w, h, vec![0; (w * h * 4) as usize]);
+        }
+        builder
+    };
+
+    assert!(faces(&[(4, 4); 6]).build().is_ok());
+    let err = faces(&[(4, 4); 5]).build().unwrap_err();
+    assert!(err.to_string().contains("6 faces"), "{}", err);
+    let err = faces(&[(4, 4), (4, 4), (4, 2), (4, 4), (4, 4), (4, 4)])
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("not square"), "{}", err);
+    let err = faces(&[(4, 4), (4, 4), (4, 4), (4, 4), (4, 4), (8, 8)])
+        .build()
+        .unwrap_err();
+    assert!(err.to_string().contains("face 5"), "{}", err);
+
+    let mut builder = CubemapBuilder::new(gl::RGBA8);
+    for _ in 0..5 {
+        builder = builder.face(4, 4, face(4));
+    }
+    let builder = builder.face(4, 4, vec![0; 60]);
+    assert!(builder.build().is_err());
+}
+
+#[test]
+fn test_equirectangular_directions() {
+    // Store the texture coordinates of each pixel
+    let (width, height) = (64, 32);
+    let mut pixels = vec![];
+    for y in 0..height {
+        for x in 0..width {
+            let u = (x as f32 + 0.5) / width as f32;
+            let v = (y as f32 + 0.5) / height as f32;
+            pixels.extend_from_slice(&[u, v, 0.0, 1.0]);
+        }
+    }
+    let faces = equirectangular_to_faces(&pixels, width, height, 8);
+    assert_eq!(faces.len(), 6);
+
+    let center = |face: &[f32]| {
+        // Average of the four pixels around the center
+        let px = |x: usize, y: usize| &face[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        let mut sum = [0.0; 4];
+        for (x, y) in [(3, 3), (4, 3), (3, 4), (4, 4)].iter() {
+            for (c, v) in sum.iter_mut().zip(px(*x, *y)) {
+                *c += v / 4.0;
+            }
+        }
+        sum
+    };
+    let close = |a: f32, b: f32| (a - b).abs() < 0.02;
+
+    // +X, -X and -Z are on the horizon, -Z in the middle
+    let c = center(&faces[0]);
+    assert!(close(c[0], 0.75) && close(c[1], 0.5), "{:?}", c);
+    let c = center(&faces[1]);
+    assert!(close(c[0], 0.25) && close(c[1], 0.5), "{:?}", c);
+    let c = center(&faces[5]);
+    assert!(close(c[0], 0.5) && close(c[1], 0.5), "{:?}", c);
+    // +Y is the top row, -Y the bottom row
+    assert!(faces[2].chunks_exact(4).all(|px| px[1] < 0.5));
+    assert!(faces[3].chunks_exact(4).all(|px| px[1] > 0.5));
+    assert!(faces
+        .iter()
+        .all(|face| face.chunks_exact(4).all(|px| px[3] == 1.0)));
+}
+
+#[tokio::test]
+async fn test_from_equirectangular() {
+    let (width, height) = (32, 16);
+    let pixels: Vec<f32> = (0..width * height)
+        .flat_map(|_| [0.5, 0.25, 1.0, 1.0])
+        .collect();
+    let builder =
+        CubemapBuilder::from_equirectangular(gl::SRGB8_ALPHA8, &pixels, width, height, 4).unwrap();
+    let (info, frames) = builder.build().unwrap();
+    assert_eq!(info.number_of_faces, 6);
+    assert_eq!((info.pixel_width, info.pixel_height), (4, 4));
+    for (frame, buf) in &frames {
+        assert_eq!(buf.len(), 4 * 4 * 4);
+        for px in buf.chunks_exact(4) {
+            assert_eq!(px, [128, 64, 255, 255], "face {}", frame.face);
+        }
+    }
+
+    let data = ktx::Encoder::new(Vec::new())
+        .write_async(&info, &frames)
+        .await
+        .unwrap();
+    assert_eq!(ktx::validate(&data), vec![]);
+
+    assert!(CubemapBuilder::from_equirectangular(gl::RGBA8, &pixels, width, 8, 4).is_err());
+    let etc = gl::COMPRESSED_RGB8_ETC2;
+    assert!(CubemapBuilder::from_equirectangular(etc, &pixels, width, height, 4).is_err());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
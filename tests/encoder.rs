@@ -0,0 +1,193 @@
+extern crate ktx_async as ktx;
+
+mod common;
+
+use ktx::{gl, ktx2, vk, Encoder, ErrorKind, FrameInfo, HeaderInfo, KeyValueData};
+use lazy_static::lazy_static;
+
+#[tokio::test]
+async fn test_round_trip() {
+    for path in [
+        "data/khr/rgb-mipmap-reference.ktx",
+        "data/khr/rgb-reference-metadata.ktx",
+        "data/khr/not4_rgb888_srgb.ktx",
+        "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+        "data/khr/texturearray_bc3_unorm.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+    ]
+    .iter()
+    {
+        let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+        let (info, frames) = common::decode(&data).await;
+        let encoded = Encoder::new(Vec::new())
+            .write_async(&info, &frames)
+            .await
+            .unwrap();
+        assert!(encoded == data, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_ktx2() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (info, frames) = common::decode(&data).await;
+    let mut info = info;
+    info.key_value_data.push("KTXorientation", b"S=r,T=d\0");
+
+    let encoded = Encoder::new(Vec::new())
+        .write_ktx2_async(&info, &frames)
+        .await
+        .unwrap();
+    assert!(ktx::validate(&encoded).is_empty());
+
+    let header = ktx2::read_header_async(&encoded[..]).await.unwrap();
+    assert_eq!(header.vk_format, vk::R8G8B8_UNORM);
+    assert_eq!(header.type_size, 1);
+    assert_eq!(header.level_count, 7);
+    assert_eq!(
+        header.key_value_data.get("KTXorientation"),
+        Some(&b"rd\0"[..])
+    );
+
+    // Levels are stored smallest first, without row padding
+    let offsets: Vec<u64> = header.levels.iter().map(|x| x.byte_offset).collect();
+    assert!(offsets.windows(2).all(|x| x[0] > x[1]));
+    for (level, (frame, buf)) in header.levels.iter().zip(&frames) {
+        let size = frame.pixel_width * frame.pixel_height * 3;
+        assert_eq!(level.byte_length, u64::from(size));
+        assert_eq!(level.byte_offset % 12, 0);
+        let start = level.byte_offset as usize;
+        let row_size = frame.pixel_width as usize * 3;
+        assert_eq!(&encoded[start..start + row_size], &buf[..row_size]);
+    }
+}
+
+#[tokio::test]
+async fn test_ktx2_unsupported_format() {
+    let path = "data/khr/etc1.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (info, frames) = common::decode(&data).await;
+    let err = Encoder::new(Vec::new())
+        .write_ktx2_async(&info, &frames)
+        .await
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::UnsupportedFormat(x) => assert_eq!(*x, gl::ETC1_RGB8_OES),
+        x => panic!("unexpected error {:?}", x),
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_frames() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (info, mut frames) = common::decode(&data).await;
+
+    frames[1].1.pop();
+    let err = Encoder::new(Vec::new())
+        .write_async(&info, &frames)
+        .await
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::InvalidBufferSize(expect, actual) => {
+            assert_eq!((*expect, *actual), (3072, 3071))
+        }
+        x => panic!("unexpected error {:?}", x),
+    }
+
+    frames[1].1.push(0);
+    frames.swap(1, 2);
+    let err = Encoder::new(Vec::new())
+        .write_async(&info, &frames)
+        .await
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::FrameOutOfOrder(2, 0, 0) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+
+    frames.pop();
+    let err = Encoder::new(Vec::new())
+        .write_async(&info, &frames)
+        .await
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::InvalidFrameCount(7, 6) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+}
+
+#[tokio::test]
+async fn test_image_size_overflow() {
+    // 65 layers of 64 MiB share a buffer
+    let buf = vec![0_u8; 4096 * 4096 * 4];
+    let info = HeaderInfo {
+        gl_type: gl::UNSIGNED_BYTE,
+        gl_type_size: 1,
+        gl_format: gl::RGBA,
+        gl_internal_format: gl::RGBA8,
+        gl_base_internal_format: gl::RGBA,
+        pixel_width: 4096,
+        pixel_height: 4096,
+        pixel_depth: 0,
+        number_of_array_elements: 65,
+        number_of_faces: 1,
+        number_of_mipmap_levels: 1,
+        key_value_data: Default::default(),
+    };
+    let frames: Vec<_> = (0..65)
+        .map(|layer| {
+            let frame = FrameInfo {
+                layer,
+                ..common::frame(4096, 4096)
+            };
+            (frame, &buf[..])
+        })
+        .collect();
+    let err = Encoder::new(Vec::new())
+        .write_async(&info, &frames)
+        .await
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::ImageSizeOverflow(size) => assert_eq!(*size, 65 << 26),
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!(err.level(), Some(0));
+}
+
+#[test]
+fn test_key_value_data() {
+    let mut kvd = KeyValueData::default();
+    kvd.push("KTXorientation", b"S=r,T=d\0");
+    kvd.push("a", b"");
+    kvd.push("b", &[1, 2, 3]);
+    let entries: Vec<_> = kvd.iter().collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("KTXorientation", &b"S=r,T=d\0"[..]),
+            ("a", &b""[..]),
+            ("b", &[1, 2, 3][..])
+        ]
+    );
+    assert_eq!(kvd.get("b"), Some(&[1, 2, 3][..]));
+    assert_eq!(kvd.get("c"), None);
+}
+
+#[test]
+fn test_from_name() {
+    assert_eq!(gl::from_name("GL_RGBA8"), Some(gl::RGBA8));
+    assert_eq!(gl::from_name("SRGB8_ALPHA8"), Some(gl::SRGB8_ALPHA8));
+    assert_eq!(gl::from_name("RGBA9"), None);
+    assert_eq!(vk::from_name("R8_UNORM"), Some(vk::R8_UNORM));
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,173 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::mipmap::Filter;
+use ktx::{Chunk, Decoder, DecoderOptions, ErrorKind, FrameInfo, FrameOrder, Limits};
+use lazy_static::lazy_static;
+use std::error::Error as _;
+use std::io::Cursor;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(PROJECT_DIR.join(path)).unwrap()
+}
+
+fn assert_eof(err: &ktx::Error) {
+    match err.kind() {
+        ErrorKind::Io(e) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert!(err.source().is_some());
+}
+
+#[test]
+fn test_error_traits() {
+    fn is_error<E: std::error::Error + Send + Sync + 'static>() {}
+    is_error::<ktx::Error>();
+}
+
+#[tokio::test]
+async fn test_header_errors() {
+    let mut data = read("data/khr/rgb-mipmap-reference.ktx");
+    data[0] = 0;
+    let err = Decoder::new(&data[..]).read_async().await.err().unwrap();
+    assert_eq!(err.offset(), Some(0));
+    assert_eq!(err.level(), None);
+    assert!(err.to_string().starts_with("not a KTX file"), "{}", err);
+
+    let data = read("data/khr/rgb-mipmap-reference.ktx");
+    let options = DecoderOptions {
+        limits: Limits {
+            max_dimension: 32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let err = Decoder::with_options(&data[..], options)
+        .read_async()
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.to_string(),
+        "pixelWidth of 64 exceeds the limit of 32 at byte 36"
+    );
+
+    let err = Decoder::new(&data[..40]).read_async().await.err().unwrap();
+    assert_eof(&err);
+    assert_eq!(err.to_string(), "unexpected end of file at byte 0");
+}
+
+#[tokio::test]
+async fn test_truncated_level() {
+    // Level 2 is 768 bytes at byte 15436
+    let data = read("data/khr/rgb-mipmap-reference.ktx");
+    let data = &data[..15436 + 100];
+
+    let (_, stream) = Decoder::new(data).read_async().await.unwrap();
+    let err = stream.try_collect::<Frames>().await.unwrap_err();
+    assert_eof(&err);
+    assert_eq!(
+        (err.offset(), err.level(), err.layer(), err.face()),
+        (Some(15436), Some(2), Some(0), Some(0))
+    );
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of file at byte 15436 (level 2, layer 0, face 0)"
+    );
+
+    // The imageSize field of level 2
+    let (_, stream) = Decoder::new(&data[..15434]).read_async().await.unwrap();
+    let err = stream.try_collect::<Frames>().await.unwrap_err();
+    assert_eq!(
+        (err.offset(), err.level(), err.layer()),
+        (Some(15432), Some(2), None)
+    );
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of file at byte 15432 (level 2)"
+    );
+
+    let (_, stream) = Decoder::new(data).read_chunks_async(96).await.unwrap();
+    let err = stream.try_collect::<Vec<Chunk>>().await.unwrap_err();
+    assert_eof(&err);
+    // Rows of 48 bytes, two per chunk
+    assert_eq!((err.offset(), err.level()), (Some(15436 + 96), Some(2)));
+
+    let options = DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(Cursor::new(data), options);
+    let err = decoder.read_seekable_async().await.err().unwrap();
+    assert_eof(&err);
+    assert_eq!((err.offset(), err.level()), (Some(15436 + 768), Some(3)));
+}
+
+#[tokio::test]
+async fn test_truncated_cubemap_face() {
+    // Faces of 131072 bytes from byte 100
+    let data = read("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+    let offset = 100 + 3 * 131_072;
+    let (_, stream) = Decoder::new(&data[..offset + 10])
+        .read_async()
+        .await
+        .unwrap();
+    let err = stream.try_collect::<Frames>().await.unwrap_err();
+    assert_eq!(
+        (err.offset(), err.level(), err.layer(), err.face()),
+        (Some(offset as u64), Some(0), Some(0), Some(3))
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_image_size() {
+    let mut data = read("data/khr/rgb-mipmap-reference.ktx");
+    data[12356..12360].copy_from_slice(&3071_u32.to_ne_bytes());
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let err = stream.try_collect::<Frames>().await.unwrap_err();
+    match err.kind() {
+        ErrorKind::InvalidImageSize(3071, 1, 1) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!((err.offset(), err.level()), (Some(12356), Some(1)));
+}
+
+#[tokio::test]
+async fn test_reader_errors() {
+    let mut data = read("data/khr/rgb-reference.ktx");
+    let options = DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(&data[..], options.clone());
+    let err = decoder.read_async().await.err().unwrap();
+    assert!(matches!(err.kind(), ErrorKind::NeedsSeek));
+    let err = Decoder::with_options(&data[..], options)
+        .read()
+        .err()
+        .unwrap();
+    assert!(matches!(err.kind(), ErrorKind::NeedsSeek));
+
+    // Levels to generate
+    data[56..60].copy_from_slice(&0_u32.to_ne_bytes());
+    let options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        ..Default::default()
+    };
+    let err = Decoder::with_options(&data[..], options)
+        .read()
+        .err()
+        .unwrap();
+    assert!(matches!(err.kind(), ErrorKind::NeedsAsyncReader));
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "tokio02")]
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::mipmap::Filter;
+use ktx::{Decoder, DecoderOptions, FrameInfo, FrameOrder};
+use lazy_static::lazy_static;
+use std::io::Cursor;
+use tokio::fs::File;
+
+fn smallest_first() -> DecoderOptions {
+    DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    }
+}
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn key(frame: &FrameInfo) -> (u32, u32, u32) {
+    (frame.level, frame.layer, frame.face)
+}
+
+/// Frames of `frames` reordered smallest level first
+fn reversed_levels(frames: &Frames) -> Vec<(u32, u32, u32)> {
+    let mut keys: Vec<_> = frames.iter().map(|(frame, _)| key(frame)).collect();
+    keys.sort_by_key(|&(level, layer, face)| (std::cmp::Reverse(level), layer, face));
+    keys
+}
+
+#[tokio::test]
+async fn test_smallest_first() {
+    for path in [
+        "data/khr/rgb-mipmap-reference.ktx",
+        "data/khr/pattern_02_bc2.ktx",
+        "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+        "data/pvr/etc1-mipmap.ktx",
+    ]
+    .iter()
+    {
+        let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+        let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+        let largest_first: Frames = stream.try_collect().await.unwrap();
+
+        let decoder = Decoder::with_options(Cursor::new(&data[..]), smallest_first());
+        let (info, stream) = decoder.read_seekable_async().await.unwrap();
+        let smallest_first: Frames = stream.try_collect().await.unwrap();
+        assert_eq!(smallest_first.len(), largest_first.len(), "{}", path);
+        assert_eq!(smallest_first[0].0.level, info.number_of_mipmap_levels - 1);
+
+        let keys: Vec<_> = smallest_first.iter().map(|(frame, _)| key(frame)).collect();
+        assert_eq!(keys, reversed_levels(&largest_first), "{}", path);
+        for (frame, buf) in &smallest_first {
+            let (original, original_buf) = largest_first
+                .iter()
+                .find(|(x, _)| key(x) == key(frame))
+                .unwrap();
+            assert_eq!(frame.pixel_width, original.pixel_width);
+            assert_eq!(frame.pixel_height, original.pixel_height);
+            assert_eq!(buf, original_buf, "{} {:?}", path, key(frame));
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_smallest_first_file() {
+    let path = PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx");
+    let file = File::open(path).await.unwrap();
+    let (_, stream) = Decoder::with_options(file, smallest_first())
+        .read_seekable_async()
+        .await
+        .unwrap();
+    let frames: Frames = stream.try_collect().await.unwrap();
+    let sizes: Vec<u32> = frames.iter().map(|(frame, _)| frame.pixel_width).collect();
+    assert_eq!(sizes, vec![1, 2, 4, 8, 16, 32, 64]);
+}
+
+#[tokio::test]
+async fn test_largest_first_seekable() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (_, stream) = Decoder::new(Cursor::new(&data[..]))
+        .read_seekable_async()
+        .await
+        .unwrap();
+    let frames: Frames = stream.try_collect().await.unwrap();
+    let levels: Vec<u32> = frames.iter().map(|(frame, _)| frame.level).collect();
+    assert_eq!(levels, (0..7).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn test_smallest_first_needs_seek() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let decoder = Decoder::with_options(&data[..], smallest_first());
+    assert!(decoder.read_async().await.is_err());
+}
+
+#[tokio::test]
+async fn test_smallest_first_generated() {
+    let path = "data/khr/metalplate-amg-rgba8.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(Cursor::new(&data[..]), options);
+    let (info, stream) = decoder.read_seekable_async().await.unwrap();
+    let frames: Frames = stream.try_collect().await.unwrap();
+    assert_eq!(frames.len() as u32, info.number_of_mipmap_levels);
+    let levels: Vec<u32> = frames.iter().map(|(frame, _)| frame.level).collect();
+    let expected: Vec<u32> = (0..info.number_of_mipmap_levels).rev().collect();
+    assert_eq!(levels, expected);
+}
+
+#[tokio::test]
+async fn test_truncated() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let data = &data[..data.len() - 8];
+    let decoder = Decoder::with_options(Cursor::new(data), smallest_first());
+    assert!(decoder.read_seekable_async().await.is_err());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,141 @@
+//! Regression tests for inputs found by the fuzz targets in `fuzz/`
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{Decoder, DecoderOptions, FrameInfo, FrameOrder, Limits, Result};
+use lazy_static::lazy_static;
+use std::io::Cursor;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+/// Decode `data` in each of the reading modes, returning the result of
+/// reading the file order stream
+async fn decode(data: &[u8], options: DecoderOptions) -> Result<Frames> {
+    let decoder = Decoder::with_options(data, options.clone());
+    if let Ok((_, stream)) = decoder.read_chunks_async(64).await {
+        let _ = stream.try_collect::<Vec<_>>().await;
+    }
+    let seekable = DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..options.clone()
+    };
+    let decoder = Decoder::with_options(Cursor::new(data), seekable);
+    if let Ok((_, stream)) = decoder.read_seekable_async().await {
+        let _ = stream.try_collect::<Frames>().await;
+    }
+    let (info, stream) = Decoder::with_options(data, options).read_async().await?;
+    let _ = info.orientation();
+    let _ = info.key_value_data.iter().count();
+    stream.try_collect().await
+}
+
+/// Header of a 1x1 RGBA8 texture, followed by the given words
+fn header(levels: u32, layers: u32, faces: u32, rest: &[u32]) -> Vec<u8> {
+    let fields = [
+        0x1401, 1, 0x1908, 0x8058, 0x1908, 1, 1, 0, layers, faces, levels, 0,
+    ];
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&0x0403_0201_u32.to_ne_bytes());
+    for x in fields.iter().chain(rest) {
+        data.extend_from_slice(&x.to_ne_bytes());
+    }
+    data
+}
+
+const MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// A key/value pair claiming 27 bytes in 32 bytes of key/value data
+/// that end before its NUL terminator
+const KEY_WITHOUT_NUL: [u8; 96] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A, 0x01, 0x02, 0x03, 0x04,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x8D, 0x00, 0x00,
+    0x07, 0x19, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00,
+    0x1B, 0x00, 0x00, 0x00, 0x4B, 0x54, 0x58, 0x6F, 0x72, 0x69, 0x65, 0x6E, 0xBB, 0x0D, 0x0A, 0x1A,
+    0x0A, 0x01, 0x02, 0x03, 0x04, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+/// A key/value pair whose key is not UTF-8
+const KEY_NOT_UTF8: [u8; 96] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A, 0x01, 0x02, 0x03, 0x04,
+    0x01, 0x14, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x07, 0x19, 0x00, 0x00, 0x51, 0x80, 0x00, 0x00,
+    0x07, 0x19, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00,
+    0x1B, 0x00, 0x00, 0x00, 0x4B, 0x54, 0x58, 0xAB, 0xAB, 0x4B, 0x65, 0x6E, 0x74, 0x61, 0x74, 0x69,
+    0x6F, 0x6E, 0x00, 0x53, 0x3D, 0x72, 0x2C, 0x54, 0x3D, 0x64, 0x2C, 0x52, 0x3D, 0x69, 0x00, 0x00,
+];
+
+#[tokio::test]
+async fn test_invalid_key_value_data() {
+    assert!(decode(&KEY_WITHOUT_NUL, Default::default()).await.is_err());
+    assert!(decode(&KEY_NOT_UTF8, Default::default()).await.is_err());
+
+    // A pair running past the end of the key/value data
+    let mut data = KEY_NOT_UTF8.to_vec();
+    data[64..68].copy_from_slice(&0xFFFF_FFFF_u32.to_ne_bytes());
+    assert!(decode(&data, Default::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_invalid_image_size() {
+    // Not 4-byte aligned
+    let data = header(1, 0, 1, &[3, 0]);
+    assert!(decode(&data, Default::default()).await.is_err());
+    // Not a multiple of the number of layers
+    let data = header(1, 3, 1, &[8, 0, 0]);
+    assert!(decode(&data, Default::default()).await.is_err());
+    // Not a multiple of the number of faces
+    let data = header(1, 2, 6, &[8, 0, 0]);
+    assert!(decode(&data, Default::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_invalid_number_of_faces() {
+    let data = header(1, 0, 3, &[12, 0, 0, 0]);
+    assert!(decode(&data, Default::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_levels_beyond_32() {
+    let levels = 40;
+    let mut data = header(levels, 0, 1, &[]);
+    for _ in 0..levels {
+        data.extend_from_slice(&4_u32.to_ne_bytes());
+        data.extend_from_slice(&[0; 4]);
+    }
+    let options = DecoderOptions {
+        limits: Limits {
+            max_levels: u32::MAX,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let frames = decode(&data, options).await.unwrap();
+    assert_eq!(frames.len(), levels as usize);
+    let (frame, _) = &frames[levels as usize - 1];
+    assert_eq!((frame.pixel_width, frame.pixel_height), (1, 1));
+}
+
+#[tokio::test]
+async fn test_truncated_files() {
+    for dir in ["data/khr", "data/pvr"].iter() {
+        for entry in std::fs::read_dir(PROJECT_DIR.join(dir)).unwrap() {
+            let data = std::fs::read(entry.unwrap().path()).unwrap();
+            for len in (0..256).chain((256..data.len()).step_by(4093)) {
+                assert!(decode(&data[..len], Default::default()).await.is_err());
+            }
+        }
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
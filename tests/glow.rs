@@ -0,0 +1,167 @@
+//! Target and function selection of the glow uploads, without a
+//! context
+#![cfg(feature = "glow")]
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::glow::{parse_swizzle, Api, Function, ImageCall, Upload};
+use ktx::{Decoder, HeaderInfo, LevelInfo};
+use lazy_static::lazy_static;
+
+async fn read_levels(path: &str) -> (HeaderInfo, Vec<LevelInfo>) {
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let (info, stream) = Decoder::new(&data[..]).read_levels_async().await.unwrap();
+    let levels: Vec<_> = stream.try_collect().await.unwrap();
+    (info, levels.into_iter().map(|(level, _)| level).collect())
+}
+
+fn targets(calls: &[ImageCall]) -> Vec<u32> {
+    calls.iter().map(|x| x.target).collect()
+}
+
+#[tokio::test]
+async fn test_upload_cubemap() {
+    let (info, levels) = read_levels("data/khr/cubemap_yokohama_etc2_unorm.ktx").await;
+    let upload = Upload::new(&info, Api::Gles2).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_CUBE_MAP);
+    assert_eq!(upload.function, Function::CompressedTexImage2D);
+    assert_eq!(upload.internal_format, ktx::gl::COMPRESSED_RGB8_ETC2);
+    assert_eq!((upload.max_level, upload.swizzle), (None, None));
+
+    let calls = upload.calls(&levels[0]);
+    let faces: Vec<_> = (0..6)
+        .map(|x| glow::TEXTURE_CUBE_MAP_POSITIVE_X + x)
+        .collect();
+    assert_eq!(targets(&calls), faces);
+    assert_eq!(calls[1].size, [512, 512, 0]);
+    assert_eq!(
+        calls[1].range,
+        levels[0].frame_size as usize..2 * levels[0].frame_size as usize
+    );
+
+    // Cubemap arrays are uploaded by layer-face
+    let mut info = info;
+    info.number_of_array_elements = 2;
+    let upload = Upload::new(&info, Api::Gl).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_CUBE_MAP_ARRAY);
+    assert_eq!(upload.function, Function::CompressedTexImage3D);
+    let level = LevelInfo {
+        number_of_layers: 2,
+        ..levels[0].clone()
+    };
+    let calls = upload.calls(&level);
+    assert_eq!(targets(&calls), vec![glow::TEXTURE_CUBE_MAP_ARRAY]);
+    assert_eq!(calls[0].size, [512, 512, 12]);
+    assert_eq!(calls[0].range, 0..level.size());
+    assert!(Upload::new(&info, Api::Gles2).is_err());
+    assert!(Upload::new(&info, Api::Gles3).is_err());
+    let upload = Upload::new(&info, Api::Gles32).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_CUBE_MAP_ARRAY);
+}
+
+#[tokio::test]
+async fn test_upload_array() {
+    let (info, levels) = read_levels("data/khr/texturearray_etc2_unorm.ktx").await;
+    let upload = Upload::new(&info, Api::Gles3).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_2D_ARRAY);
+    assert_eq!(upload.function, Function::CompressedTexImage3D);
+    let calls = upload.calls(&levels[0]);
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].size, [256, 256, 7]);
+    assert!(Upload::new(&info, Api::Gles2).is_err());
+
+    // 1D arrays take the layers as height
+    let mut info = info;
+    info.pixel_height = 0;
+    info.gl_type = ktx::gl::UNSIGNED_BYTE;
+    let upload = Upload::new(&info, Api::Gl).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_1D_ARRAY);
+    assert_eq!(upload.function, Function::TexImage2D);
+    let level = LevelInfo {
+        pixel_height: 1,
+        ..levels[0].clone()
+    };
+    assert_eq!(upload.calls(&level)[0].size, [256, 7, 0]);
+    assert!(Upload::new(&info, Api::Gles3).is_err());
+}
+
+#[tokio::test]
+async fn test_upload_uncompressed() {
+    let (info, levels) = read_levels("data/khr/rgb-mipmap-reference.ktx").await;
+    let upload = Upload::new(&info, Api::Gl).unwrap();
+    assert_eq!(upload.target, glow::TEXTURE_2D);
+    assert_eq!(upload.function, Function::TexImage2D);
+    assert_eq!(upload.internal_format, ktx::gl::RGB8);
+    assert_eq!(
+        (upload.format, upload.gl_type),
+        (ktx::gl::RGB, ktx::gl::UNSIGNED_BYTE)
+    );
+    assert_eq!(upload.unpack_alignment, 4);
+    assert_eq!(upload.max_level, Some(6));
+    let calls: Vec<_> = levels.iter().flat_map(|x| upload.calls(x)).collect();
+    let sizes: Vec<_> = calls.iter().map(|x| (x.level, x.size)).collect();
+    assert_eq!(sizes[5], (5, [2, 2, 0]));
+
+    // ES 2.0 takes unsized formats
+    let mut info = info;
+    info.gl_type = ktx::gl::HALF_FLOAT;
+    let upload = Upload::new(&info, Api::Gles2).unwrap();
+    assert_eq!(upload.internal_format, ktx::gl::RGB);
+    assert_eq!(upload.gl_type, ktx::gl::HALF_FLOAT_OES);
+    let upload = Upload::new(&info, Api::Gles3).unwrap();
+    assert_eq!(upload.internal_format, ktx::gl::RGB8);
+    assert_eq!(upload.gl_type, ktx::gl::HALF_FLOAT);
+
+    // 1D and 3D textures
+    info.pixel_height = 0;
+    let upload = Upload::new(&info, Api::Gl).unwrap();
+    assert_eq!(
+        (upload.target, upload.function),
+        (glow::TEXTURE_1D, Function::TexImage1D)
+    );
+    assert!(Upload::new(&info, Api::Gles3).is_err());
+    info.pixel_height = 64;
+    info.pixel_depth = 4;
+    let upload = Upload::new(&info, Api::Gles3).unwrap();
+    assert_eq!(
+        (upload.target, upload.function),
+        (glow::TEXTURE_3D, Function::TexImage3D)
+    );
+    let level = LevelInfo {
+        pixel_depth: 4,
+        ..levels[0].clone()
+    };
+    assert_eq!(upload.calls(&level)[0].size, [64, 64, 4]);
+
+    // No 3D cubemaps
+    info.number_of_faces = 6;
+    assert!(Upload::new(&info, Api::Gl).is_err());
+}
+
+#[tokio::test]
+async fn test_upload_swizzle() {
+    let (mut info, _) = read_levels("data/khr/rgb-mipmap-reference.ktx").await;
+    info.key_value_data.push("KTXswizzle", b"bgr1\0");
+    let upload = Upload::new(&info, Api::Gles3).unwrap();
+    let swizzle = [glow::BLUE, glow::GREEN, glow::RED, glow::ONE];
+    assert_eq!(upload.swizzle, Some(swizzle));
+    assert_eq!(Upload::new(&info, Api::Gles2).unwrap().swizzle, None);
+
+    assert_eq!(
+        parse_swizzle(b"rrr0"),
+        Some([glow::RED, glow::RED, glow::RED, glow::ZERO])
+    );
+    assert_eq!(parse_swizzle(b"rgba\0"), None);
+    assert_eq!(parse_swizzle(b"rgbx"), None);
+    assert_eq!(parse_swizzle(b"rg"), None);
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
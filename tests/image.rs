@@ -0,0 +1,85 @@
+#![cfg(all(feature = "image", feature = "tokio02"))]
+
+extern crate ktx_async as ktx;
+
+use futures_util::stream::StreamExt as _;
+use image::{ColorType, DynamicImage, ImageDecoder as _};
+use ktx::image::{frame_to_image, KtxDecoder};
+use ktx::Decoder;
+use lazy_static::lazy_static;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+#[test]
+fn test_image_decoder_rgba() {
+    let file = std::fs::File::open(PROJECT_DIR.join("data/khr/rgba-reference.ktx")).unwrap();
+    let decoder = KtxDecoder::new(file).unwrap();
+    assert_eq!(decoder.dimensions(), (128, 128));
+    assert_eq!(decoder.color_type(), ColorType::Rgba8);
+    let image = DynamicImage::from_decoder(decoder).unwrap();
+    assert_eq!(image.as_bytes().len(), 128 * 128 * 4);
+}
+
+#[test]
+fn test_image_decoder_rgb() {
+    let file = std::fs::File::open(PROJECT_DIR.join("data/khr/rgb-reference.ktx")).unwrap();
+    let decoder = KtxDecoder::new(file).unwrap();
+    assert_eq!(decoder.color_type(), ColorType::Rgb8);
+}
+
+#[test]
+fn test_image_decoder_luminance() {
+    let path = PROJECT_DIR.join("data/khr/luminance-reference-metadata.ktx");
+    let decoder = KtxDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+    assert_eq!(decoder.dimensions(), (200, 100));
+    assert_eq!(decoder.color_type(), ColorType::L8);
+}
+
+#[test]
+fn test_image_decoder_pvrtc() {
+    let path = PROJECT_DIR.join("data/pvr/array-pvrtc-mipmap.ktx");
+    let decoder = KtxDecoder::new(std::fs::File::open(path).unwrap()).unwrap();
+    assert_eq!(decoder.dimensions(), (256, 256));
+    assert_eq!(decoder.color_type(), ColorType::Rgba8);
+}
+
+#[test]
+fn test_image_decoder_unsupported() {
+    // No software decoder for ETC1 yet
+    let path = PROJECT_DIR.join("data/khr/etc1.ktx");
+    assert!(KtxDecoder::new(std::fs::File::open(path).unwrap()).is_err());
+
+    // Not a KTX file
+    assert!(KtxDecoder::new(&[0_u8; 64][..]).is_err());
+}
+
+#[tokio::test]
+async fn test_frame_to_png() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let reader = BufReader::new(file);
+    let decoder = Decoder::new(reader);
+    let (info, mut stream) = decoder.read_async().await.unwrap();
+
+    let out_dir = std::env::temp_dir().join("ktx-async-test-frame-to-png");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    while let Some((frame, buf)) = stream.next().await.map(|r| r.unwrap()) {
+        let image = frame_to_image(&info, &frame, &buf).unwrap();
+        assert_eq!(image.width(), frame.pixel_width);
+        assert_eq!(image.height(), frame.pixel_height);
+
+        let path = out_dir.join(format!("level{}.png", frame.level));
+        image.save(&path).unwrap();
+        let saved = image::open(&path).unwrap();
+        assert_eq!(saved.to_rgb8(), image.to_rgb8());
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
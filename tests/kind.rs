@@ -0,0 +1,109 @@
+extern crate ktx_async as ktx;
+
+mod common;
+
+use ktx::{ErrorKind, HeaderInfo, TextureKind};
+use lazy_static::lazy_static;
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(PROJECT_DIR.join(path)).unwrap()
+}
+
+fn invalid_at(info: &HeaderInfo) -> u64 {
+    let e = info.kind().unwrap_err();
+    match e.kind() {
+        ErrorKind::InvalidDimensions(_) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    e.offset().unwrap()
+}
+
+#[tokio::test]
+async fn test_kind_of_files() {
+    for (path, kind) in [
+        ("data/khr/rgb-mipmap-reference.ktx", TextureKind::Texture2D),
+        (
+            "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+            TextureKind::Cube,
+        ),
+        (
+            "data/khr/texturearray_etc2_unorm.ktx",
+            TextureKind::Texture2DArray,
+        ),
+    ]
+    .iter()
+    {
+        let (info, _) = common::decode(&read(path)).await;
+        assert_eq!(info.kind().unwrap(), *kind, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_kind_of_dimensions() {
+    let (mut info, _) = common::decode(&read("data/khr/rgb-mipmap-reference.ktx")).await;
+    info.pixel_height = 0;
+    assert_eq!(info.kind().unwrap(), TextureKind::Texture1D);
+    info.number_of_array_elements = 4;
+    assert_eq!(info.kind().unwrap(), TextureKind::Texture1DArray);
+    assert!(info.kind().unwrap().is_array());
+    assert_eq!(info.kind().unwrap().dimensions(), 1);
+
+    info.number_of_array_elements = 0;
+    info.pixel_height = 64;
+    info.pixel_depth = 4;
+    assert_eq!(info.kind().unwrap(), TextureKind::Texture3D);
+    assert_eq!(info.kind().unwrap().dimensions(), 3);
+
+    info.pixel_depth = 0;
+    info.number_of_faces = 6;
+    info.number_of_array_elements = 2;
+    assert_eq!(info.kind().unwrap(), TextureKind::CubeArray);
+    assert!(info.kind().unwrap().is_cube());
+
+    // No 3D cubemaps, non-square faces, 3D arrays or 1D depths
+    info.pixel_depth = 4;
+    assert_eq!(invalid_at(&info), 44);
+    info.pixel_depth = 0;
+    info.pixel_height = 32;
+    assert_eq!(invalid_at(&info), 40);
+    info.number_of_faces = 1;
+    info.pixel_depth = 4;
+    assert_eq!(invalid_at(&info), 44);
+    info.number_of_array_elements = 0;
+    info.pixel_height = 0;
+    assert_eq!(invalid_at(&info), 44);
+}
+
+#[tokio::test]
+async fn test_kind_of_invalid_files() {
+    // A cubemap and an array of 3D textures with a pixelDepth of 1,
+    // whose frames have the same size as without it
+    for path in [
+        "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+        "data/khr/texturearray_etc2_unorm.ktx",
+    ]
+    .iter()
+    {
+        let mut data = read(path);
+        data[44..48].copy_from_slice(&1_u32.to_ne_bytes());
+
+        // Decoded, but without a kind
+        let (info, frames) = common::decode(&data).await;
+        assert_eq!(invalid_at(&info), 44, "{}", path);
+        assert!(!frames.is_empty(), "{}", path);
+        assert!(ktx::parse::read(&data, &Default::default()).is_ok());
+
+        // Reported by `validate`
+        let issues = ktx::validate::validate(&data);
+        assert!(issues.iter().any(|x| x.offset == 44), "{}", path);
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,109 @@
+extern crate ktx_async as ktx;
+
+use ktx::{gl, ktx2, vk, ErrorKind, Limits};
+
+/// Build a KTX 2.0 file with one level and one key/value pair
+fn ktx2_file() -> Vec<u8> {
+    let le = |x: u32| x.to_le_bytes().to_vec();
+    let le64 = |x: u64| x.to_le_bytes().to_vec();
+
+    let mut kvd = vec![];
+    kvd.extend(le(18));
+    kvd.extend(b"KTXorientation\0rd\0");
+    kvd.extend([0, 0]);
+
+    let dfd_offset = 80 + 24;
+    let dfd = le(4);
+    let kvd_offset = dfd_offset + dfd.len() as u32;
+    let data_offset = u64::from(kvd_offset) + kvd.len() as u64;
+
+    let mut v = ktx2::MAGIC.to_vec();
+    for x in [vk::R8G8B8A8_UNORM, 1, 4, 4, 0, 0, 1, 1, 0].iter() {
+        v.extend(le(*x));
+    }
+    v.extend(le(dfd_offset));
+    v.extend(le(dfd.len() as u32));
+    v.extend(le(kvd_offset));
+    v.extend(le(kvd.len() as u32));
+    v.extend(le64(0));
+    v.extend(le64(0));
+    v.extend(le64(data_offset));
+    v.extend(le64(64));
+    v.extend(le64(64));
+    v.extend(dfd);
+    v.extend(kvd);
+    v.extend(vec![0xFF; 64]);
+    v
+}
+
+#[tokio::test]
+async fn test_ktx2_header() {
+    let data = ktx2_file();
+    let info = ktx2::read_header_async(&data[..]).await.unwrap();
+    assert_eq!(info.vk_format, vk::R8G8B8A8_UNORM);
+    assert_eq!(vk::name(info.vk_format), Some("VK_FORMAT_R8G8B8A8_UNORM"));
+    assert_eq!(info.pixel_width, 4);
+    assert_eq!(info.pixel_height, 4);
+    assert_eq!(info.face_count, 1);
+    assert_eq!(info.level_count, 1);
+    assert_eq!(
+        ktx2::supercompression_scheme_name(info.supercompression_scheme),
+        Some("None")
+    );
+    assert_eq!(info.levels.len(), 1);
+    assert_eq!(info.levels[0].byte_offset, 132);
+    assert_eq!(info.levels[0].byte_length, 64);
+
+    let entries: Vec<_> = info.key_value_data.iter().collect();
+    assert_eq!(entries, vec![("KTXorientation", &b"rd\0"[..])]);
+}
+
+#[tokio::test]
+async fn test_ktx2_rejects_ktx1() {
+    let mut data = ktx2_file();
+    data[5] = b'1';
+    data[6] = b'1';
+    assert!(ktx2::read_header_async(&data[..]).await.is_err());
+}
+
+#[tokio::test]
+async fn test_ktx2_key_value_bounds() {
+    // kvdByteLength is checked before allocating
+    let mut data = ktx2_file();
+    data[60..64].copy_from_slice(&0xFFFF_FFFC_u32.to_le_bytes());
+    let e = ktx2::read_header_async(&data[..]).await.unwrap_err();
+    match e.kind() {
+        ErrorKind::LimitExceeded("kvdByteLength", 0xFFFF_FFFC, _) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!(e.offset(), Some(60));
+
+    // ... and must end before the levels
+    let limits = Limits {
+        max_key_value_bytes: u32::MAX,
+        ..Default::default()
+    };
+    let e = ktx2::read_header_with_limits_async(&data[..], &limits)
+        .await
+        .unwrap_err();
+    match e.kind() {
+        ErrorKind::InvalidKeyValueData => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!(e.offset(), Some(56));
+}
+
+#[test]
+fn test_enum_names() {
+    assert_eq!(gl::name(gl::RGBA8), Some("GL_RGBA8"));
+    assert_eq!(
+        gl::name(gl::COMPRESSED_RGB8_ETC2),
+        Some("GL_COMPRESSED_RGB8_ETC2")
+    );
+    assert_eq!(gl::name(0xFFFF_FFFF), None);
+    assert_eq!(
+        vk::name(vk::BC1_RGB_UNORM_BLOCK),
+        Some("VK_FORMAT_BC1_RGB_UNORM_BLOCK")
+    );
+    assert_eq!(vk::name(0xFFFF), None);
+}
@@ -0,0 +1,176 @@
+extern crate ktx_async as ktx;
+
+use futures_core::stream::Stream;
+use futures_util::stream::StreamExt as _;
+use ktx::{mipmap::Filter, Decoder, DecoderOptions, FrameInfo, FrameOrder, Result};
+use lazy_static::lazy_static;
+use std::io::Cursor;
+
+type Frame = (FrameInfo, Vec<u8>);
+
+/// Collect the frames of a stream and its error, if any
+async fn collect(
+    stream: impl Stream<Item = Result<Frame>> + Unpin,
+) -> (Vec<Frame>, Option<ktx::Error>) {
+    let items: Vec<_> = stream.collect().await;
+    let mut frames = vec![];
+    let mut error = None;
+    for item in items {
+        assert!(error.is_none(), "the error is the last item");
+        match item {
+            Ok(frame) => frames.push(frame),
+            Err(e) => error = Some(e),
+        }
+    }
+    (frames, error)
+}
+
+fn keys(frames: &[Frame]) -> Vec<(u32, u32, u32)> {
+    frames
+        .iter()
+        .map(|(frame, _)| (frame.level, frame.layer, frame.face))
+        .collect()
+}
+
+fn lenient(frame_order: FrameOrder) -> DecoderOptions {
+    DecoderOptions {
+        frame_order,
+        lenient: true,
+        ..Default::default()
+    }
+}
+
+/// `rgb-mipmap-reference.ktx` cut in level 2 (768 bytes at byte 15436)
+fn truncated_mipmaps() -> Vec<u8> {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx")).unwrap();
+    data[..15436 + 100].to_vec()
+}
+
+#[tokio::test]
+async fn test_truncated_file_order() {
+    let data = truncated_mipmaps();
+    let decoder = Decoder::with_options(&data[..], lenient(FrameOrder::LargestFirst));
+    let (_, stream) = decoder.read_async().await.unwrap();
+    let (frames, error) = collect(stream).await;
+    assert_eq!(keys(&frames), [(0, 0, 0), (1, 0, 0)]);
+    assert_eq!(frames[1].1.len(), 3072);
+    let error = error.unwrap();
+    assert!(error.is_truncation());
+    assert_eq!((error.offset(), error.level()), (Some(15436), Some(2)));
+}
+
+#[tokio::test]
+async fn test_truncated_smallest_first() {
+    let data = truncated_mipmaps();
+    let options = DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(Cursor::new(&data[..]), options);
+    assert!(decoder.read_seekable_async().await.is_err());
+
+    let decoder = Decoder::with_options(Cursor::new(&data[..]), lenient(FrameOrder::SmallestFirst));
+    let (info, stream) = decoder.read_seekable_async().await.unwrap();
+    assert_eq!(info.number_of_mipmap_levels, 7);
+    let (frames, error) = collect(stream).await;
+    assert_eq!(keys(&frames), [(1, 0, 0), (0, 0, 0)]);
+    let error = error.unwrap();
+    assert!(error.is_truncation());
+    assert_eq!(
+        (error.offset(), error.level(), error.face()),
+        (Some(15436), Some(2), Some(0))
+    );
+}
+
+#[tokio::test]
+async fn test_truncated_cubemap_face() {
+    // Faces of 131072 bytes from byte 100, cut in face 3
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/cubemap_yokohama_etc2_unorm.ktx")).unwrap();
+    let data = &data[..100 + 3 * 131_072 + 10];
+
+    let decoder = Decoder::with_options(Cursor::new(data), lenient(FrameOrder::SmallestFirst));
+    let (_, stream) = decoder.read_seekable_async().await.unwrap();
+    let (frames, error) = collect(stream).await;
+    assert_eq!(keys(&frames), [(0, 0, 0), (0, 0, 1), (0, 0, 2)]);
+    let error = error.unwrap();
+    assert_eq!(
+        (error.offset(), error.level(), error.face()),
+        (Some(100 + 3 * 131_072), Some(0), Some(3))
+    );
+}
+
+#[tokio::test]
+async fn test_truncated_generated_mipmaps() {
+    // Two layers of 4x4 RGBA8 without mipmaps, cut in layer 1
+    let mut data = vec![
+        0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+    ];
+    let fields = [
+        0x0403_0201_u32,
+        0x1401,
+        1,
+        0x1908,
+        0x8058,
+        0x1908,
+        4,
+        4,
+        0,
+        2,
+        1,
+        0,
+        0,
+        128,
+    ];
+    for x in fields.iter() {
+        data.extend_from_slice(&x.to_ne_bytes());
+    }
+    data.extend_from_slice(&[0x80; 64 + 10]);
+
+    let generate = |lenient| DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        lenient,
+        ..Default::default()
+    };
+    let (_, stream) = Decoder::with_options(&data[..], generate(false))
+        .read_async()
+        .await
+        .unwrap();
+    let (frames, error) = collect(stream).await;
+    assert!(frames.is_empty());
+    assert!(error.unwrap().is_truncation());
+
+    let (_, stream) = Decoder::with_options(&data[..], generate(true))
+        .read_async()
+        .await
+        .unwrap();
+    let (frames, error) = collect(stream).await;
+    assert_eq!(keys(&frames), [(0, 0, 0)]);
+    let error = error.unwrap();
+    assert_eq!((error.offset(), error.layer()), (Some(132), Some(1)));
+}
+
+#[tokio::test]
+async fn test_complete_files() {
+    for path in [
+        "data/khr/rgb-mipmap-reference.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+    ]
+    .iter()
+    {
+        let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+        let decoder =
+            Decoder::with_options(Cursor::new(&data[..]), lenient(FrameOrder::SmallestFirst));
+        let (_, stream) = decoder.read_seekable_async().await.unwrap();
+        let (_, error) = collect(stream).await;
+        assert!(error.is_none(), "{}", path);
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,151 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::mipmap::Filter;
+use ktx::{Decoder, DecoderOptions, FrameInfo, LevelInfo};
+use lazy_static::lazy_static;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn key(frame: &FrameInfo) -> (u32, u32, u32, u32, u32, u32) {
+    (
+        frame.level,
+        frame.layer,
+        frame.face,
+        frame.pixel_width,
+        frame.pixel_height,
+        frame.pixel_depth,
+    )
+}
+
+async fn read_frames(data: &[u8], options: DecoderOptions) -> Frames {
+    let (_, stream) = Decoder::with_options(data, options)
+        .read_async()
+        .await
+        .unwrap();
+    stream.try_collect().await.unwrap()
+}
+
+/// Levels split back into frames
+fn split(levels: &[(LevelInfo, Vec<u8>)]) -> Frames {
+    let mut frames = vec![];
+    for (level, buf) in levels {
+        assert_eq!(buf.len(), level.size());
+        for (frame, range) in level.frames() {
+            frames.push((frame, buf[range].to_vec()));
+        }
+    }
+    frames
+}
+
+fn assert_same(frames: &Frames, expected: &Frames, path: &str) {
+    let keys: Vec<_> = frames.iter().map(|(frame, _)| key(frame)).collect();
+    let expected_keys: Vec<_> = expected.iter().map(|(frame, _)| key(frame)).collect();
+    assert_eq!(keys, expected_keys, "{}", path);
+    for ((_, buf), (_, expected)) in frames.iter().zip(expected) {
+        assert!(buf == expected, "{}", path);
+    }
+}
+
+#[tokio::test]
+async fn test_read_levels() {
+    for path in [
+        "data/khr/rgb-mipmap-reference.ktx",
+        "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+        "data/khr/pattern_02_bc2.ktx",
+        "data/khr/texturearray_etc2_unorm.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+    ]
+    .iter()
+    {
+        let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+        let expected = read_frames(&data, Default::default()).await;
+
+        let (info, stream) = Decoder::new(&data[..]).read_levels_async().await.unwrap();
+        let levels: Vec<_> = stream.try_collect().await.unwrap();
+        assert_eq!(
+            levels.len(),
+            info.number_of_mipmap_levels as usize,
+            "{}",
+            path
+        );
+        for (i, (level, _)) in levels.iter().enumerate() {
+            assert_eq!(level.level, i as u32);
+            assert_eq!(level.number_of_layers, info.number_of_array_elements.max(1));
+            assert_eq!(level.number_of_faces, info.number_of_faces.max(1));
+        }
+        assert_same(&split(&levels), &expected, path);
+
+        let (_, texture) = Decoder::new(&data[..]).read_all_async().await.unwrap();
+        assert_eq!(texture.levels.len(), levels.len());
+        for ((level, buf), (_, range)) in levels.iter().zip(&texture.levels) {
+            assert_eq!(texture.level(level.level), Some(&buf[..]));
+            assert_eq!(range.len(), buf.len());
+        }
+        assert_eq!(texture.levels.last().unwrap().1.end, texture.data.len());
+        let frames: Frames = texture
+            .frames
+            .iter()
+            .map(|(frame, range)| (frame.clone(), texture.data[range.clone()].to_vec()))
+            .collect();
+        assert_same(&frames, &expected, path);
+        let (frame, buf) = &expected[expected.len() - 1];
+        let found = texture.frame(frame.level, frame.layer, frame.face);
+        assert_eq!(found, Some(&buf[..]));
+        assert_eq!(texture.frame(info.number_of_mipmap_levels, 0, 0), None);
+    }
+}
+
+#[tokio::test]
+async fn test_read_levels_generated() {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/metalplate-amg-rgba8.ktx")).unwrap();
+    let options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        ..Default::default()
+    };
+    let expected = read_frames(&data, options.clone()).await;
+
+    let decoder = Decoder::with_options(&data[..], options.clone());
+    let (info, stream) = decoder.read_levels_async().await.unwrap();
+    let levels: Vec<_> = stream.try_collect().await.unwrap();
+    assert_eq!(levels.len(), info.number_of_mipmap_levels as usize);
+    assert_same(&split(&levels), &expected, "generated");
+
+    let decoder = Decoder::with_options(&data[..], options);
+    let (_, texture) = decoder.read_all_async().await.unwrap();
+    assert_eq!(texture.frames.len(), expected.len());
+    assert_eq!(texture.data.len(), expected.iter().map(|x| x.1.len()).sum());
+}
+
+#[tokio::test]
+async fn test_read_levels_truncated() {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx")).unwrap();
+    let data = &data[..15500];
+    let (_, mut stream) = Decoder::new(data).read_levels_async().await.unwrap();
+    let mut levels = vec![];
+    let e = loop {
+        match stream.try_next().await {
+            Ok(Some((level, _))) => levels.push(level.level),
+            Ok(None) => panic!("no error"),
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(levels, vec![0, 1]);
+    assert!(e.is_truncation());
+    assert_eq!(
+        (e.offset(), e.level(), e.layer()),
+        (Some(15436), Some(2), Some(0))
+    );
+
+    let e = Decoder::new(data).read_all_async().await.unwrap_err();
+    assert!(e.is_truncation());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,164 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{Decoder, DecoderOptions, ErrorKind, FrameInfo, Limits};
+use lazy_static::lazy_static;
+use std::io::Cursor;
+
+const MAGIC: [u8; 12] = [
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+];
+
+/// Header of an RGBA8 texture, followed by the given words
+fn header(fields: [u32; 12], rest: &[u32]) -> Vec<u8> {
+    let mut data = MAGIC.to_vec();
+    data.extend_from_slice(&0x0403_0201_u32.to_ne_bytes());
+    for x in fields.iter().chain(rest) {
+        data.extend_from_slice(&x.to_ne_bytes());
+    }
+    data
+}
+
+/// glType, glTypeSize, glFormat, glInternalFormat, glBaseInternalFormat,
+/// width, height, depth, layers, faces, levels, bytesOfKeyValueData
+fn rgba8(width: u32, height: u32, layers: u32, levels: u32, kv: u32) -> [u32; 12] {
+    [
+        0x1401, 1, 0x1908, 0x8058, 0x1908, width, height, 0, layers, 1, levels, kv,
+    ]
+}
+
+fn assert_limit(err: &ktx::Error, name: &str) {
+    match err.kind() {
+        ErrorKind::LimitExceeded(what, value, limit) => {
+            assert_eq!(*what, name);
+            assert!(value > limit);
+        }
+        x => panic!("unexpected error {:?}", x),
+    }
+}
+
+#[tokio::test]
+async fn test_header_limits() {
+    let cases = [
+        (rgba8(1 << 20, 1, 0, 1, 0), "pixelWidth"),
+        (rgba8(1, 1 << 20, 0, 1, 0), "pixelHeight"),
+        (rgba8(1, 1, 1 << 20, 1, 0), "numberOfArrayElements"),
+        (rgba8(1, 1, 0, 100, 0), "numberOfMipmapLevels"),
+        (rgba8(1, 1, 0, 1, 0xFFFF_FFF0), "bytesOfKeyValueData"),
+        (rgba8(1 << 16, 1 << 16, 1 << 10, 1, 0), "total frame size"),
+    ];
+    for (fields, name) in cases.iter() {
+        let data = header(*fields, &[]);
+        let err = Decoder::new(&data[..]).read_async().await.err().unwrap();
+        assert_limit(&err, name);
+    }
+}
+
+#[tokio::test]
+async fn test_image_size_limit() {
+    // A cubemap of an unknown compressed format claiming 6 faces of
+    // almost 4 GiB each
+    let mut fields = rgba8(1, 1, 0, 1, 0);
+    fields[0] = 0;
+    fields[2] = 0;
+    fields[3] = 0x1234;
+    fields[9] = 6;
+    let data = header(fields, &[0xFFFF_FFFC]);
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let err = stream.try_collect::<Vec<_>>().await.unwrap_err();
+    assert_limit(&err, "total frame size");
+
+    let (_, stream) = Decoder::new(&data[..])
+        .read_chunks_async(1024)
+        .await
+        .unwrap();
+    let err = stream.try_collect::<Vec<_>>().await.unwrap_err();
+    assert_limit(&err, "frame size");
+
+    // A single image of almost 4 GiB is over the default limits too
+    fields[9] = 1;
+    let data = header(fields, &[0xFFFF_FFFC]);
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let err = stream.try_collect::<Vec<_>>().await.unwrap_err();
+    assert_limit(&err, "total frame size");
+
+    // The imageSize fields of known formats must match the images
+    let data = header(rgba8(2, 2, 0, 1, 0), &[0xFFFF_FFFC]);
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let err = stream.try_collect::<Vec<_>>().await.unwrap_err();
+    match err.kind() {
+        ErrorKind::MismatchedImageSize(16, 0xFFFF_FFFC) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!(err.offset(), Some(64));
+}
+
+#[tokio::test]
+async fn test_chunks_over_total_limit() {
+    // A 16384x16384 RGBA8 texture, with the data of its first row
+    let mut data = header(rgba8(16384, 16384, 0, 1, 0), &[1 << 30]);
+    data.resize(data.len() + 16384 * 4, 0);
+    let options = || DecoderOptions {
+        limits: Limits {
+            max_total_bytes: 1 << 20,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(&data[..], options());
+    assert_limit(
+        &decoder.read_async().await.err().unwrap(),
+        "total frame size",
+    );
+
+    // Chunks are bounded by their own size
+    let decoder = Decoder::with_options(&data[..], options());
+    let (_, mut stream) = decoder.read_chunks_async(1 << 16).await.unwrap();
+    let (_, rows, buf) = stream.try_next().await.unwrap().unwrap();
+    assert_eq!((rows, buf.len()), (0..1, 1 << 16));
+    assert!(stream.try_next().await.unwrap_err().is_truncation());
+}
+
+#[tokio::test]
+async fn test_custom_limits() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let decode = |limits: Limits| {
+        let options = DecoderOptions {
+            limits,
+            ..Default::default()
+        };
+        Decoder::with_options(Cursor::new(data.clone()), options).read_async()
+    };
+
+    let limits = Limits {
+        max_levels: 3,
+        ..Default::default()
+    };
+    assert_limit(&decode(limits).await.err().unwrap(), "numberOfMipmapLevels");
+
+    let limits = Limits {
+        max_dimension: 32,
+        ..Default::default()
+    };
+    assert_limit(&decode(limits).await.err().unwrap(), "pixelWidth");
+
+    let limits = Limits {
+        max_total_bytes: 1000,
+        ..Default::default()
+    };
+    assert_limit(&decode(limits).await.err().unwrap(), "total frame size");
+
+    let (_, stream) = decode(Limits::default()).await.unwrap();
+    let frames: Vec<(FrameInfo, Vec<u8>)> = stream.try_collect().await.unwrap();
+    assert_eq!(frames.len(), 7);
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
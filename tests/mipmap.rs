@@ -0,0 +1,146 @@
+#![cfg(feature = "tokio02")]
+
+extern crate ktx_async as ktx;
+
+mod common;
+
+use common::{frame, header};
+use futures_util::stream::TryStreamExt as _;
+use ktx::mipmap::{self, Filter};
+use ktx::{gl, Decoder, DecoderOptions, FrameInfo};
+use lazy_static::lazy_static;
+use tokio::fs::File;
+use tokio::io::BufReader;
+
+#[tokio::test]
+async fn test_zero_levels_rejected_by_default() {
+    let path = "data/khr/metalplate-amg-rgba8.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let decoder = Decoder::new(BufReader::new(file));
+    assert!(decoder.read_async().await.is_err());
+}
+
+#[tokio::test]
+async fn test_generate_mipmaps() {
+    let path = "data/khr/metalplate-amg-rgba8.ktx";
+    let file = File::open(PROJECT_DIR.join(path)).await.unwrap();
+    let options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        ..Default::default()
+    };
+    let decoder = Decoder::with_options(BufReader::new(file), options);
+    let (info, stream) = decoder.read_async().await.unwrap();
+    let frames: Vec<(FrameInfo, Vec<u8>)> = stream.try_collect().await.unwrap();
+
+    let nlevels = mipmap::level_count(info.pixel_width, info.pixel_height, 1);
+    assert_eq!(info.number_of_mipmap_levels, nlevels);
+    assert_eq!(frames.len(), nlevels as usize);
+    for (level, (frame, buf)) in frames.iter().enumerate() {
+        let (w, h, _) = info.mipmap_size(level as u32);
+        assert_eq!(frame.level, level as u32);
+        assert_eq!((frame.pixel_width, frame.pixel_height), (w, h));
+        assert_eq!(buf.len(), (w * h * 4) as usize);
+    }
+    assert_eq!(
+        (
+            frames[nlevels as usize - 1].0.pixel_width,
+            frames[nlevels as usize - 1].0.pixel_height
+        ),
+        (1, 1)
+    );
+}
+
+#[tokio::test]
+async fn test_generate_mipmaps_keeps_existing_levels() {
+    let path = "data/khr/rgb-mipmap-reference.ktx";
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    let options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Kaiser),
+        ..Default::default()
+    };
+    let (info, stream) = Decoder::with_options(&data[..], options)
+        .read_async()
+        .await
+        .unwrap();
+    let frames: Vec<(FrameInfo, Vec<u8>)> = stream.try_collect().await.unwrap();
+    assert_eq!(info.number_of_mipmap_levels, 7);
+    assert_eq!(frames.len(), 7);
+}
+
+#[test]
+fn test_level_count() {
+    assert_eq!(mipmap::level_count(1, 1, 1), 1);
+    assert_eq!(mipmap::level_count(64, 64, 1), 7);
+    assert_eq!(mipmap::level_count(270, 1, 1), 9);
+    assert_eq!(mipmap::level_count(3, 5, 9), 4);
+}
+
+#[test]
+fn test_box_npot() {
+    // 5 pixels become 2, each covering 2.5 source pixels
+    let pixels: Vec<f32> = [0.0, 1.0, 2.0, 3.0, 4.0]
+        .iter()
+        .flat_map(|&x| [x, x, x, 1.0])
+        .collect();
+    let (out, size) = mipmap::downsample(&pixels, (5, 1, 1), Filter::Box);
+    assert_eq!(size, (2, 1, 1));
+    let expected = [(0.0 + 1.0 + 1.0) / 2.5, (1.0 + 3.0 + 4.0) / 2.5];
+    for (px, e) in out.chunks_exact(4).zip(expected.iter()) {
+        assert!((px[0] - e).abs() < 1e-5, "{} != {}", px[0], e);
+        assert_eq!(px[3], 1.0);
+    }
+
+    // 3x3 becomes 1x1, the average of all pixels
+    let pixels: Vec<f32> = (0..9).flat_map(|x| [x as f32, 0.0, 0.0, 0.0]).collect();
+    let (out, size) = mipmap::downsample(&pixels, (3, 3, 1), Filter::Box);
+    assert_eq!(size, (1, 1, 1));
+    assert!((out[0] - 4.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_windowed_sinc_preserves_constant() {
+    let pixels: Vec<f32> = (0..7 * 6).flat_map(|_| [0.25, 0.5, 0.75, 1.0]).collect();
+    for filter in [Filter::Lanczos3, Filter::Kaiser].iter() {
+        let (out, size) = mipmap::downsample(&pixels, (7, 6, 1), *filter);
+        assert_eq!(size, (3, 3, 1));
+        for px in out.chunks_exact(4) {
+            for (c, e) in px.iter().zip([0.25, 0.5, 0.75, 1.0].iter()) {
+                assert!((c - e).abs() < 1e-5);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_srgb_is_filtered_in_linear_space() {
+    // A black and a white pixel
+    let buf = [0, 0, 0, 255, 255, 255, 0, 0];
+    let srgb = mipmap::generate(
+        &header(gl::SRGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        &frame(2, 1),
+        &buf,
+        Filter::Box,
+    )
+    .unwrap();
+    assert_eq!(srgb.len(), 1);
+    assert_eq!((srgb[0].0.level, srgb[0].0.pixel_width), (1, 1));
+    assert_eq!(&srgb[0].1[..3], &[188, 188, 188]);
+
+    let linear = mipmap::generate(
+        &header(gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE),
+        &frame(2, 1),
+        &buf,
+        Filter::Box,
+    )
+    .unwrap();
+    assert_eq!(&linear[0].1[..3], &[128, 128, 128]);
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,218 @@
+extern crate ktx_async as ktx;
+
+mod common;
+
+use common::{frame, header};
+use ktx::orientation::{reorient, Orientation};
+use ktx::{gl, ErrorKind};
+use lazy_static::lazy_static;
+
+const DOWN: Orientation = Orientation {
+    right: true,
+    down: true,
+    into: true,
+};
+const UP: Orientation = Orientation {
+    right: true,
+    down: false,
+    into: true,
+};
+const LEFT_UP: Orientation = Orientation {
+    right: false,
+    down: false,
+    into: true,
+};
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(PROJECT_DIR.join(path)).unwrap()
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(Orientation::parse(b"S=r,T=d\0"), Some(DOWN));
+    assert_eq!(Orientation::parse(b"S=r,T=u"), Some(UP));
+    assert_eq!(Orientation::parse(b"lu\0"), Some(LEFT_UP));
+    let out = Orientation {
+        into: false,
+        ..DOWN
+    };
+    assert_eq!(Orientation::parse(b"S=r,T=d,R=o"), Some(out));
+    assert_eq!(Orientation::parse(b"rdo"), Some(out));
+    assert_eq!(Orientation::parse(b""), Some(DOWN));
+    assert_eq!(Orientation::parse(b"T=d,S=r"), None);
+    assert_eq!(Orientation::parse(b"S=x"), None);
+    assert_eq!(Orientation::parse(b"rdoo"), None);
+}
+
+#[tokio::test]
+async fn test_header_orientation() {
+    let (info, _) = common::decode(&read("data/khr/orient-up-metadata.ktx")).await;
+    assert_eq!(info.orientation(), UP);
+    let (info, _) = common::decode(&read("data/khr/orient-up.ktx")).await;
+    assert_eq!(info.orientation(), DOWN);
+}
+
+#[tokio::test]
+async fn test_uncompressed() {
+    let (up_info, up) = common::decode(&read("data/khr/orient-up-metadata.ktx")).await;
+    let (down_info, down) = common::decode(&read("data/khr/orient-down-metadata.ktx")).await;
+
+    let flipped = reorient(&up_info, &up[0].0, &up[0].1, DOWN).unwrap();
+    assert_eq!(flipped, down[0].1);
+    let flipped = reorient(&down_info, &down[0].0, &down[0].1, UP).unwrap();
+    assert_eq!(flipped, up[0].1);
+
+    // Columns of RGB8 pixels, rows are padded to 4 bytes
+    let info = header(gl::RGB8, gl::RGB, gl::UNSIGNED_BYTE);
+    let buf = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+    let flipped = reorient(&info, &frame(2, 2), &buf, LEFT_UP).unwrap();
+    assert_eq!(flipped, [10, 11, 12, 7, 8, 9, 0, 0, 4, 5, 6, 1, 2, 3, 0, 0]);
+}
+
+#[test]
+fn test_bc1_block() {
+    let info = header(gl::COMPRESSED_RGB_S3TC_DXT1_EXT, 0, 0);
+    // Index of pixel (x, y) is x, rows are bytes
+    let block = [0x12, 0x34, 0x56, 0x78, 0xE4, 0xE4, 0xE4, 0xE4];
+    let flipped = reorient(&info, &frame(4, 4), &block, LEFT_UP).unwrap();
+    assert_eq!(flipped, [0x12, 0x34, 0x56, 0x78, 0x1B, 0x1B, 0x1B, 0x1B]);
+
+    // A 2x2 mip only uses the top left pixels of the block
+    let block = [0, 0, 0, 0, 0b0100, 0b1011, 0, 0];
+    let flipped = reorient(&info, &frame(2, 2), &block, UP).unwrap();
+    assert_eq!(flipped, [0, 0, 0, 0, 0b1011, 0b0100, 0, 0]);
+}
+
+#[test]
+fn test_etc1_block() {
+    let info = header(gl::ETC1_RGB8_OES, 0, 0);
+
+    // Individual mode, stacked subblocks, table codewords 5 and 3
+    let block = [
+        0x12,
+        0x34,
+        0x56,
+        (0b101 << 5) | (0b011 << 2) | 1,
+        0,
+        0,
+        0,
+        0,
+    ];
+    let flipped = reorient(&info, &frame(4, 4), &block, UP).unwrap();
+    assert_eq!(
+        flipped,
+        [
+            0x21,
+            0x43,
+            0x65,
+            (0b011 << 5) | (0b101 << 2) | 1,
+            0,
+            0,
+            0,
+            0
+        ]
+    );
+
+    // Differential mode with base 1 + 3 = 4
+    let block = [(1 << 3) | 3, 0, 0, 0b11, 0, 0, 0, 0];
+    let flipped = reorient(&info, &frame(4, 4), &block, UP).unwrap();
+    assert_eq!(flipped, [(4 << 3) | 5, 0, 0, 0b11, 0, 0, 0, 0]);
+
+    // Side by side subblocks are not swapped by a vertical flip, but
+    // the index of pixel (0, 0) moves to (0, 3)
+    let block = [0x12, 0x34, 0x56, 0, 0, 1, 0, 1];
+    let flipped = reorient(&info, &frame(4, 4), &block, UP).unwrap();
+    assert_eq!(flipped, [0x12, 0x34, 0x56, 0, 0, 8, 0, 8]);
+
+    // A difference of -4 cannot be negated
+    let block = [(8 << 3) | 4, 0, 0, 0b11, 0, 0, 0, 0];
+    let err = reorient(&info, &frame(4, 4), &block, UP).unwrap_err();
+    match err.kind() {
+        ErrorKind::UnflippableBlocks(x) => assert_eq!(*x, gl::ETC1_RGB8_OES),
+        e => panic!("unexpected error {:?}", e),
+    }
+}
+
+#[tokio::test]
+async fn test_compressed_roundtrip() {
+    for path in [
+        "data/khr/pattern_02_bc2.ktx",
+        "data/khr/texturearray_bc3_unorm.ktx",
+    ]
+    .iter()
+    {
+        let (info, frames) = common::decode(&read(path)).await;
+        let flipped_info = {
+            let mut info = info.clone();
+            info.key_value_data = Default::default();
+            info.key_value_data.push("KTXorientation", b"S=l,T=u\0");
+            info
+        };
+        let mut changed = false;
+        for (frame, buf) in &frames {
+            let flipped = reorient(&info, frame, buf, LEFT_UP).unwrap();
+            assert_eq!(flipped.len(), buf.len());
+            changed |= flipped != *buf;
+            let restored = reorient(&flipped_info, frame, &flipped, DOWN).unwrap();
+            assert_eq!(restored, *buf, "{} level {}", path, frame.level);
+        }
+        assert!(changed);
+    }
+}
+
+#[tokio::test]
+async fn test_etc1_file() {
+    let (info, frames) = common::decode(&read("data/khr/etc1.ktx")).await;
+    let (frame, buf) = &frames[0];
+
+    // Some blocks have base colors that cannot be swapped
+    let err = reorient(&info, frame, buf, UP).unwrap_err();
+    assert!(err.to_string().contains("ETC1"), "{}", err);
+
+    // Flipping blocks with side by side subblocks vertically and blocks
+    // with stacked subblocks horizontally never swaps the subblocks
+    let mut side_by_side = buf.clone();
+    for block in side_by_side.chunks_exact_mut(8) {
+        block[3] &= !1;
+    }
+    let flipped = reorient(&info, frame, &side_by_side, UP).unwrap();
+    assert_ne!(flipped, side_by_side);
+    let mut flipped_info = info.clone();
+    flipped_info
+        .key_value_data
+        .push("KTXorientation", b"S=r,T=u\0");
+    let restored = reorient(&flipped_info, frame, &flipped, DOWN).unwrap();
+    assert_eq!(restored, side_by_side);
+}
+
+#[tokio::test]
+async fn test_unsupported() {
+    let (info, frames) = common::decode(&read("data/khr/texturearray_astc_8x8_unorm.ktx")).await;
+    let (astc_frame, buf) = &frames[0];
+    let err = reorient(&info, astc_frame, buf, UP).unwrap_err();
+    match err.kind() {
+        ErrorKind::UnsupportedFormat(x) => assert_eq!(*x, info.gl_internal_format),
+        e => panic!("unexpected error {:?}", e),
+    }
+    // Nothing to flip
+    assert_eq!(reorient(&info, astc_frame, buf, DOWN).unwrap(), *buf);
+
+    // Pixels of the partial blocks on the bottom would need to move
+    let info = header(gl::COMPRESSED_RGB_S3TC_DXT1_EXT, 0, 0);
+    let buf = vec![0; 8 * 2 * 2];
+    let err = reorient(&info, &frame(8, 6), &buf, UP).unwrap_err();
+    match err.kind() {
+        ErrorKind::UnflippableBlocks(x) => assert_eq!(*x, gl::COMPRESSED_RGB_S3TC_DXT1_EXT),
+        e => panic!("unexpected error {:?}", e),
+    }
+    assert!(reorient(&info, &frame(8, 6), &buf, DOWN).is_ok());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
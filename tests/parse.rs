@@ -0,0 +1,143 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{parse, Decoder, ErrorKind, FrameInfo, Limits};
+use lazy_static::lazy_static;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn paths() -> Vec<std::path::PathBuf> {
+    let mut paths = vec![];
+    for dir in ["data/khr", "data/pvr"].iter() {
+        for entry in std::fs::read_dir(PROJECT_DIR.join(dir)).unwrap() {
+            paths.push(entry.unwrap().path());
+        }
+    }
+    paths.sort();
+    paths
+}
+
+fn key(frame: &FrameInfo) -> String {
+    format!("{:?}", frame)
+}
+
+/// Frames of `parse::read`, or the error of the header
+fn parse_frames(data: &[u8]) -> ktx::Result<(String, Frames)> {
+    let (header, frames) = parse::read(data, &Limits::default())?;
+    let frames = frames
+        .map(|x| x.map(|(frame, buf)| (frame, buf.to_vec())))
+        .collect::<ktx::Result<_>>()?;
+    Ok((format!("{:?}", ktx::HeaderInfo::from(header)), frames))
+}
+
+async fn async_frames(data: &[u8]) -> ktx::Result<(String, Frames)> {
+    let (info, stream) = Decoder::new(data).read_async().await?;
+    Ok((format!("{:?}", info), stream.try_collect().await?))
+}
+
+fn sync_frames(data: &[u8]) -> ktx::Result<(String, Frames)> {
+    let (info, frames) = Decoder::new(data).read()?;
+    Ok((format!("{:?}", info), frames.collect::<ktx::Result<_>>()?))
+}
+
+fn assert_same(
+    result: &ktx::Result<(String, Frames)>,
+    expected: &ktx::Result<(String, Frames)>,
+    path: &std::path::Path,
+) {
+    match (result, expected) {
+        (Ok((info, frames)), Ok((expected_info, expected_frames))) => {
+            assert_eq!(info, expected_info, "{}", path.display());
+            let keys: Vec<_> = frames.iter().map(|(frame, _)| key(frame)).collect();
+            let expected_keys: Vec<_> = expected_frames.iter().map(|(x, _)| key(x)).collect();
+            assert_eq!(keys, expected_keys, "{}", path.display());
+            for ((_, buf), (_, expected)) in frames.iter().zip(expected_frames) {
+                assert!(buf == expected, "{}", path.display());
+            }
+        }
+        (Err(e), Err(expected)) => {
+            assert_eq!(e.to_string(), expected.to_string(), "{}", path.display())
+        }
+        _ => panic!(
+            "{}: {:?} {:?}",
+            path.display(),
+            result.is_ok(),
+            expected.is_ok()
+        ),
+    }
+}
+
+#[tokio::test]
+async fn test_parse_files() {
+    for path in paths() {
+        let data = std::fs::read(&path).unwrap();
+        let expected = async_frames(&data).await;
+        assert_same(&parse_frames(&data), &expected, &path);
+        assert_same(&sync_frames(&data), &expected, &path);
+    }
+}
+
+#[tokio::test]
+async fn test_parse_truncated() {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx")).unwrap();
+    for &len in [0, 40, 70, 12356, 12358, 15436, 15500].iter() {
+        let data = &data[..len];
+        let error = |result: ktx::Result<(String, Frames)>| {
+            let e = result.unwrap_err();
+            (
+                e.is_truncation(),
+                e.offset(),
+                e.level(),
+                e.layer(),
+                e.face(),
+            )
+        };
+        let expected = error(async_frames(data).await);
+        assert_eq!(error(parse_frames(data)), expected, "{}", len);
+        assert_eq!(error(sync_frames(data)), expected, "{}", len);
+    }
+
+    // Frames before the end are yielded
+    let (_, frames) = parse::read(&data[..15500], &Limits::default()).unwrap();
+    let levels: Vec<_> = frames.map(|x| x.map(|(frame, _)| frame.level)).collect();
+    assert_eq!(levels.len(), 3);
+    assert_eq!(
+        (levels[0].as_ref().ok(), levels[1].as_ref().ok()),
+        (Some(&0), Some(&1))
+    );
+    let e = levels[2].as_ref().unwrap_err();
+    assert_eq!(e.kind().to_string(), "unexpected end of file");
+    assert_eq!((e.offset(), e.level()), (Some(15436), Some(2)));
+}
+
+#[test]
+fn test_parse_header() {
+    let data = std::fs::read(PROJECT_DIR.join("data/khr/orient-up-metadata.ktx")).unwrap();
+    let header = parse::read_header(&data, &Limits::default()).unwrap();
+    let orientation = header
+        .key_values()
+        .find(|(key, _)| *key == "KTXorientation");
+    assert_eq!(orientation.map(|(_, value)| value), Some(&b"S=r,T=u\0"[..]));
+    assert_eq!(
+        header.data_offset(),
+        64 + header.key_value_data.len() as u64
+    );
+
+    let mut data = data;
+    data[52..56].copy_from_slice(&3_u32.to_ne_bytes());
+    let e = parse::read_header(&data, &Limits::default()).unwrap_err();
+    match e.kind() {
+        ErrorKind::InvalidNumberOfFaces(3) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+    assert_eq!(e.offset(), Some(52));
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
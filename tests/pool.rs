@@ -0,0 +1,127 @@
+extern crate ktx_async as ktx;
+
+use futures_util::stream::TryStreamExt as _;
+use ktx::{BufferPool, Decoder, DecoderOptions, FrameInfo, FrameOrder};
+use lazy_static::lazy_static;
+use std::io::Cursor;
+
+type Frames = Vec<(FrameInfo, Vec<u8>)>;
+
+fn with_pool(pool: &BufferPool) -> DecoderOptions {
+    DecoderOptions {
+        buffer_pool: Some(pool.clone()),
+        ..Default::default()
+    }
+}
+
+fn keys(frames: &Frames) -> Vec<(u32, u32, u32)> {
+    frames
+        .iter()
+        .map(|(frame, _)| (frame.level, frame.layer, frame.face))
+        .collect()
+}
+
+/// Frames of a file, each handed back to the pool once read
+async fn decode_with_pool(data: &[u8], pool: &BufferPool) -> Frames {
+    let decoder = Decoder::with_options(data, with_pool(pool));
+    let (_, mut stream) = decoder.read_async().await.unwrap();
+    let mut frames = vec![];
+    while let Some((frame, buf)) = stream.try_next().await.unwrap() {
+        frames.push((frame, buf.clone()));
+        pool.put(buf);
+    }
+    frames
+}
+
+#[tokio::test]
+async fn test_pool_frames() {
+    let path = PROJECT_DIR.join("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+    let data = std::fs::read(path).unwrap();
+    let (_, stream) = Decoder::new(&data[..]).read_async().await.unwrap();
+    let expected: Frames = stream.try_collect().await.unwrap();
+
+    let pool = BufferPool::default();
+    let frames = decode_with_pool(&data, &pool).await;
+    assert_eq!(keys(&frames), keys(&expected));
+    for ((_, buf), (_, expected)) in frames.iter().zip(&expected) {
+        assert!(buf == expected);
+    }
+    // Only the first frame is allocated, the other faces reuse its buffer
+    assert_eq!(pool.stats(), (1, expected.len() as u64 - 1));
+    assert_eq!(pool.len(), 1);
+
+    // The next file reuses the buffer
+    decode_with_pool(&data, &pool).await;
+    assert_eq!(pool.stats(), (1, 2 * expected.len() as u64 - 1));
+}
+
+#[tokio::test]
+async fn test_pool_seekable_and_chunks() {
+    let path = PROJECT_DIR.join("data/khr/rgb-mipmap-reference.ktx");
+    let data = std::fs::read(path).unwrap();
+    let pool = BufferPool::default();
+
+    // Smallest first: every level is larger than the buffers pooled
+    let options = DecoderOptions {
+        frame_order: FrameOrder::SmallestFirst,
+        ..with_pool(&pool)
+    };
+    let decoder = Decoder::with_options(Cursor::new(&data[..]), options);
+    let (info, mut stream) = decoder.read_seekable_async().await.unwrap();
+    while let Some((_, buf)) = stream.try_next().await.unwrap() {
+        pool.put(buf);
+    }
+    let nlevels = u64::from(info.number_of_mipmap_levels);
+    assert_eq!(pool.stats(), (nlevels, 0));
+
+    let decoder = Decoder::with_options(&data[..], with_pool(&pool));
+    let (_, mut stream) = decoder.read_chunks_async(1024).await.unwrap();
+    let mut nchunks = 0;
+    while let Some((_, _, buf)) = stream.try_next().await.unwrap() {
+        pool.put(buf);
+        nchunks += 1;
+    }
+    assert_eq!(pool.stats(), (nlevels, nchunks));
+
+    // Largest first: every level fits in the buffer of level 0
+    let decoder = Decoder::with_options(&data[..], with_pool(&pool));
+    let (_, frames) = decoder.read().unwrap();
+    for frame in frames {
+        pool.put(frame.unwrap().1);
+    }
+    assert_eq!(pool.stats(), (nlevels, nchunks + nlevels));
+}
+
+#[test]
+fn test_pool_take() {
+    let pool = BufferPool::new(2);
+    pool.put(vec![1; 16]);
+    pool.put(Vec::with_capacity(64));
+    pool.put(vec![1; 32]);
+    // The smallest buffer is dropped
+    assert_eq!(pool.len(), 2);
+
+    let buf = pool.take(20);
+    assert_eq!(buf, vec![0; 20]);
+    assert_eq!(buf.capacity(), 32);
+    let buf = pool.take(100);
+    assert_eq!(buf.len(), 100);
+    assert_eq!(pool.stats(), (1, 1));
+    assert_eq!(pool.take(64).capacity(), 64);
+    assert!(pool.is_empty());
+
+    // Clones share the buffers
+    pool.clone().put(vec![0; 8]);
+    assert_eq!(pool.len(), 1);
+    pool.clear();
+    assert!(pool.is_empty());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
@@ -0,0 +1,135 @@
+extern crate ktx_async as ktx;
+
+mod common;
+
+use ktx::mipmap::Filter;
+use ktx::trim::{trim_async, trim_levels, TrimOptions};
+use ktx::{DecoderOptions, Limits};
+use lazy_static::lazy_static;
+
+#[tokio::test]
+async fn test_drop_levels() {
+    let path = PROJECT_DIR.join("data/khr/pattern_02_bc2.ktx");
+    let data = std::fs::read(path).unwrap();
+    let (info, frames) = common::decode(&data).await;
+    assert_eq!(info.number_of_mipmap_levels, 11);
+
+    let options = TrimOptions {
+        drop_levels: 2,
+        ..Default::default()
+    };
+    let trimmed = trim_async(&data[..], Vec::new(), &options, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(ktx::validate(&trimmed), vec![]);
+
+    let (trimmed_info, trimmed_frames) = common::decode(&trimmed).await;
+    assert_eq!(
+        (trimmed_info.pixel_width, trimmed_info.pixel_height),
+        (256, 256)
+    );
+    assert_eq!(trimmed_info.pixel_depth, 0);
+    assert_eq!(trimmed_info.number_of_mipmap_levels, 9);
+    assert_eq!(
+        trimmed_info.key_value_data.get("KTXorientation"),
+        info.key_value_data.get("KTXorientation")
+    );
+    assert!(trimmed_info.key_value_data.get("KTXorientation").is_some());
+    for ((frame, buf), (original, original_buf)) in trimmed_frames.iter().zip(&frames[2..]) {
+        assert_eq!(frame.level + 2, original.level);
+        assert_eq!(frame.pixel_width, original.pixel_width);
+        assert_eq!(buf, original_buf);
+    }
+}
+
+#[tokio::test]
+async fn test_generated_levels() {
+    // numberOfMipmapLevels is 0
+    let path = PROJECT_DIR.join("data/khr/metalplate-amg-rgba8.ktx");
+    let data = std::fs::read(path).unwrap();
+    let options = TrimOptions {
+        drop_levels: 1,
+        ..Default::default()
+    };
+    assert!(
+        trim_async(&data[..], Vec::new(), &options, Default::default())
+            .await
+            .is_err()
+    );
+
+    let decoder_options = DecoderOptions {
+        generate_mipmaps: Some(Filter::Box),
+        limits: Limits::relaxed(),
+        ..Default::default()
+    };
+    let trimmed = trim_async(&data[..], Vec::new(), &options, decoder_options)
+        .await
+        .unwrap();
+    assert_eq!(ktx::validate(&trimmed), vec![]);
+    let (info, frames) = common::decode(&trimmed).await;
+    assert_eq!((info.pixel_width, info.pixel_height), (256, 256));
+    assert_eq!(info.number_of_mipmap_levels, 9);
+    assert_eq!(frames.len(), 9);
+}
+
+#[tokio::test]
+async fn test_size_limits() {
+    let path = PROJECT_DIR.join("data/khr/pattern_02_bc2.ktx");
+    let data = std::fs::read(path).unwrap();
+    let (info, frames) = common::decode(&data).await;
+
+    let trim = |options: TrimOptions| trim_levels(&info, frames.clone(), &options).unwrap();
+
+    let (capped, _) = trim(TrimOptions {
+        max_size: Some(300),
+        ..Default::default()
+    });
+    assert_eq!(capped.pixel_width, 256);
+    assert_eq!(capped.number_of_mipmap_levels, 9);
+
+    // Without the 2x2 and 1x1 levels
+    let (stripped, stripped_frames) = trim(TrimOptions {
+        min_size: Some(4),
+        ..Default::default()
+    });
+    assert_eq!(stripped.pixel_width, 1024);
+    assert_eq!(stripped.number_of_mipmap_levels, 9);
+    assert_eq!(stripped_frames.last().unwrap().0.pixel_width, 4);
+
+    let (both, both_frames) = trim(TrimOptions {
+        drop_levels: 1,
+        max_size: Some(256),
+        min_size: Some(64),
+    });
+    assert_eq!(both.pixel_width, 256);
+    assert_eq!(both.number_of_mipmap_levels, 3);
+    assert_eq!(both_frames.len(), 3);
+
+    // The largest remaining level is kept
+    let (kept, _) = trim(TrimOptions {
+        drop_levels: 10,
+        min_size: Some(4),
+        ..Default::default()
+    });
+    assert_eq!((kept.pixel_width, kept.number_of_mipmap_levels), (1, 1));
+
+    let options = TrimOptions {
+        drop_levels: 11,
+        ..Default::default()
+    };
+    assert!(trim_levels(&info, frames.clone(), &options).is_err());
+    let options = TrimOptions {
+        max_size: Some(0),
+        ..Default::default()
+    };
+    assert!(trim_levels(&info, frames, &options).is_err());
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
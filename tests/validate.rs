@@ -0,0 +1,131 @@
+extern crate ktx_async as ktx;
+
+use ktx::validate::{Issue, Severity};
+use lazy_static::lazy_static;
+
+fn read(path: &str) -> Vec<u8> {
+    std::fs::read(PROJECT_DIR.join(path)).unwrap()
+}
+
+fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+    data[offset..offset + 4].copy_from_slice(&value.to_ne_bytes());
+}
+
+fn has(issues: &[Issue], severity: Severity, offset: u64) -> bool {
+    issues
+        .iter()
+        .any(|x| x.severity == severity && x.offset == offset)
+}
+
+#[test]
+fn test_valid_files() {
+    for path in [
+        "data/khr/rgb-reference-metadata.ktx",
+        "data/khr/cubemap_yokohama_etc2_unorm.ktx",
+        "data/khr/texturearray_bc3_unorm.ktx",
+        "data/khr/not4_rgb888_srgb.ktx",
+        "data/pvr/array-pvrtc-mipmap.ktx",
+    ]
+    .iter()
+    {
+        assert_eq!(ktx::validate(&read(path)), vec![], "{}", path);
+    }
+}
+
+#[test]
+fn test_bad_identifier() {
+    let mut data = read("data/khr/rgb-reference.ktx");
+    data[1] = b'X';
+    let issues = ktx::validate(&data);
+    assert_eq!(issues.len(), 1);
+    assert!(has(&issues, Severity::Error, 0));
+}
+
+#[test]
+fn test_key_value_data() {
+    // "KTXorientation" starts at byte 68, its value ends at byte 91
+    let data = read("data/khr/rgb-reference-metadata.ktx");
+
+    let mut reserved = data.clone();
+    reserved[71] = b'O';
+    let issues = ktx::validate(&reserved);
+    assert_eq!(issues.len(), 1);
+    assert!(has(&issues, Severity::Error, 68));
+    assert!(issues[0].message.contains("KTXOrientation"));
+
+    // KTXswizzle is only defined by KTX 2.0
+    let mut ktx2_key = data.clone();
+    ktx2_key[71..79].copy_from_slice(b"swizzle\0");
+    let issues = ktx::validate(&ktx2_key);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("KTXswizzle"));
+
+    let mut non_utf8 = data.clone();
+    non_utf8[72] = 0xFF;
+    let issues = ktx::validate(&non_utf8);
+    assert_eq!(issues.len(), 1);
+    assert!(has(&issues, Severity::Error, 68));
+
+    let mut padding = data;
+    padding[91] = 1;
+    let issues = ktx::validate(&padding);
+    assert_eq!(issues.len(), 1);
+    assert!(has(&issues, Severity::Warning, 91));
+}
+
+#[test]
+fn test_image_size() {
+    let mut data = read("data/khr/rgb-reference-metadata.ktx");
+    write_u32(&mut data, 92, 128 * 128 * 3 - 4);
+    let issues = ktx::validate(&data);
+    assert!(has(&issues, Severity::Error, 92));
+    // The 4 bytes left over are reported as well
+    assert!(has(&issues, Severity::Warning, data.len() as u64 - 4));
+}
+
+#[test]
+fn test_cubemap_dimensions() {
+    let data = read("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+
+    let mut not_square = data.clone();
+    write_u32(&mut not_square, 40, 256);
+    let issues = ktx::validate(&not_square);
+    assert!(has(&issues, Severity::Error, 40));
+
+    let mut with_depth = data;
+    write_u32(&mut with_depth, 44, 4);
+    let issues = ktx::validate(&with_depth);
+    assert!(has(&issues, Severity::Error, 44));
+}
+
+#[test]
+fn test_too_many_levels() {
+    let mut data = read("data/khr/rgb-reference.ktx");
+    write_u32(&mut data, 56, 9);
+    let issues = ktx::validate(&data);
+    assert!(has(&issues, Severity::Error, 56));
+    assert!(issues
+        .iter()
+        .any(|x| x.severity == Severity::Error && x.message == "missing level 1"));
+}
+
+#[test]
+fn test_truncated() {
+    let data = read("data/khr/cubemap_yokohama_etc2_unorm.ktx");
+    let issues = ktx::validate(&data[..data.len() - 1]);
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("truncated"));
+
+    let issues = ktx::validate(&data[..40]);
+    assert_eq!(issues.len(), 1);
+    assert!(has(&issues, Severity::Error, 0));
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}
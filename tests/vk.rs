@@ -0,0 +1,129 @@
+extern crate ktx_async as ktx;
+
+use ktx::vk::{self, BufferImageCopy, Extent3D};
+use ktx::{Decoder, ErrorKind, HeaderInfo, LevelInfo, TextureData};
+use lazy_static::lazy_static;
+
+async fn read_all(path: &str) -> (HeaderInfo, TextureData) {
+    let data = std::fs::read(PROJECT_DIR.join(path)).unwrap();
+    Decoder::new(&data[..]).read_all_async().await.unwrap()
+}
+
+fn subresource(region: &BufferImageCopy) -> (u32, u32, u32, u32) {
+    let x = region.image_subresource;
+    (
+        x.aspect_mask,
+        x.mip_level,
+        x.base_array_layer,
+        x.layer_count,
+    )
+}
+
+#[tokio::test]
+async fn test_copies_cubemap_and_array() {
+    let (info, texture) = read_all("data/khr/cubemap_yokohama_etc2_unorm.ktx").await;
+    let regions = vk::buffer_image_copies(&info, &texture.levels, 0).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].buffer_offset, 0);
+    assert_eq!(regions[0].buffer_row_length, 0);
+    assert_eq!(
+        subresource(&regions[0]),
+        (vk::IMAGE_ASPECT_COLOR_BIT, 0, 0, 6)
+    );
+    let extent = Extent3D {
+        width: 512,
+        height: 512,
+        depth: 1,
+    };
+    assert_eq!(regions[0].image_extent, extent);
+
+    let (info, texture) = read_all("data/khr/texturearray_etc2_unorm.ktx").await;
+    let regions = vk::buffer_image_copies(&info, &texture.levels, 256).unwrap();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].buffer_offset, 256);
+    assert_eq!(
+        subresource(&regions[0]),
+        (vk::IMAGE_ASPECT_COLOR_BIT, 0, 0, 7)
+    );
+
+    // ETC2 blocks are 8 bytes
+    let e = vk::buffer_image_copies(&info, &texture.levels, 4).unwrap_err();
+    assert_eq!(e.level(), Some(0));
+}
+
+#[tokio::test]
+async fn test_copies_pvrtc_levels() {
+    let (info, texture) = read_all("data/pvr/array-pvrtc-mipmap.ktx").await;
+    let regions = vk::buffer_image_copies(&info, &texture.levels, 0).unwrap();
+
+    // Levels of 8x8 pixels and more in one region, smaller ones padded
+    // to 2x2 blocks by frame
+    assert_eq!(regions.len(), 6 + 3 * 7);
+    for (level, (_, range)) in texture.levels.iter().enumerate().take(6) {
+        assert_eq!(regions[level].buffer_offset, range.start as u64);
+        assert_eq!(subresource(&regions[level]), (1, level as u32, 0, 7));
+    }
+    let (frame, range) = &texture.frames[6 * 7 + 3];
+    assert_eq!((frame.level, frame.layer), (6, 3));
+    assert_eq!(regions[6 + 3].buffer_offset, range.start as u64);
+    assert_eq!(subresource(&regions[6 + 3]), (1, 6, 3, 1));
+    assert_eq!(regions[6 + 3].image_extent.width, 4);
+}
+
+#[tokio::test]
+async fn test_copies_padded_rows() {
+    // RGB8 rows of 2 and 1 pixels are padded to 8 and 4 bytes
+    let (info, texture) = read_all("data/khr/rgb-mipmap-reference.ktx").await;
+    let regions = vk::buffer_image_copies(&info, &texture.levels[..5], 0).unwrap();
+    assert!(regions.iter().all(|x| x.buffer_row_length == 0));
+    let e = vk::buffer_image_copies(&info, &texture.levels, 0).unwrap_err();
+    assert_eq!(e.level(), Some(5));
+
+    // Packed rows can be copied for every level
+    let (data, levels) = vk::pack_rows(&info, &texture.levels, &texture.data).unwrap();
+    let regions = vk::buffer_image_copies(&info, &levels, 0).unwrap();
+    assert_eq!(regions.len(), 7);
+    assert!(regions.iter().all(|x| x.buffer_row_length == 0));
+    for (region, (level, range)) in regions.iter().zip(&levels) {
+        assert_eq!(region.buffer_offset, range.start as u64);
+        assert_eq!(region.buffer_offset % 12, 0);
+        let (width, height) = (level.pixel_width as usize, level.pixel_height as usize);
+        assert_eq!(range.len(), width * height * 3);
+        let original = texture.level(level.level).unwrap();
+        let padded_row = (width * 3 + 3) & !3;
+        for y in 0..height {
+            let row = &data[range.start + y * width * 3..][..width * 3];
+            assert_eq!(row, &original[y * padded_row..][..width * 3]);
+        }
+    }
+
+    // R8 rows of 5 pixels are padded to 8
+    let mut info = info;
+    info.gl_format = ktx::gl::RED;
+    info.gl_internal_format = ktx::gl::R8;
+    let level = LevelInfo {
+        level: 0,
+        pixel_width: 5,
+        pixel_height: 3,
+        pixel_depth: 1,
+        number_of_layers: 1,
+        number_of_faces: 1,
+        frame_size: 24,
+    };
+    let regions = vk::buffer_image_copies(&info, &[(level.clone(), 0..24)], 0).unwrap();
+    assert_eq!(regions[0].buffer_row_length, 8);
+    let e = vk::buffer_image_copies(&info, &[(level, 0..20)], 0).unwrap_err();
+    match e.kind() {
+        ErrorKind::InvalidBufferSize(24, 20) => {}
+        x => panic!("unexpected error {:?}", x),
+    }
+}
+
+lazy_static! {
+    static ref PROJECT_DIR: std::path::PathBuf = {
+        use std::env::var_os;
+        var_os("CARGO_MANIFEST_DIR")
+            .map(std::path::PathBuf::from)
+            .unwrap()
+    };
+}